@@ -0,0 +1,527 @@
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// this is the default pattern that is used when matching the benchmarks with the categories file,
+/// and the benchmark's name does not match any non-default pattern.
+pub const DEFAULT_PATTERN: &str = "*";
+
+/// this is the score that is used when matching the benchmarks with the categories file, and the benchmark's name
+/// does not match any non-default pattern, and there is no default pattern (no "*" entry).
+pub const DEFAULT_BENCHMARK_SCORE_IN_CATEGORY: f32 = 1.0;
+
+/// the patterns of a single pu type in a category, and their scores, in the order of their precedence.
+///
+/// in the categories file, the patterns are either a list of pattern entries, or a map from each pattern
+/// to its score, which is the original format of the file and only supports substring patterns. in both
+/// cases, when a benchmark matches several patterns, the pattern that comes first in the file is used.
+#[derive(Debug, Clone)]
+pub struct PatternScores {
+    patterns: Vec<BenchmarkPattern>,
+    /// whether the patterns were written as a list, so that they are written back the same way
+    written_as_list: bool,
+}
+
+/// a single pattern of the categories file, which matches the names of benchmarks, along with its score
+#[derive(Debug, Clone)]
+pub struct BenchmarkPattern {
+    /// the pattern as it is written in the categories file
+    entry: PatternEntry,
+    matcher: PatternMatcher,
+    /// the sequences that the name must not contain, in lowercase if the pattern is case insensitive
+    excluded_sequences: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum PatternMatcher {
+    /// the default pattern, which is used for the benchmarks that don't match any other pattern
+    Default,
+    /// matches names that contain all of the sequences, which are in lowercase if the pattern is case insensitive
+    Sequences(Vec<String>),
+    Regex(Regex),
+}
+
+/// a single entry of a list of patterns in the categories file. for example:
+/// `{"pattern": "Cinebench&&Multi", "except": ["R10"], "caseInsensitive": true, "score": 5}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct PatternEntry {
+    /// a pattern in the format of the pattern maps, which matches names that contain all of its
+    /// `&&` separated sequences, or the default pattern
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
+    /// a regular expression that must match some part of the name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    regex: Option<String>,
+    /// sequences that the name must not contain
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    except: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    case_insensitive: bool,
+    score: f32,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl PatternScores {
+    /// returns the patterns in the order of their precedence, including the default pattern
+    pub fn patterns(&self) -> &[BenchmarkPattern] {
+        &self.patterns
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// finds the index of the first non-default pattern that the benchmark's name matches, or None if
+    /// the benchmark should get the default score.
+    pub fn find_matching_pattern(&self, benchmark_name: &str) -> Option<usize> {
+        self.patterns
+            .iter()
+            .position(|pattern| pattern.matches(benchmark_name))
+    }
+
+    /// returns the index of the default pattern, if there is one
+    pub fn default_pattern(&self) -> Option<usize> {
+        self.patterns.iter().position(BenchmarkPattern::is_default)
+    }
+
+    /// the score of the benchmarks that don't match any non-default pattern
+    pub fn default_score(&self) -> f32 {
+        self.default_pattern()
+            .map(|index| self.patterns[index].score())
+            .unwrap_or(DEFAULT_BENCHMARK_SCORE_IN_CATEGORY)
+    }
+
+    /// the score of the benchmark with the given name, which is the score of the first pattern that it
+    /// matches, or the default score if it matches none of them
+    pub fn benchmark_score(&self, benchmark_name: &str) -> f32 {
+        match self.find_matching_pattern(benchmark_name) {
+            Some(index) => self.patterns[index].score(),
+            None => self.default_score(),
+        }
+    }
+
+    pub fn set_score(&mut self, index: usize, score: f32) {
+        self.patterns[index].entry.score = score;
+    }
+
+    /// adds a default pattern with the given score, and returns its index. there must not already be one.
+    pub fn add_default_pattern(&mut self, score: f32) -> usize {
+        self.patterns.push(BenchmarkPattern {
+            entry: PatternEntry {
+                pattern: Some(DEFAULT_PATTERN.to_string()),
+                regex: None,
+                except: Vec::new(),
+                case_insensitive: false,
+                score,
+            },
+            matcher: PatternMatcher::Default,
+            excluded_sequences: Vec::new(),
+        });
+        self.patterns.len() - 1
+    }
+}
+
+impl BenchmarkPattern {
+    /// creates a pattern from an entry of the categories file, or describes why the entry is invalid
+    fn from_entry(entry: PatternEntry) -> Result<Self, String> {
+        let matcher = match (&entry.pattern, &entry.regex) {
+            (Some(pattern), None) if pattern == DEFAULT_PATTERN => {
+                if !entry.except.is_empty() || entry.case_insensitive {
+                    return Err("the default pattern can't have exceptions or be case insensitive".to_string());
+                }
+                PatternMatcher::Default
+            }
+            (Some(pattern), None) => PatternMatcher::Sequences(
+                pattern
+                    .split("&&")
+                    .map(|sequence| Self::normalize_case(sequence, entry.case_insensitive))
+                    .collect(),
+            ),
+            (None, Some(regex)) => PatternMatcher::Regex(
+                RegexBuilder::new(regex)
+                    .case_insensitive(entry.case_insensitive)
+                    .build()
+                    .map_err(|error| format!("invalid regex {}: {}", regex, error))?,
+            ),
+            _ => return Err("each pattern entry must have either a pattern or a regex".to_string()),
+        };
+        let excluded_sequences = entry
+            .except
+            .iter()
+            .map(|sequence| Self::normalize_case(sequence, entry.case_insensitive))
+            .collect();
+        Ok(Self {
+            entry,
+            matcher,
+            excluded_sequences,
+        })
+    }
+
+    fn normalize_case(text: &str, case_insensitive: bool) -> String {
+        if case_insensitive {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        matches!(self.matcher, PatternMatcher::Default)
+    }
+
+    pub fn score(&self) -> f32 {
+        self.entry.score
+    }
+
+    /// the text of the pattern or of the regex, as it is written in the categories file
+    pub fn text(&self) -> &str {
+        self.entry
+            .pattern
+            .as_deref()
+            .or(self.entry.regex.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// checks if the given benchmark name matches this pattern. the default pattern doesn't match any
+    /// name, since it is only used when no other pattern matches.
+    pub fn matches(&self, benchmark_name: &str) -> bool {
+        let benchmark_name = Self::normalize_case(benchmark_name, self.entry.case_insensitive);
+        let included = match &self.matcher {
+            PatternMatcher::Default => return false,
+            PatternMatcher::Sequences(required_sequences) => required_sequences
+                .iter()
+                .all(|required_sequence| benchmark_name.contains(required_sequence.as_str())),
+            PatternMatcher::Regex(regex) => regex.is_match(&benchmark_name),
+        };
+        included
+            && !self
+                .excluded_sequences
+                .iter()
+                .any(|excluded_sequence| benchmark_name.contains(excluded_sequence.as_str()))
+    }
+}
+
+/// describes the pattern the way it is written in the categories file, for example
+/// `"Cinebench" except "R10"` or `/^Geekbench [45]/ (case insensitive)`
+impl fmt::Display for BenchmarkPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.entry.regex {
+            Some(regex) => write!(f, "/{}/", regex)?,
+            None => write!(f, "\"{}\"", self.text())?,
+        }
+        if !self.entry.except.is_empty() {
+            let excluded_sequences: Vec<String> = self
+                .entry
+                .except
+                .iter()
+                .map(|excluded_sequence| format!("\"{}\"", excluded_sequence))
+                .collect();
+            write!(f, " except {}", excluded_sequences.join(", "))?;
+        }
+        if self.entry.case_insensitive {
+            write!(f, " (case insensitive)")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for PatternScores {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PatternScoresVisitor;
+        impl<'de> Visitor<'de> for PatternScoresVisitor {
+            type Value = PatternScores;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a list of pattern entries, or a map from patterns to scores")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut patterns = Vec::new();
+                while let Some(entry) = seq.next_element::<PatternEntry>()? {
+                    patterns.push(BenchmarkPattern::from_entry(entry).map_err(de::Error::custom)?);
+                }
+                Ok(PatternScores {
+                    patterns,
+                    written_as_list: true,
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut patterns: Vec<BenchmarkPattern> = Vec::new();
+                while let Some((pattern, score)) = map.next_entry::<String, f32>()? {
+                    // like when the map was deserialized into a hashmap, the last score of a repeated
+                    // pattern is used
+                    if let Some(existing_pattern) = patterns.iter_mut().find(|existing| existing.text() == pattern) {
+                        existing_pattern.entry.score = score;
+                        continue;
+                    }
+                    let entry = PatternEntry {
+                        pattern: Some(pattern),
+                        regex: None,
+                        except: Vec::new(),
+                        case_insensitive: false,
+                        score,
+                    };
+                    patterns.push(BenchmarkPattern::from_entry(entry).map_err(de::Error::custom)?);
+                }
+                Ok(PatternScores {
+                    patterns,
+                    written_as_list: false,
+                })
+            }
+        }
+        deserializer.deserialize_any(PatternScoresVisitor)
+    }
+}
+
+impl Serialize for PatternScores {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.written_as_list {
+            let mut seq = serializer.serialize_seq(Some(self.patterns.len()))?;
+            for pattern in &self.patterns {
+                seq.serialize_element(&pattern.entry)?;
+            }
+            seq.end()
+        } else {
+            let mut map = serializer.serialize_map(Some(self.patterns.len()))?;
+            for pattern in &self.patterns {
+                map.serialize_entry(pattern.text(), &pattern.entry.score)?;
+            }
+            map.end()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::{BTreeSet, HashMap};
+
+    fn pattern_scores(patterns: serde_json::Value) -> PatternScores {
+        serde_json::from_value(patterns).unwrap()
+    }
+
+    /// the error of deserializing the given patterns
+    fn deserialization_error(patterns: serde_json::Value) -> String {
+        serde_json::from_value::<PatternScores>(patterns).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn pattern_lists_are_deserialized() {
+        let scores = pattern_scores(json!([
+            {"pattern": "Cinebench&&Multi", "except": ["R10", "R11.5"], "score": 5},
+            {"regex": "^Geekbench [45]", "score": 4},
+            {"pattern": "kraken", "caseInsensitive": true, "score": 3},
+            {"pattern": "*", "score": 0.5}
+        ]));
+        assert_eq!(scores.patterns().len(), 4);
+        assert_eq!(scores.default_pattern(), Some(3));
+        assert_eq!(scores.default_score(), 0.5);
+        assert_eq!(scores.patterns()[0].to_string(), "\"Cinebench&&Multi\" except \"R10\", \"R11.5\"");
+        assert_eq!(scores.patterns()[1].to_string(), "/^Geekbench [45]/");
+        assert_eq!(scores.patterns()[2].to_string(), "\"kraken\" (case insensitive)");
+    }
+
+    #[test]
+    fn pattern_maps_are_deserialized() {
+        // json! sorts the keys of maps, so the map is parsed from a string to keep its order
+        let scores: PatternScores = serde_json::from_str(r#"{"Cinebench&&Multi": 5, "*": 2, "Geekbench": 7}"#).unwrap();
+        let patterns: Vec<(&str, f32)> = scores.patterns().iter().map(|pattern| (pattern.text(), pattern.score())).collect();
+        assert_eq!(patterns, vec![("Cinebench&&Multi", 5.0), ("*", 2.0), ("Geekbench", 7.0)]);
+        assert_eq!(scores.benchmark_score("Cinebench R23 CPU (Multi Core)"), 5.0);
+        assert_eq!(scores.benchmark_score("Cinebench R23 CPU (Single Core)"), 2.0);
+        // map patterns are case sensitive, and have no exceptions
+        assert_eq!(scores.benchmark_score("geekbench 5"), 2.0);
+        assert_eq!(scores.benchmark_score("Geekbench 5 R10"), 7.0);
+    }
+
+    #[test]
+    fn the_last_score_of_a_repeated_map_pattern_is_used() {
+        let scores: PatternScores = serde_json::from_str(r#"{"Geekbench": 7, "Blender": 3, "Geekbench": 2}"#).unwrap();
+        assert_eq!(scores.patterns().len(), 2);
+        assert_eq!(scores.benchmark_score("Geekbench 5"), 2.0);
+    }
+
+    #[test]
+    fn the_first_matching_pattern_is_used() {
+        let scores = pattern_scores(json!([
+            {"pattern": "Cinebench&&Multi", "score": 5},
+            {"pattern": "*", "score": 0.5},
+            {"pattern": "Cinebench", "score": 3}
+        ]));
+        assert_eq!(scores.find_matching_pattern("Cinebench R23 CPU (Multi Core)"), Some(0));
+        assert_eq!(scores.benchmark_score("Cinebench R23 CPU (Multi Core)"), 5.0);
+        // the default pattern never matches, wherever it is in the list
+        assert_eq!(scores.find_matching_pattern("Cinebench R23 CPU (Single Core)"), Some(2));
+        assert_eq!(scores.benchmark_score("Cinebench R23 CPU (Single Core)"), 3.0);
+        assert_eq!(scores.find_matching_pattern("Blender"), None);
+        assert_eq!(scores.benchmark_score("Blender"), 0.5);
+
+        let reordered = pattern_scores(json!([
+            {"pattern": "Cinebench", "score": 3},
+            {"pattern": "Cinebench&&Multi", "score": 5}
+        ]));
+        assert_eq!(reordered.benchmark_score("Cinebench R23 CPU (Multi Core)"), 3.0);
+        // without a default pattern, the default score is used
+        assert_eq!(reordered.benchmark_score("Blender"), DEFAULT_BENCHMARK_SCORE_IN_CATEGORY);
+    }
+
+    #[test]
+    fn sequence_patterns_require_all_sequences() {
+        let scores = pattern_scores(json!([{"pattern": "Cinebench&&Multi", "score": 1}]));
+        let pattern = &scores.patterns()[0];
+        assert!(pattern.matches("Cinebench R15 CPU Multi 64 Bit"));
+        assert!(!pattern.matches("Cinebench R15 CPU Single 64 Bit"));
+        assert!(!pattern.matches("cinebench r15 cpu multi 64 bit"));
+    }
+
+    #[test]
+    fn regex_patterns_match_any_part_of_the_name() {
+        let scores = pattern_scores(json!([{"regex": "Geekbench [45][.]\\d", "score": 1}]));
+        let pattern = &scores.patterns()[0];
+        assert!(pattern.matches("Geekbench 4.0 64 Bit Single-Core"));
+        assert!(pattern.matches("Geekbench 5.1 - 5.4 64 Bit Multi-Core"));
+        assert!(!pattern.matches("Geekbench 3.0"));
+        assert!(!pattern.matches("geekbench 5.0"));
+    }
+
+    #[test]
+    fn excluded_sequences_prevent_matching() {
+        let scores = pattern_scores(json!([
+            {"pattern": "Cinebench", "except": ["R10", "R11.5"], "score": 5},
+            {"regex": "^3DMark", "except": ["Physics"], "score": 4}
+        ]));
+        let pattern = &scores.patterns()[0];
+        assert!(pattern.matches("Cinebench R23 CPU (Multi Core)"));
+        assert!(!pattern.matches("Cinebench R10 Rend. Multi (64bit)"));
+        assert!(!pattern.matches("Cinebench R11.5 CPU Multi 64 Bit"));
+        let regex_pattern = &scores.patterns()[1];
+        assert!(regex_pattern.matches("3DMark Time Spy CPU"));
+        assert!(!regex_pattern.matches("3DMark Fire Strike Standard Physics"));
+    }
+
+    #[test]
+    fn case_insensitive_patterns_ignore_case() {
+        let scores = pattern_scores(json!([
+            {"pattern": "MOZILLA kraken", "except": ["V1.0"], "caseInsensitive": true, "score": 5},
+            {"regex": "^octane", "caseInsensitive": true, "score": 4}
+        ]));
+        let pattern = &scores.patterns()[0];
+        assert!(pattern.matches("Mozilla Kraken 1.1"));
+        assert!(!pattern.matches("Mozilla Kraken v1.0"));
+        assert!(scores.patterns()[1].matches("Octane V2 Total Score"));
+    }
+
+    #[test]
+    fn invalid_pattern_entries_are_rejected() {
+        assert!(deserialization_error(json!([{"regex": "Geekbench (5", "score": 1}]))
+            .starts_with("invalid regex Geekbench (5"));
+        assert!(deserialization_error(json!([{"pattern": "Geekbench", "regex": "Geekbench", "score": 1}]))
+            .starts_with("each pattern entry must have either a pattern or a regex"));
+        assert!(deserialization_error(json!([{"score": 1}]))
+            .starts_with("each pattern entry must have either a pattern or a regex"));
+        assert!(deserialization_error(json!([{"pattern": "*", "except": ["R10"], "score": 1}]))
+            .starts_with("the default pattern can't have exceptions or be case insensitive"));
+        assert!(deserialization_error(json!([{"pattern": "Geekbench", "caseInsenstive": true, "score": 1}]))
+            .contains("unknown field `caseInsenstive`"));
+        assert!(deserialization_error(json!([{"pattern": "Geekbench"}])).contains("missing field `score`"));
+        assert!(deserialization_error(json!("Geekbench")).contains("a list of pattern entries, or a map"));
+    }
+
+    #[test]
+    fn patterns_are_serialized_in_their_original_format() {
+        let list = json!([
+            {"pattern": "Cinebench", "except": ["R10"], "caseInsensitive": true, "score": 5.0},
+            {"regex": "^Geekbench", "score": 4.0}
+        ]);
+        assert_eq!(serde_json::to_value(pattern_scores(list.clone())).unwrap(), list);
+        let map = json!({"Cinebench&&Multi": 5.0, "*": 2.0});
+        assert_eq!(serde_json::to_value(pattern_scores(map.clone())).unwrap(), map);
+    }
+
+    /// the names of all cpu and gpu benchmarks in the laptops files of the repository
+    fn benchmark_names_in_laptops_files() -> (BTreeSet<String>, BTreeSet<String>) {
+        let laptops_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("laptops");
+        let mut cpu_benchmark_names = BTreeSet::new();
+        let mut gpu_benchmark_names = BTreeSet::new();
+        for laptops_file in std::fs::read_dir(laptops_dir).unwrap() {
+            let laptops: Vec<serde_json::Value> =
+                serde_json::from_reader(std::fs::File::open(laptops_file.unwrap().path()).unwrap()).unwrap();
+            for laptop in laptops {
+                cpu_benchmark_names.extend(laptop["cpu_bench"].as_object().unwrap().keys().cloned());
+                gpu_benchmark_names.extend(laptop["gpu_bench"].as_object().unwrap().keys().cloned());
+            }
+        }
+        (cpu_benchmark_names, gpu_benchmark_names)
+    }
+
+    /// the scores that the benchmark could get from the pattern maps, as they were matched before the patterns
+    /// were ordered. a benchmark that matched several patterns got the score of the first of them in the order
+    /// of a hashmap, so it could get any of their scores.
+    fn scores_before_ordered_patterns(benchmark_name: &str, patterns_and_scores: &HashMap<String, f32>) -> Vec<f32> {
+        let matching_scores: Vec<f32> = patterns_and_scores
+            .iter()
+            .filter(|(pattern, _)| *pattern != DEFAULT_PATTERN)
+            .filter(|(pattern, _)| pattern.split("&&").all(|sequence| benchmark_name.contains(sequence)))
+            .map(|(_, score)| *score)
+            .collect();
+        if matching_scores.is_empty() {
+            vec![*patterns_and_scores
+                .get(DEFAULT_PATTERN)
+                .unwrap_or(&DEFAULT_BENCHMARK_SCORE_IN_CATEGORY)]
+        } else {
+            matching_scores
+        }
+    }
+
+    #[test]
+    fn the_categories_file_gives_the_same_scores_as_before() {
+        #[derive(Deserialize)]
+        struct LegacyCategoryPatternScores {
+            cpu: HashMap<String, f32>,
+            gpu: HashMap<String, f32>,
+        }
+        #[derive(Deserialize)]
+        struct CategoryPatternScores {
+            cpu: PatternScores,
+            gpu: PatternScores,
+        }
+
+        let categories_file_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("categories.json");
+        let categories_file = std::fs::read_to_string(categories_file_path).unwrap();
+        let legacy_categories: HashMap<String, LegacyCategoryPatternScores> =
+            serde_json::from_str(&categories_file).unwrap();
+        let categories: HashMap<String, CategoryPatternScores> = serde_json::from_str(&categories_file).unwrap();
+        let (cpu_benchmark_names, gpu_benchmark_names) = benchmark_names_in_laptops_files();
+        assert!(!cpu_benchmark_names.is_empty() && !gpu_benchmark_names.is_empty());
+
+        for (category_name, legacy_category) in &legacy_categories {
+            let category = &categories[category_name];
+            for (benchmark_names, legacy_patterns_and_scores, pattern_scores) in [
+                (&cpu_benchmark_names, &legacy_category.cpu, &category.cpu),
+                (&gpu_benchmark_names, &legacy_category.gpu, &category.gpu),
+            ] {
+                for benchmark_name in benchmark_names {
+                    let scores_before = scores_before_ordered_patterns(benchmark_name, legacy_patterns_and_scores);
+                    let score = pattern_scores.benchmark_score(benchmark_name);
+                    assert!(
+                        scores_before.contains(&score),
+                        "the score of {} in {} is {}, but it was one of {:?}",
+                        benchmark_name,
+                        category_name,
+                        score,
+                        scores_before
+                    );
+                }
+            }
+        }
+    }
+}
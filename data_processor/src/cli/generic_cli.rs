@@ -16,7 +16,9 @@ impl<T:CliCommandEnum> Cli<T>{
             line: String::new(),
         }
     }
-    pub fn get_next_command(&mut self) -> T{
+    /// reads lines until one of them starts with the name of a command, and returns the command along
+    /// with the whitespace separated arguments that follow its name
+    pub fn get_next_command(&mut self) -> (T, Vec<String>){
         loop{
             print!(">>> ");
             self.stdout.flush().unwrap();
@@ -34,13 +36,29 @@ impl<T:CliCommandEnum> Cli<T>{
             if self.line == "help"{
                 self.print_help();
             } else{
-                match self.commands.get(&self.line){
-                    Some(command) => return *command,
+                match self.find_command(){
+                    Some((command, arguments)) => return (command, arguments),
                     None => println!("unknown command"),
                 }
             }
         }
     }
+    /// finds the command with the longest name that the line starts with
+    fn find_command(&self) -> Option<(T, Vec<String>)>{
+        let (command_name, command) = self.commands
+            .iter()
+            .filter(|(command_name, _)| {
+                self.line == **command_name
+                    || self.line.starts_with(command_name.as_str())
+                        && self.line[command_name.len()..].starts_with(char::is_whitespace)
+            })
+            .max_by_key(|(command_name, _)| command_name.len())?;
+        let arguments = self.line[command_name.len()..]
+            .split_whitespace()
+            .map(|argument| argument.to_string())
+            .collect();
+        Some((*command, arguments))
+    }
     fn print_help(&self){
         println!("available commands:");
         for command_name in self.commands.keys(){
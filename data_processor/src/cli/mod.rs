@@ -1,27 +1,233 @@
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
+
 use self::generic_cli::{Cli, CliCommandEnum};
+use crate::errors::{self, DataProcessorErrorKind};
+use crate::input_paths::InputPaths;
 use maplit::hashmap;
 
 mod generic_cli;
 
-pub use generic_cli::*;
+/// describes how the data processor can be started
+pub const USAGE: &str = "usage: data-processor [<command> [<argument>]] [--laptops-dir <dir>] [--categories <file>] [--exchange-rates <file>] [--reports-dir <dir>]
+
+when a command is given, it is executed once, and the data processor exits with a non-zero status if it fails.
+otherwise, the interactive shell is started, and its commands use the given paths.
+
+commands:
+\tload-laptops
+\tupdate-laptops
+\tvalidate-laptops
+\tload-categories
+\tlint-categories
+\tcalculate-scores
+\tadd-category <category>
+\tupdate-category <category>
+\tremove-category <category>
+\treload-all
+\treload-dry-run
+\tfit-category-weights
+\tlist-generations
+\tactivate-generation <generation>
+\trollback-generation
+\tprune-generations [<kept generations>]";
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum DataProcessorCliCommand{
     Exit,
     LoadCategories,
     LoadLaptops,
+    UpdateLaptops,
     CalculateScores,
     ReloadAll,
+    FitCategoryWeights,
+    ListGenerations,
+    ActivateGeneration,
+    RollbackGeneration,
+    PruneGenerations,
+    AddCategory,
+    UpdateCategory,
+    RemoveCategory,
+    ReloadDryRun,
+    ValidateLaptops,
+    LintCategories,
 }
 
 impl CliCommandEnum for DataProcessorCliCommand{}
 
-pub fn create_data_processor_cli()->Cli<DataProcessorCliCommand>{
-    Cli::with_commands(hashmap!{
+impl DataProcessorCliCommand{
+    /// whether the command replaces large parts of the dataset, in which case it modifies a new generation of
+    /// the dataset which is activated once the command succeeds
+    pub fn creates_generation(self)->bool{
+        matches!(
+            self,
+            DataProcessorCliCommand::LoadCategories
+                | DataProcessorCliCommand::LoadLaptops
+                | DataProcessorCliCommand::CalculateScores
+                | DataProcessorCliCommand::ReloadAll
+        )
+    }
+
+    /// whether the command makes small changes to the dataset, in which case it modifies the active generation in
+    /// place, since copying the whole dataset into a new generation would take longer than the changes themselves
+    pub fn modifies_active_generation(self)->bool{
+        matches!(
+            self,
+            DataProcessorCliCommand::UpdateLaptops
+                | DataProcessorCliCommand::AddCategory
+                | DataProcessorCliCommand::UpdateCategory
+                | DataProcessorCliCommand::RemoveCategory
+        )
+    }
+
+    /// the name of the single argument that the command accepts, if it accepts one
+    pub fn argument_name(self)->Option<&'static str>{
+        match self{
+            DataProcessorCliCommand::ActivateGeneration => Some("generation"),
+            DataProcessorCliCommand::PruneGenerations => Some("kept generations"),
+            DataProcessorCliCommand::AddCategory
+            | DataProcessorCliCommand::UpdateCategory
+            | DataProcessorCliCommand::RemoveCategory => Some("category"),
+            _ => None,
+        }
+    }
+
+    /// whether the argument of the command may contain spaces, in which case all the words that follow
+    /// the command's name make up its argument
+    fn argument_may_contain_spaces(self)->bool{
+        self.argument_name() == Some("category")
+    }
+
+    /// the name of the command in the interactive shell
+    pub fn name(self)->String{
+        data_processor_commands()
+            .into_iter()
+            .find(|(_, command)| *command == self)
+            .map(|(command_name, _)| command_name)
+            .unwrap()
+    }
+
+    /// makes sure that the command wasn't given more arguments than it accepts
+    pub fn validate_arguments(self, arguments: &[String])->errors::Result<()>{
+        if self.argument_may_contain_spaces(){
+            return Ok(());
+        }
+        let max_arguments = if self.argument_name().is_some() { 1 } else { 0 };
+        match arguments.get(max_arguments){
+            Some(value) => Err(DataProcessorErrorKind::UnexpectedCommandArgument{ value: value.clone() }
+                .into_empty_data_processor_error()),
+            None => Ok(()),
+        }
+    }
+
+    /// parses the argument of the command, or returns None if it wasn't given
+    pub fn parse_argument<A: FromStr>(self, arguments: &[String])->errors::Result<Option<A>>{
+        self.validate_arguments(arguments)?;
+        let argument_name = match self.argument_name(){
+            Some(argument_name) => argument_name,
+            None => return Ok(None),
+        };
+        let value = if self.argument_may_contain_spaces(){
+            Some(arguments.join(" ")).filter(|value| !value.is_empty())
+        } else{
+            arguments.first().cloned()
+        };
+        value
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    DataProcessorErrorKind::InvalidCommandArgument{
+                        name: argument_name.to_string(),
+                        value,
+                    }
+                    .into_empty_data_processor_error()
+                })
+            })
+            .transpose()
+    }
+
+    /// parses the argument of the command, which must be given
+    pub fn parse_required_argument<A: FromStr>(self, arguments: &[String])->errors::Result<A>{
+        self.parse_argument(arguments)?.ok_or_else(|| {
+            DataProcessorErrorKind::MissingCommandArgument{
+                name: self.argument_name().unwrap_or_default().to_string(),
+            }
+            .into_empty_data_processor_error()
+        })
+    }
+}
+
+/// the commands of the interactive shell, mapped by their names
+fn data_processor_commands()->HashMap<String, DataProcessorCliCommand>{
+    hashmap!{
         "exit".to_string() => DataProcessorCliCommand::Exit,
         "load categories".to_string() => DataProcessorCliCommand::LoadCategories,
         "load laptops".to_string() => DataProcessorCliCommand::LoadLaptops,
+        "update laptops".to_string() => DataProcessorCliCommand::UpdateLaptops,
         "calculate scores".to_string() => DataProcessorCliCommand::CalculateScores,
         "reload all".to_string() => DataProcessorCliCommand::ReloadAll,
-    })
-}
\ No newline at end of file
+        "fit category weights".to_string() => DataProcessorCliCommand::FitCategoryWeights,
+        "list generations".to_string() => DataProcessorCliCommand::ListGenerations,
+        "activate generation".to_string() => DataProcessorCliCommand::ActivateGeneration,
+        "rollback generation".to_string() => DataProcessorCliCommand::RollbackGeneration,
+        "prune generations".to_string() => DataProcessorCliCommand::PruneGenerations,
+        "add category".to_string() => DataProcessorCliCommand::AddCategory,
+        "update category".to_string() => DataProcessorCliCommand::UpdateCategory,
+        "remove category".to_string() => DataProcessorCliCommand::RemoveCategory,
+        "reload dry run".to_string() => DataProcessorCliCommand::ReloadDryRun,
+        "validate laptops".to_string() => DataProcessorCliCommand::ValidateLaptops,
+        "lint categories".to_string() => DataProcessorCliCommand::LintCategories,
+    }
+}
+
+pub fn create_data_processor_cli()->Cli<DataProcessorCliCommand>{
+    Cli::with_commands(data_processor_commands())
+}
+
+/// the arguments that the data processor was started with
+#[derive(Debug, Default)]
+pub struct CommandLineArguments{
+    /// the command that should be executed once, or None if the interactive shell should be started
+    pub command: Option<DataProcessorCliCommand>,
+    /// the arguments that follow the command
+    pub command_arguments: Vec<String>,
+    pub input_paths: InputPaths,
+    /// whether the usage should be printed instead of doing anything else
+    pub help: bool,
+}
+
+/// parses the command line arguments, not including the name of the executable. the names of the
+/// commands are the names of the shell's commands, with dashes instead of spaces.
+pub fn parse_command_line_arguments(arguments: impl IntoIterator<Item=String>)->Result<CommandLineArguments, String>{
+    let mut parsed_arguments = CommandLineArguments::default();
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next(){
+        let path_argument = match argument.as_str(){
+            "--help" | "-h" | "help" => {
+                parsed_arguments.help = true;
+                continue;
+            }
+            "--laptops-dir" => &mut parsed_arguments.input_paths.laptops_dir,
+            "--categories" => &mut parsed_arguments.input_paths.categories_file,
+            "--exchange-rates" => &mut parsed_arguments.input_paths.exchange_rates_file,
+            "--reports-dir" => &mut parsed_arguments.input_paths.reports_dir,
+            flag if flag.starts_with('-') => return Err(format!("unknown flag: {}", flag)),
+            command_name => {
+                if parsed_arguments.command.is_some(){
+                    // the command validates its own arguments
+                    parsed_arguments.command_arguments.push(command_name.to_string());
+                    continue;
+                }
+                // exiting only makes sense in the interactive shell
+                let command = data_processor_commands()
+                    .get(&command_name.replace('-', " "))
+                    .copied()
+                    .filter(|command| *command != DataProcessorCliCommand::Exit)
+                    .ok_or_else(|| format!("unknown command: {}", command_name))?;
+                parsed_arguments.command = Some(command);
+                continue;
+            }
+        };
+        let path = arguments.next().ok_or_else(|| format!("missing value for {}", argument))?;
+        *path_argument = PathBuf::from(path);
+    }
+    Ok(parsed_arguments)
+}
@@ -8,34 +8,44 @@ use diesel::prelude::*;
 use diesel::PgConnection;
 
 /// maps the benchmarks by the laptop id, and then by global benchmark id
-type MappedBenchmarks = HashMap<i32, BenchmarksByGlobalBenchmarkId>;
+pub(super) type MappedBenchmarks = HashMap<i32, BenchmarksByGlobalBenchmarkId>;
 
 /// maps the benchmarks by global benchmark id
-type BenchmarksByGlobalBenchmarkId = HashMap<i32, f32>;
+pub(super) type BenchmarksByGlobalBenchmarkId = HashMap<i32, f32>;
 
 /// maps the benchmark scores in categories by category id, and then by global benchmark id
-type MappedBenchmarkScoresInCategories =
+pub(super) type MappedBenchmarkScoresInCategories =
     HashMap<i32, BenchmarkScoresInCategoriesByGlobalBenchmarkId>;
 
 /// maps the benchmark scores in categories by global benchmark id
-type BenchmarkScoresInCategoriesByGlobalBenchmarkId = HashMap<i32, f32>;
+pub(super) type BenchmarkScoresInCategoriesByGlobalBenchmarkId = HashMap<i32, f32>;
 
 /// this is information about each global benchmark that is required for calculating the laptop's score
-struct GlobalBenchmarkInfo {
+pub(super) struct GlobalBenchmarkInfo {
     max: f32,
     sum: BigDecimal,
     amount: i64,
 }
 impl GlobalBenchmarkInfo {
-    fn average(&self) -> f32 {
+    pub(super) fn average(&self) -> f32 {
         // it is safe to unwrap here since, if the max can be stored as an f32, then the average must be
         // in the range of f32
         (&self.sum / self.amount).to_f32().unwrap()
     }
+
+    /// normalizes a score in this benchmark to the range between 0 and 1, by dividing it by the max score
+    pub(super) fn normalize(&self, score_in_benchmark: f32) -> f32 {
+        // when calculating the normalized score, make sure we don't divide by zero
+        if self.max == 0.0 {
+            0.0
+        } else {
+            score_in_benchmark / self.max
+        }
+    }
 }
 
 /// maps the global benchmarks by global benchmark id
-type MappedGlobalBenchmarks = HashMap<i32, GlobalBenchmarkInfo>;
+pub(super) type MappedGlobalBenchmarks = HashMap<i32, GlobalBenchmarkInfo>;
 
 /// calculates the scores of each laptop in each category and caches it.
 pub fn calculate_scores(db_connection: &PgConnection) -> Result<()> {
@@ -55,6 +65,7 @@ pub fn calculate_scores(db_connection: &PgConnection) -> Result<()> {
         &mapped_benchmark_scores_in_categories,
         &mapped_global_benchmarks,
     );
+    verify_scores_are_finite(&new_laptop_scores_in_categories)?;
 
     println!("inserting scores into the database...");
     // save the scores to the database
@@ -64,27 +75,100 @@ pub fn calculate_scores(db_connection: &PgConnection) -> Result<()> {
     Ok(())
 }
 
+/// recalculates the scores of the given laptops in each category, without touching the scores of
+/// other laptops. this is only correct if the global benchmarks that the other laptops' scores were
+/// calculated with didn't change.
+pub(super) fn calculate_scores_of_laptops(db_connection: &PgConnection, laptop_ids: &[i32]) -> Result<()> {
+    use schema::laptop_score_in_category;
+
+    let benchmarks: Vec<models::Benchmark> = {
+        use schema::benchmark::dsl::*;
+        benchmark
+            .filter(laptop_id.eq_any(laptop_ids))
+            .load(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+    };
+    let mapped_benchmarks = map_benchmarks(benchmarks);
+    let mapped_global_benchmarks = load_and_map_global_benchmarks(db_connection)?;
+    let mapped_benchmark_scores_in_categories =
+        load_and_map_benchmark_scores_in_categories(db_connection)?;
+
+    let new_laptop_scores_in_categories = calculate_laptop_scores_in_each_category(
+        &mapped_benchmarks,
+        &mapped_benchmark_scores_in_categories,
+        &mapped_global_benchmarks,
+    );
+    verify_scores_are_finite(&new_laptop_scores_in_categories)?;
+
+    diesel::delete(
+        laptop_score_in_category::table.filter(laptop_score_in_category::laptop_id.eq_any(laptop_ids)),
+    )
+    .execute(db_connection)
+    .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::insert_into(laptop_score_in_category::table)
+        .values(&new_laptop_scores_in_categories)
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
+}
+
+/// recalculates the scores of all laptops in a single category, without touching the scores in other
+/// categories. the scores aren't adjusted by `weight × (new score − old score)`, since the weights of the
+/// category are normalized by their sum, so changing any of its patterns changes all of its weights, and
+/// every score in the category would be adjusted anyway.
+pub(super) fn calculate_scores_in_category(db_connection: &PgConnection, category_id: i32) -> Result<()> {
+    use schema::laptop_score_in_category;
+
+    let mapped_benchmarks = load_and_map_benchmarks(db_connection)?;
+    let mapped_global_benchmarks = load_and_map_global_benchmarks(db_connection)?;
+    let mut mapped_benchmark_scores_in_categories =
+        load_and_map_benchmark_scores_in_categories(db_connection)?;
+    mapped_benchmark_scores_in_categories.retain(|id, _| *id == category_id);
+
+    let new_laptop_scores_in_category = calculate_laptop_scores_in_each_category(
+        &mapped_benchmarks,
+        &mapped_benchmark_scores_in_categories,
+        &mapped_global_benchmarks,
+    );
+    verify_scores_are_finite(&new_laptop_scores_in_category)?;
+
+    diesel::delete(
+        laptop_score_in_category::table.filter(laptop_score_in_category::category_id.eq(category_id)),
+    )
+    .execute(db_connection)
+    .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::insert_into(laptop_score_in_category::table)
+        .values(&new_laptop_scores_in_category)
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
+}
+
 /// loads all benchmarks and maps them by global benchmark id and then by laptop id
-fn load_and_map_benchmarks(db_connection: &PgConnection) -> Result<MappedBenchmarks> {
+pub(super) fn load_and_map_benchmarks(db_connection: &PgConnection) -> Result<MappedBenchmarks> {
     let benchmarks: Vec<models::Benchmark> = {
         use schema::benchmark::dsl::*;
         benchmark
             .load(db_connection)
             .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
     };
+    Ok(map_benchmarks(benchmarks))
+}
 
+/// maps the given benchmarks by laptop id and then by global benchmark id
+fn map_benchmarks(benchmarks: Vec<models::Benchmark>) -> MappedBenchmarks {
     let mut result = MappedBenchmarks::new();
     for benchmark in benchmarks {
         result
             .entry(benchmark.laptop_id)
-            .or_insert_with(BenchmarksByGlobalBenchmarkId::new)
+            .or_default()
             .insert(benchmark.global_benchmark_id, benchmark.score);
     }
-    Ok(result)
+    result
 }
 
 /// loads all global benchmarks and maps them by global benchmark id
-fn load_and_map_global_benchmarks(db_connection: &PgConnection) -> Result<MappedGlobalBenchmarks> {
+pub(super) fn load_and_map_global_benchmarks(db_connection: &PgConnection) -> Result<MappedGlobalBenchmarks> {
     let global_benchmarks: Vec<models::GlobalBenchmark> = {
         use schema::global_benchmark::dsl::*;
 
@@ -93,18 +177,23 @@ fn load_and_map_global_benchmarks(db_connection: &PgConnection) -> Result<Mapped
             .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
     };
 
+    Ok(map_global_benchmarks(&global_benchmarks))
+}
+
+/// maps the information of the given global benchmarks by global benchmark id
+pub(super) fn map_global_benchmarks(global_benchmarks: &[models::GlobalBenchmark]) -> MappedGlobalBenchmarks {
     let mut result = MappedGlobalBenchmarks::new();
     for global_benchmark in global_benchmarks {
         result.insert(
             global_benchmark.id,
             GlobalBenchmarkInfo {
                 max: global_benchmark.max,
-                sum: global_benchmark.sum,
+                sum: global_benchmark.sum.clone(),
                 amount: global_benchmark.amount,
             },
         );
     }
-    Ok(result)
+    result
 }
 
 /// loads all benchmark scores in categories and maps them by category id and then by global benchmark id
@@ -122,7 +211,7 @@ fn load_and_map_benchmark_scores_in_categories(
     for benchmark_score_in_category in benchmark_scores_in_categories {
         result
             .entry(benchmark_score_in_category.category_id)
-            .or_insert_with(BenchmarksByGlobalBenchmarkId::new)
+            .or_default()
             .insert(
                 benchmark_score_in_category.global_benchmark_id,
                 benchmark_score_in_category.score,
@@ -132,8 +221,12 @@ fn load_and_map_benchmark_scores_in_categories(
 }
 
 /// performs the actual calculation of the scores of each laptop in each category, and returns a vector
-/// of insertable structs that represent these scores
-fn calculate_laptop_scores_in_each_category(
+/// of insertable structs that represent these scores.
+///
+/// along with each score, the confidence of the score is calculated, which is the share of the category's
+/// benchmark weights that is backed by benchmarks that were actually measured for the laptop. a laptop that
+/// has only a few real benchmarks has most of its score made up of average scores, and thus a low confidence.
+pub(super) fn calculate_laptop_scores_in_each_category(
     mapped_benchmarks: &MappedBenchmarks,
     mapped_benchmark_scores_in_categories: &MappedBenchmarkScoresInCategories,
     mapped_global_benchmarks: &MappedGlobalBenchmarks,
@@ -144,38 +237,63 @@ fn calculate_laptop_scores_in_each_category(
             mapped_benchmark_scores_in_categories
         {
             let mut score_in_category = 0.0;
+            let mut measured_weight = 0.0;
+            let mut total_weight = 0.0;
             for (global_benchmark_id, benchmark_score_in_category) in
                 benchmark_scores_in_categories_by_global_benchmark_id
             {
                 let global_benchmark_info =
-                    mapped_global_benchmarks.get(&global_benchmark_id).unwrap();
-                let score_in_benchmark = match laptop_scores_in_benchmarks.get(&global_benchmark_id)
+                    mapped_global_benchmarks.get(global_benchmark_id).unwrap();
+                total_weight += benchmark_score_in_category;
+                let score_in_benchmark = match laptop_scores_in_benchmarks.get(global_benchmark_id)
                 {
-                    Some(&score) => score,
+                    Some(&score) => {
+                        measured_weight += benchmark_score_in_category;
+                        score
+                    }
                     None => {
                         // if the laptop has no score for this benchmark, we should give him the average score
                         global_benchmark_info.average()
                     }
                 };
 
-                // when calculating the normalized score, make sure we don't divide by zero
-                let normalized_score_in_benchmark = if global_benchmark_info.max == 0.0 {
-                    0.0
-                } else{
-                    score_in_benchmark / global_benchmark_info.max
-                };
+                let normalized_score_in_benchmark = global_benchmark_info.normalize(score_in_benchmark);
                 score_in_category += normalized_score_in_benchmark * benchmark_score_in_category;
             }
+            // if the category gives no weight to any benchmark, there is nothing that could be imputed
+            let confidence = if total_weight > 0.0 {
+                measured_weight / total_weight
+            } else {
+                1.0
+            };
             result.push(models::NewLaptopScoreInCategory {
                 score: score_in_category,
                 laptop_id: *laptop_id,
                 category_id: *category_id,
+                confidence,
             });
         }
     }
     result
 }
 
+/// the selector can't rank laptops with scores that are not finite, so make sure that no such
+/// scores are stored in the database. the inputs are validated when they are loaded, so this
+/// should never fail, but a score that is not finite would otherwise only be noticed by the selector.
+pub(super) fn verify_scores_are_finite(scores: &[models::NewLaptopScoreInCategory]) -> Result<()> {
+    match scores
+        .iter()
+        .find(|score| !score.score.is_finite() || !score.confidence.is_finite())
+    {
+        Some(score) => Err(DataProcessorErrorKind::NonFiniteLaptopScore {
+            laptop_id: score.laptop_id,
+            category_id: score.category_id,
+        }
+        .into_empty_data_processor_error()),
+        None => Ok(()),
+    }
+}
+
 /// inserts the laptop scores in categories into the database
 fn insert_scores(
     scores: &[models::NewLaptopScoreInCategory],
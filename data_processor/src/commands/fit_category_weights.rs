@@ -0,0 +1,720 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+
+use crate::benchmark_patterns::{PatternScores, DEFAULT_BENCHMARK_SCORE_IN_CATEGORY};
+use crate::errors::*;
+use crate::input_paths::InputPaths;
+use db_access::{models, schema};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::Deserialize;
+
+use super::calculate_scores::{
+    load_and_map_benchmarks, load_and_map_global_benchmarks, MappedBenchmarks,
+    MappedGlobalBenchmarks,
+};
+use super::load_categories::{parse_categories_file, validate_categories_file, CategoriesFile, CategoryPatternScores};
+use super::write_report_file;
+
+const PROPOSED_CATEGORIES_FILE_NAME: &str = "proposed_categories.json";
+const REPORT_FILE_NAME: &str = "category_weights_report.txt";
+
+/// how strongly the feedback moves the weights. an adjustment factor is `e` to the power of this rate
+/// times the average relative advantage in the pattern of the laptops that got positive feedback.
+const LEARNING_RATE: f32 = 2.0;
+
+/// the amount of imaginary feedback that agrees with the current weights, which keeps a category with
+/// only a few pieces of feedback from being adjusted too much
+const PRIOR_FEEDBACK_AMOUNT: f32 = 10.0;
+
+/// the largest factor by which a single fit may multiply or divide the score of a pattern
+const MAX_ADJUSTMENT_FACTOR: f32 = 2.0;
+
+/// the amount of top laptops in each category whose changes are reported
+const REPORT_TOP_LAPTOPS_AMOUNT: usize = 10;
+
+/// the max amount of ranking movements that are reported for each category
+const REPORT_MOVEMENTS_AMOUNT: usize = 5;
+
+/// the only part of the stored selection parameters that the fit needs
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedbackSelection {
+    category_scores: HashMap<String, f32>,
+}
+
+/// identifies a pattern of some pu type in a category. the benchmarks that don't match any
+/// non-default pattern belong to the default pattern, even if the category has no default pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct PatternKey {
+    pu_type: &'static str,
+    /// the index of the pattern among the patterns of the pu type, or None for the default pattern
+    /// of a category that has no default pattern
+    pattern_index: Option<usize>,
+}
+
+/// the global benchmarks of each pattern in a single category
+type BenchmarksByPattern = HashMap<PatternKey, Vec<i32>>;
+
+/// the sums from which the adjustments of a single category's patterns are calculated
+#[derive(Debug, Default)]
+struct CategoryFit {
+    /// the feedback weighted sum of the relative advantage of the laptops that got feedback in each
+    /// pattern's benchmarks, which is how much more they are better than the average laptop in the
+    /// pattern's benchmarks than in the category as a whole
+    advantages: HashMap<PatternKey, f32>,
+    /// the total weight of the feedback in this category
+    feedback_weight: f32,
+}
+
+/// the result of fitting the category weights to the feedback
+#[derive(Debug, Default)]
+struct CategoryWeightsFit {
+    used_feedback_amount: usize,
+    /// the feedback whose laptop no longer exists, whose action is unknown or whose selection is invalid
+    skipped_feedback_amount: usize,
+    /// the categories file with the adjusted pattern scores, which is empty if no feedback was used
+    proposed_categories_file: BTreeMap<String, CategoryPatternScores>,
+    /// describes the adjustments and the expected changes in the ranking of each category
+    report: String,
+}
+
+/// fits adjustments to the scores of the patterns in the categories file using the feedback that the
+/// selector recorded, writes the adjusted categories to a proposed categories file, and writes a report
+/// of the adjustments and of the expected changes in the ranking. the database is not modified.
+pub fn fit_category_weights(db_connection: &PgConnection, input_paths: &InputPaths) -> Result<()> {
+    println!("loading the categories file...");
+    let categories_file = parse_categories_file(&input_paths.categories_file)?;
+    validate_categories_file(&categories_file)?;
+
+    println!("loading benchmarks and laptops...");
+    let global_benchmarks: Vec<models::GlobalBenchmark> = {
+        use schema::global_benchmark::dsl::*;
+        global_benchmark
+            .load(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+    };
+    let mapped_benchmarks = load_and_map_benchmarks(db_connection)?;
+    let mapped_global_benchmarks = load_and_map_global_benchmarks(db_connection)?;
+    let laptop_names: HashMap<i32, String> = {
+        use schema::laptop::dsl::*;
+        laptop
+            .select((id, name))
+            .load::<(i32, String)>(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+            .into_iter()
+            .collect()
+    };
+
+    println!("loading feedback...");
+    let feedback: Vec<models::SelectionFeedback> = {
+        use schema::selection_feedback::dsl::*;
+        selection_feedback
+            .order(id)
+            .load(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+    };
+    println!("loaded {} pieces of feedback", feedback.len());
+
+    println!("fitting adjustments...");
+    let fit = fit(
+        &feedback,
+        &categories_file,
+        &global_benchmarks,
+        &mapped_benchmarks,
+        &mapped_global_benchmarks,
+        &laptop_names,
+    );
+    println!(
+        "used {} pieces of feedback, skipped {} whose laptops no longer exist or that are invalid",
+        fit.used_feedback_amount, fit.skipped_feedback_amount
+    );
+    if fit.used_feedback_amount == 0 {
+        println!("there is no usable feedback, so there is nothing to fit");
+        return Ok(());
+    }
+
+    println!("writing the proposed categories file and the report...");
+    let serialized_proposed_categories_file = serde_json::to_string_pretty(&fit.proposed_categories_file)
+        .into_data_processor_result(DataProcessorErrorKind::FailedToWriteProposedCategoriesFile)?;
+    let proposed_categories_file_path = input_paths.report_file(PROPOSED_CATEGORIES_FILE_NAME);
+    write_report_file(&proposed_categories_file_path, &serialized_proposed_categories_file)
+        .into_data_processor_result(DataProcessorErrorKind::FailedToWriteProposedCategoriesFile)?;
+    let report_file_path = input_paths.report_file(REPORT_FILE_NAME);
+    write_report_file(&report_file_path, &fit.report)
+        .into_data_processor_result(DataProcessorErrorKind::FailedToWriteCategoryWeightsReport)?;
+
+    println!(
+        "successfully wrote {} and {}",
+        proposed_categories_file_path.display(),
+        report_file_path.display()
+    );
+    Ok(())
+}
+
+/// fits the scores of the patterns in the categories file to the feedback, given the stored dataset.
+/// returns the proposed categories file along with a report of the adjustments.
+fn fit(
+    feedback: &[models::SelectionFeedback],
+    categories_file: &CategoriesFile,
+    global_benchmarks: &[models::GlobalBenchmark],
+    mapped_benchmarks: &MappedBenchmarks,
+    mapped_global_benchmarks: &MappedGlobalBenchmarks,
+    laptop_names: &HashMap<i32, String>,
+) -> CategoryWeightsFit {
+    let benchmarks_by_pattern_in_each_category: HashMap<&str, BenchmarksByPattern> = categories_file
+        .iter()
+        .map(|(category_name, category_pattern_scores)| {
+            (
+                category_name.as_str(),
+                match_benchmarks_with_patterns(global_benchmarks, category_pattern_scores),
+            )
+        })
+        .collect();
+    let current_benchmark_weights_in_each_category: HashMap<&str, HashMap<i32, f32>> =
+        benchmarks_by_pattern_in_each_category
+            .iter()
+            .map(|(category_name, benchmarks_by_pattern)| {
+                let benchmark_weights = benchmark_weights(&categories_file[*category_name], benchmarks_by_pattern);
+                (*category_name, benchmark_weights)
+            })
+            .collect();
+
+    let laptop_ids_by_name: HashMap<&str, i32> = laptop_names
+        .iter()
+        .map(|(laptop_id, laptop_name)| (laptop_name.as_str(), *laptop_id))
+        .collect();
+    let mut fits: HashMap<&str, CategoryFit> = HashMap::new();
+    let mut used_feedback_amount = 0;
+    for feedback in feedback {
+        // the selector only stores valid selections, but the laptop might have been removed since
+        let laptop_id = match laptop_ids_by_name.get(feedback.laptop_name.as_str()) {
+            Some(laptop_id) => *laptop_id,
+            None => continue,
+        };
+        let label = match feedback_label(&feedback.action) {
+            Some(label) => label,
+            None => continue,
+        };
+        let selection: FeedbackSelection = match serde_json::from_str(&feedback.selection_parameters) {
+            Ok(selection) => selection,
+            Err(_) => continue,
+        };
+        let total_category_score: f32 = selection.category_scores.values().sum();
+        if !total_category_score.is_normal() {
+            continue;
+        }
+        used_feedback_amount += 1;
+
+        // the feedback tells us about each category in proportion to how much the user cared about it
+        for (category_name, category_score) in &selection.category_scores {
+            let (category_name, benchmarks_by_pattern) =
+                match benchmarks_by_pattern_in_each_category.get_key_value(category_name.as_str()) {
+                    Some(category_name_and_benchmarks_by_pattern) => category_name_and_benchmarks_by_pattern,
+                    None => continue,
+                };
+            fits.entry(*category_name).or_default().add_feedback(
+                label * category_score / total_category_score,
+                laptop_id,
+                benchmarks_by_pattern,
+                &current_benchmark_weights_in_each_category[category_name],
+                mapped_benchmarks,
+                mapped_global_benchmarks,
+            );
+        }
+    }
+    let skipped_feedback_amount = feedback.len() - used_feedback_amount;
+    let mut result = CategoryWeightsFit {
+        used_feedback_amount,
+        skipped_feedback_amount,
+        ..CategoryWeightsFit::default()
+    };
+    if used_feedback_amount == 0 {
+        return result;
+    }
+
+    let no_fit = CategoryFit::default();
+    let mut category_names: Vec<&str> = categories_file.keys().map(String::as_str).collect();
+    category_names.sort_unstable();
+
+    writeln!(
+        result.report,
+        "fitted from {} pieces of feedback ({} skipped)",
+        used_feedback_amount, skipped_feedback_amount
+    )
+    .unwrap();
+    for category_name in category_names {
+        let fit = fits.get(category_name).unwrap_or(&no_fit);
+        let benchmarks_by_pattern = &benchmarks_by_pattern_in_each_category[category_name];
+
+        writeln!(result.report, "\n{} (feedback weight {:.2})", category_name, fit.feedback_weight).unwrap();
+        let proposed = propose_pattern_scores(
+            &categories_file[category_name],
+            benchmarks_by_pattern,
+            fit,
+            &mut result.report,
+        );
+
+        let old_ranking = rank_laptops_in_category(
+            &current_benchmark_weights_in_each_category[category_name],
+            mapped_benchmarks,
+            mapped_global_benchmarks,
+        );
+        let new_ranking = rank_laptops_in_category(
+            &benchmark_weights(&proposed, benchmarks_by_pattern),
+            mapped_benchmarks,
+            mapped_global_benchmarks,
+        );
+        write_ranking_changes(&mut result.report, &old_ranking, &new_ranking, laptop_names);
+
+        result.proposed_categories_file.insert(category_name.to_string(), proposed);
+    }
+    result
+}
+
+/// groups the global benchmarks by the pattern of the category that they match
+fn match_benchmarks_with_patterns(
+    global_benchmarks: &[models::GlobalBenchmark],
+    category_pattern_scores: &CategoryPatternScores,
+) -> BenchmarksByPattern {
+    let mut benchmarks_by_pattern = BenchmarksByPattern::new();
+    for benchmark in global_benchmarks {
+        let (pu_type, patterns_and_scores) = match benchmark.pu_type() {
+            models::PuType::Cpu => ("cpu", &category_pattern_scores.cpu),
+            models::PuType::Gpu => ("gpu", &category_pattern_scores.gpu),
+        };
+        let pattern_index = patterns_and_scores
+            .find_matching_pattern(benchmark.name_without_prefix())
+            .or_else(|| patterns_and_scores.default_pattern());
+        benchmarks_by_pattern
+            .entry(PatternKey { pu_type, pattern_index })
+            .or_default()
+            .push(benchmark.id);
+    }
+    benchmarks_by_pattern
+}
+
+impl CategoryFit {
+    /// adds a single piece of feedback about the laptop, weighted by its label times the share of the
+    /// category scores that its selection gave the category
+    fn add_feedback(
+        &mut self,
+        feedback_weight: f32,
+        laptop_id: i32,
+        benchmarks_by_pattern: &BenchmarksByPattern,
+        current_benchmark_weights: &HashMap<i32, f32>,
+        mapped_benchmarks: &MappedBenchmarks,
+        mapped_global_benchmarks: &MappedGlobalBenchmarks,
+    ) {
+        self.feedback_weight += feedback_weight.abs();
+        let advantages: Vec<(&PatternKey, f32)> = benchmarks_by_pattern
+            .iter()
+            .map(|(pattern_key, benchmark_ids)| {
+                let advantage = laptop_advantage(laptop_id, benchmark_ids, mapped_benchmarks, mapped_global_benchmarks);
+                (pattern_key, advantage)
+            })
+            .collect();
+
+        // raising the scores of all patterns together doesn't change anything, since the scores are
+        // normalized, so what matters is how much better the laptop is in each pattern than in the
+        // category as a whole, according to the current weights
+        let overall_advantage: f32 = advantages
+            .iter()
+            .map(|(pattern_key, advantage)| {
+                let pattern_weight: f32 = benchmarks_by_pattern[*pattern_key]
+                    .iter()
+                    .map(|global_benchmark_id| current_benchmark_weights[global_benchmark_id])
+                    .sum();
+                pattern_weight * advantage
+            })
+            .sum();
+        for (pattern_key, advantage) in advantages {
+            *self.advantages.entry(pattern_key.clone()).or_default() += feedback_weight * (advantage - overall_advantage);
+        }
+    }
+}
+
+/// adjusts the scores of the category's patterns according to its fit, and describes the adjustments in
+/// the report
+fn propose_pattern_scores(
+    category_pattern_scores: &CategoryPatternScores,
+    benchmarks_by_pattern: &BenchmarksByPattern,
+    fit: &CategoryFit,
+    report: &mut String,
+) -> CategoryPatternScores {
+    let mut proposed = category_pattern_scores.clone();
+    let mut pattern_keys: Vec<&PatternKey> = benchmarks_by_pattern.keys().collect();
+    pattern_keys.sort_unstable();
+    for pattern_key in pattern_keys {
+        let factor = adjustment_factor(fit, pattern_key);
+        let patterns_and_scores = match pattern_key.pu_type {
+            "cpu" => &mut proposed.cpu,
+            _ => &mut proposed.gpu,
+        };
+        // a category without a default pattern implicitly gives the default score to the unmatched
+        // benchmarks, so adjusting them requires adding the default pattern
+        let pattern_index = match pattern_key.pattern_index {
+            Some(pattern_index) => pattern_index,
+            None if factor != 1.0 => patterns_and_scores.add_default_pattern(DEFAULT_BENCHMARK_SCORE_IN_CATEGORY),
+            None => continue,
+        };
+        let old_score = patterns_and_scores.patterns()[pattern_index].score();
+        let new_score = round_score(old_score * factor);
+        patterns_and_scores.set_score(pattern_index, new_score);
+        if new_score != old_score {
+            writeln!(
+                report,
+                "  {} {}: {} -> {} (x{:.3})",
+                pattern_key.pu_type,
+                patterns_and_scores.patterns()[pattern_index],
+                old_score,
+                new_score,
+                factor
+            )
+            .unwrap();
+        }
+    }
+    proposed
+}
+
+/// the weight of each global benchmark in a category with the given pattern scores
+fn benchmark_weights(
+    category_pattern_scores: &CategoryPatternScores,
+    benchmarks_by_pattern: &BenchmarksByPattern,
+) -> HashMap<i32, f32> {
+    pattern_scores_to_benchmark_weights(benchmarks_by_pattern, |pattern_key| {
+        let patterns_and_scores = match pattern_key.pu_type {
+            "cpu" => &category_pattern_scores.cpu,
+            _ => &category_pattern_scores.gpu,
+        };
+        pattern_score(patterns_and_scores, pattern_key.pattern_index)
+    })
+}
+
+/// how much the feedback action says that the laptop should have been ranked higher. a click is a
+/// weaker signal than an explicit like.
+fn feedback_label(action: &str) -> Option<f32> {
+    match action {
+        "liked" => Some(1.0),
+        "clicked" => Some(0.5),
+        "dismissed" => Some(-1.0),
+        _ => None,
+    }
+}
+
+/// the normalized score of the laptop in the benchmark, where missing scores are replaced with the
+/// average score, just like when calculating the laptops' scores
+fn normalized_score(
+    laptop_id: i32,
+    global_benchmark_id: i32,
+    mapped_benchmarks: &MappedBenchmarks,
+    mapped_global_benchmarks: &MappedGlobalBenchmarks,
+) -> f32 {
+    let global_benchmark_info = &mapped_global_benchmarks[&global_benchmark_id];
+    let score_in_benchmark = mapped_benchmarks
+        .get(&laptop_id)
+        .and_then(|laptop_scores_in_benchmarks| laptop_scores_in_benchmarks.get(&global_benchmark_id))
+        .copied()
+        .unwrap_or_else(|| global_benchmark_info.average());
+    global_benchmark_info.normalize(score_in_benchmark)
+}
+
+/// the average difference between the normalized scores of the laptop in the given benchmarks and
+/// the normalized average scores in them
+fn laptop_advantage(
+    laptop_id: i32,
+    benchmark_ids: &[i32],
+    mapped_benchmarks: &MappedBenchmarks,
+    mapped_global_benchmarks: &MappedGlobalBenchmarks,
+) -> f32 {
+    let total_advantage: f32 = benchmark_ids
+        .iter()
+        .map(|global_benchmark_id| {
+            let global_benchmark_info = &mapped_global_benchmarks[global_benchmark_id];
+            normalized_score(laptop_id, *global_benchmark_id, mapped_benchmarks, mapped_global_benchmarks)
+                - global_benchmark_info.normalize(global_benchmark_info.average())
+        })
+        .sum();
+    total_advantage / benchmark_ids.len() as f32
+}
+
+/// the factor by which the score of the pattern should be multiplied. positive feedback on laptops that
+/// are strong in the pattern's benchmarks raises it, and negative feedback on such laptops lowers it.
+fn adjustment_factor(fit: &CategoryFit, pattern_key: &PatternKey) -> f32 {
+    let advantage = fit.advantages.get(pattern_key).copied().unwrap_or_default();
+    (LEARNING_RATE * advantage / (fit.feedback_weight + PRIOR_FEEDBACK_AMOUNT))
+        .exp()
+        .clamp(1.0 / MAX_ADJUSTMENT_FACTOR, MAX_ADJUSTMENT_FACTOR)
+}
+
+/// the score of the pattern with the given index, or the default score if there is no such pattern
+fn pattern_score(patterns_and_scores: &PatternScores, pattern_index: Option<usize>) -> f32 {
+    match pattern_index {
+        Some(pattern_index) => patterns_and_scores.patterns()[pattern_index].score(),
+        None => patterns_and_scores.default_score(),
+    }
+}
+
+/// rounds a proposed score so that the proposed categories file stays readable
+fn round_score(score: f32) -> f32 {
+    (score * 1000.0).round() / 1000.0
+}
+
+/// normalizes the scores of the patterns in a category into the weight of each global benchmark,
+/// just like loading the categories does
+fn pattern_scores_to_benchmark_weights(
+    benchmarks_by_pattern: &BenchmarksByPattern,
+    pattern_score: impl Fn(&PatternKey) -> f32,
+) -> HashMap<i32, f32> {
+    let mut weights = HashMap::new();
+    for (pattern_key, benchmark_ids) in benchmarks_by_pattern {
+        let score = pattern_score(pattern_key);
+        for global_benchmark_id in benchmark_ids {
+            weights.insert(*global_benchmark_id, score);
+        }
+    }
+    let sum: f32 = weights.values().sum();
+    if sum.is_normal() {
+        for weight in weights.values_mut() {
+            *weight /= sum;
+        }
+    }
+    weights
+}
+
+/// ranks the laptops by their score in a category with the given benchmark weights, from best to
+/// worst. laptops with equal scores are ordered by their id.
+fn rank_laptops_in_category(
+    benchmark_weights: &HashMap<i32, f32>,
+    mapped_benchmarks: &MappedBenchmarks,
+    mapped_global_benchmarks: &MappedGlobalBenchmarks,
+) -> Vec<i32> {
+    let mut scores: Vec<(i32, f32)> = mapped_benchmarks
+        .keys()
+        .map(|laptop_id| {
+            let score = benchmark_weights
+                .iter()
+                .map(|(global_benchmark_id, weight)| {
+                    weight
+                        * normalized_score(
+                            *laptop_id,
+                            *global_benchmark_id,
+                            mapped_benchmarks,
+                            mapped_global_benchmarks,
+                        )
+                })
+                .sum();
+            (*laptop_id, score)
+        })
+        .collect();
+    scores.sort_unstable_by(|(id1, score1), (id2, score2)| score2.total_cmp(score1).then(id1.cmp(id2)));
+    scores.into_iter().map(|(laptop_id, _)| laptop_id).collect()
+}
+
+/// describes the laptops that entered or left the top of the category, and the largest movements of
+/// laptops that are at the top either before or after the adjustment
+fn write_ranking_changes(
+    report: &mut String,
+    old_ranking: &[i32],
+    new_ranking: &[i32],
+    laptop_names: &HashMap<i32, String>,
+) {
+    let old_ranks: HashMap<i32, usize> = old_ranking.iter().enumerate().map(|(rank, id)| (*id, rank + 1)).collect();
+    let new_ranks: HashMap<i32, usize> = new_ranking.iter().enumerate().map(|(rank, id)| (*id, rank + 1)).collect();
+    let describe = |laptop_id: i32| {
+        format!(
+            "{} ({} -> {})",
+            laptop_names[&laptop_id], old_ranks[&laptop_id], new_ranks[&laptop_id]
+        )
+    };
+
+    let old_top = &old_ranking[..old_ranking.len().min(REPORT_TOP_LAPTOPS_AMOUNT)];
+    let new_top = &new_ranking[..new_ranking.len().min(REPORT_TOP_LAPTOPS_AMOUNT)];
+    for laptop_id in new_top.iter().filter(|laptop_id| !old_top.contains(laptop_id)) {
+        writeln!(report, "  entered the top {}: {}", REPORT_TOP_LAPTOPS_AMOUNT, describe(*laptop_id)).unwrap();
+    }
+    for laptop_id in old_top.iter().filter(|laptop_id| !new_top.contains(laptop_id)) {
+        writeln!(report, "  left the top {}: {}", REPORT_TOP_LAPTOPS_AMOUNT, describe(*laptop_id)).unwrap();
+    }
+
+    let mut movements: Vec<(i32, usize)> = old_top
+        .iter()
+        .chain(new_top.iter().filter(|laptop_id| !old_top.contains(laptop_id)))
+        .map(|laptop_id| (*laptop_id, old_ranks[laptop_id].abs_diff(new_ranks[laptop_id])))
+        .filter(|(_, movement)| *movement > 0)
+        .collect();
+    movements.sort_unstable_by(|(id1, movement1), (id2, movement2)| {
+        movement2.cmp(movement1).then(old_ranks[id1].cmp(&old_ranks[id2]))
+    });
+    if movements.is_empty() {
+        writeln!(report, "  no changes in the top {}", REPORT_TOP_LAPTOPS_AMOUNT).unwrap();
+    }
+    for (laptop_id, _) in movements.into_iter().take(REPORT_MOVEMENTS_AMOUNT) {
+        writeln!(report, "  moved: {}", describe(laptop_id)).unwrap();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::calculate_scores::map_global_benchmarks;
+    use bigdecimal::BigDecimal;
+    use maplit::hashmap;
+    use std::time::SystemTime;
+
+    /// a dataset in which one laptop is strong in cinebench, one is strong in geekbench, and one is average
+    struct TestDataset {
+        categories_file: CategoriesFile,
+        global_benchmarks: Vec<models::GlobalBenchmark>,
+        mapped_benchmarks: MappedBenchmarks,
+        mapped_global_benchmarks: MappedGlobalBenchmarks,
+        laptop_names: HashMap<i32, String>,
+    }
+    impl TestDataset {
+        fn new() -> Self {
+            let global_benchmark = |id, name: &str| models::GlobalBenchmark {
+                id,
+                name: name.to_string(),
+                max: 1000.0,
+                sum: BigDecimal::from(1500),
+                amount: 3,
+            };
+            let global_benchmarks =
+                vec![global_benchmark(1, "cCinebench"), global_benchmark(2, "cGeekbench"), global_benchmark(3, "g3DMark")];
+            let mapped_global_benchmarks = map_global_benchmarks(&global_benchmarks);
+            Self {
+                // the gaming category has no default cpu pattern, so geekbench gets the default score
+                categories_file: serde_json::from_str(
+                    r#"{
+                        "dev": {"cpu": {"Cinebench": 1, "Geekbench": 1}, "gpu": {"*": 1}},
+                        "gaming": {"cpu": {"Cinebench": 1}, "gpu": {"*": 1}}
+                    }"#,
+                )
+                .unwrap(),
+                global_benchmarks,
+                mapped_benchmarks: hashmap! {
+                    1 => hashmap! { 1 => 1000.0, 2 => 200.0, 3 => 500.0 },
+                    2 => hashmap! { 1 => 200.0, 2 => 1000.0, 3 => 500.0 },
+                    3 => hashmap! { 1 => 300.0, 2 => 300.0, 3 => 500.0 },
+                },
+                mapped_global_benchmarks,
+                laptop_names: hashmap! {
+                    1 => "cinebench laptop".to_string(),
+                    2 => "geekbench laptop".to_string(),
+                    3 => "average laptop".to_string(),
+                },
+            }
+        }
+
+        fn fit(&self, feedback: &[models::SelectionFeedback]) -> CategoryWeightsFit {
+            fit(
+                feedback,
+                &self.categories_file,
+                &self.global_benchmarks,
+                &self.mapped_benchmarks,
+                &self.mapped_global_benchmarks,
+                &self.laptop_names,
+            )
+        }
+    }
+
+    fn feedback(laptop_name: &str, action: &str, selection_parameters: &str) -> models::SelectionFeedback {
+        models::SelectionFeedback {
+            id: 1,
+            laptop_name: laptop_name.to_string(),
+            action: action.to_string(),
+            selection_parameters: selection_parameters.to_string(),
+            created_at: SystemTime::now(),
+        }
+    }
+
+    const DEV_SELECTION: &str = r#"{"categoryScores": {"dev": 1}}"#;
+    const GAMING_SELECTION: &str = r#"{"categoryScores": {"gaming": 1}}"#;
+
+    /// the proposed score of the cpu pattern that the benchmark belongs to in the category
+    fn proposed_cpu_score(fit: &CategoryWeightsFit, category_name: &str, benchmark_name: &str) -> f32 {
+        let cpu = &fit.proposed_categories_file[category_name].cpu;
+        pattern_score(cpu, cpu.find_matching_pattern(benchmark_name).or_else(|| cpu.default_pattern()))
+    }
+
+    #[test]
+    fn liking_a_laptop_raises_the_patterns_in_which_it_is_strong() {
+        let fit = TestDataset::new().fit(&[feedback("cinebench laptop", "liked", DEV_SELECTION)]);
+        assert_eq!(fit.used_feedback_amount, 1);
+        assert!(proposed_cpu_score(&fit, "dev", "Cinebench") > 1.0);
+        assert!(proposed_cpu_score(&fit, "dev", "Geekbench") < 1.0);
+    }
+
+    #[test]
+    fn dismissing_a_laptop_lowers_the_patterns_in_which_it_is_strong() {
+        let fit = TestDataset::new().fit(&[feedback("cinebench laptop", "dismissed", DEV_SELECTION)]);
+        assert!(proposed_cpu_score(&fit, "dev", "Cinebench") < 1.0);
+        assert!(proposed_cpu_score(&fit, "dev", "Geekbench") > 1.0);
+    }
+
+    #[test]
+    fn the_adjustment_factor_is_clamped() {
+        let pattern_key = PatternKey {
+            pu_type: "cpu",
+            pattern_index: Some(0),
+        };
+        let fit_with_advantage = |advantage| CategoryFit {
+            advantages: hashmap! { pattern_key.clone() => advantage },
+            feedback_weight: 1.0,
+        };
+        assert_eq!(adjustment_factor(&fit_with_advantage(1000.0), &pattern_key), MAX_ADJUSTMENT_FACTOR);
+        assert_eq!(adjustment_factor(&fit_with_advantage(-1000.0), &pattern_key), 1.0 / MAX_ADJUSTMENT_FACTOR);
+        assert_eq!(adjustment_factor(&CategoryFit::default(), &pattern_key), 1.0);
+
+        // even a lot of agreeing feedback can't move a score out of the clamp
+        let feedback: Vec<_> = (0..1000)
+            .map(|_| feedback("cinebench laptop", "liked", DEV_SELECTION))
+            .collect();
+        let fit = TestDataset::new().fit(&feedback);
+        for benchmark_name in &["Cinebench", "Geekbench"] {
+            let score = proposed_cpu_score(&fit, "dev", benchmark_name);
+            assert!((1.0 / MAX_ADJUSTMENT_FACTOR..=MAX_ADJUSTMENT_FACTOR).contains(&score));
+        }
+    }
+
+    #[test]
+    fn the_default_pattern_is_only_added_when_it_is_adjusted() {
+        let fit = TestDataset::new().fit(&[feedback("geekbench laptop", "liked", GAMING_SELECTION)]);
+        let gaming_cpu = &fit.proposed_categories_file["gaming"].cpu;
+        assert!(gaming_cpu.default_pattern().is_some());
+        assert!(proposed_cpu_score(&fit, "gaming", "Geekbench") > DEFAULT_BENCHMARK_SCORE_IN_CATEGORY);
+
+        // without feedback in the category, its factors are 1 and it is proposed unchanged
+        let fit = TestDataset::new().fit(&[feedback("geekbench laptop", "liked", DEV_SELECTION)]);
+        let gaming_cpu = &fit.proposed_categories_file["gaming"].cpu;
+        assert!(gaming_cpu.default_pattern().is_none());
+        assert_eq!(gaming_cpu.patterns().len(), 1);
+    }
+
+    #[test]
+    fn unusable_feedback_is_skipped() {
+        let dataset = TestDataset::new();
+        let unusable_feedback = vec![
+            feedback("cinebench laptop", "shared", DEV_SELECTION),
+            feedback("removed laptop", "liked", DEV_SELECTION),
+            feedback("cinebench laptop", "liked", "not json"),
+            feedback("cinebench laptop", "liked", r#"{"categoryScores": {"dev": 0}}"#),
+        ];
+        let fit = dataset.fit(&unusable_feedback);
+        assert_eq!(fit.used_feedback_amount, 0);
+        assert_eq!(fit.skipped_feedback_amount, 4);
+        assert!(fit.proposed_categories_file.is_empty());
+
+        // the skipped feedback doesn't change the fit of the usable feedback
+        let usable_feedback = feedback("cinebench laptop", "liked", DEV_SELECTION);
+        let mut all_feedback = unusable_feedback;
+        all_feedback.push(feedback("cinebench laptop", "liked", DEV_SELECTION));
+        let fit = dataset.fit(&all_feedback);
+        assert_eq!(fit.used_feedback_amount, 1);
+        assert_eq!(
+            proposed_cpu_score(&fit, "dev", "Cinebench"),
+            proposed_cpu_score(&dataset.fit(&[usable_feedback]), "dev", "Cinebench")
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use crate::errors::*;
+use db_access::dataset_generations::{dataset_schema_name, use_dataset_generation, PUBLIC_GENERATION};
+use db_access::{models, schema};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text};
+use diesel::PgConnection;
+use diesel::QueryableByName;
+
+/// the amount of generations that are kept by default when pruning, not including the active generation
+pub const DEFAULT_KEPT_GENERATIONS: usize = 3;
+
+/// the tables that store the dataset, which are copied for each generation. the tables are ordered
+/// such that each table comes after the tables that it references.
+const DATASET_TABLES: &[&str] = &[
+    "laptop",
+    "laptop_image",
+    "laptop_specs",
+    "global_benchmark",
+    "benchmark",
+    "category",
+    "benchmark_score_in_category",
+    "laptop_score_in_category",
+    "price_limits",
+    "exchange_rate",
+];
+
+#[derive(Debug, QueryableByName)]
+struct ForeignKey {
+    #[sql_type = "Text"]
+    table_name: String,
+    #[sql_type = "Text"]
+    constraint_name: String,
+    #[sql_type = "Text"]
+    definition: String,
+}
+
+#[derive(Debug, QueryableByName)]
+struct CopiedColumns {
+    #[sql_type = "Nullable<Text>"]
+    columns: Option<String>,
+}
+
+#[derive(Debug, QueryableByName)]
+struct GenerationSummary {
+    #[sql_type = "BigInt"]
+    id: i64,
+    #[sql_type = "Text"]
+    command: String,
+    #[sql_type = "Text"]
+    created_at: String,
+}
+
+#[derive(Debug, QueryableByName)]
+struct RowsAmount {
+    #[sql_type = "BigInt"]
+    amount: i64,
+}
+
+/// returns the id of the generation that the selector currently uses
+pub fn fetch_active_generation(db_connection: &PgConnection)->Result<i64>{
+    use schema::dataset_version::dsl::*;
+
+    dataset_version
+        .select(active_generation)
+        .first(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)
+}
+
+/// makes the rest of the current transaction use the dataset of the active generation
+pub fn use_active_generation(db_connection: &PgConnection)->Result<()>{
+    let generation = fetch_active_generation(db_connection)?;
+    use_dataset_generation(db_connection, generation, true)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)
+}
+
+/// creates a new generation whose dataset is a copy of the active generation's dataset, and makes the rest of
+/// the current transaction use it, such that the command can modify it without affecting the selector.
+/// the new generation is only used by the selector once it is activated.
+pub fn create_generation(db_connection: &PgConnection, command_name: &str)->Result<i64>{
+    let source_schema = dataset_schema_name(fetch_active_generation(db_connection)?);
+    let generation: i64 = diesel::insert_into(schema::dataset_generation::table)
+        .values(&models::NewDatasetGeneration{ command: command_name })
+        .returning(schema::dataset_generation::id)
+        .get_result(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let generation_schema = dataset_schema_name(generation);
+
+    // the tables in the public schema are the templates of the tables of new generations, so that
+    // generations created after a migration have the migrated structure. their foreign keys are defined
+    // using unqualified table names, which must be fetched before the search path changes.
+    let foreign_keys: Vec<ForeignKey> = diesel::sql_query(
+        "SELECT conrelid::regclass::text AS table_name, conname::text AS constraint_name, \
+        pg_get_constraintdef(oid) AS definition FROM pg_constraint \
+        WHERE contype = 'f' AND connamespace = 'public'::regnamespace AND conrelid::regclass::text = ANY($1)"
+    )
+        .bind::<diesel::sql_types::Array<Text>, _>(DATASET_TABLES)
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+
+    execute(db_connection, format!("CREATE SCHEMA {}", generation_schema))?;
+    for table in DATASET_TABLES{
+        execute(db_connection, format!(
+            "CREATE TABLE {}.{} (LIKE public.{} INCLUDING ALL)", generation_schema, table, table
+        ))?;
+
+        // only copy the columns that the source table shares with the template, since the source
+        // generation might have been created before a migration added or removed some columns
+        let copied_columns: CopiedColumns = diesel::sql_query(
+            "SELECT string_agg(quote_ident(source.column_name), ', ' ORDER BY source.ordinal_position) AS columns \
+            FROM information_schema.columns source JOIN information_schema.columns template \
+            ON template.table_schema = 'public' AND template.table_name = source.table_name \
+            AND template.column_name = source.column_name \
+            WHERE source.table_schema = $1 AND source.table_name = $2"
+        )
+            .bind::<Text, _>(&source_schema)
+            .bind::<Text, _>(table)
+            .get_result(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+        if let Some(columns) = copied_columns.columns{
+            execute(db_connection, format!(
+                "INSERT INTO {}.{} ({}) SELECT {} FROM {}.{}",
+                generation_schema, table, columns, columns, source_schema, table
+            ))?;
+        }
+    }
+
+    use_dataset_generation(db_connection, generation, true)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    for foreign_key in foreign_keys{
+        execute(db_connection, format!(
+            "ALTER TABLE {} ADD CONSTRAINT {} {}",
+            foreign_key.table_name, foreign_key.constraint_name, foreign_key.definition
+        ))?;
+    }
+
+    // the selector's role can't read the tables of new schemas unless it is explicitly allowed to.
+    // the role only exists in production.
+    execute(db_connection, format!(
+        "DO $$ BEGIN \
+            IF EXISTS (SELECT FROM pg_roles WHERE rolname = 'favory_readonly') THEN \
+                GRANT USAGE ON SCHEMA {0} TO favory_readonly; \
+                GRANT SELECT ON ALL TABLES IN SCHEMA {0} TO favory_readonly; \
+            END IF; \
+        END $$",
+        generation_schema
+    ))?;
+    Ok(generation)
+}
+
+/// makes the selector use the dataset of the given generation
+pub fn activate_generation(db_connection: &PgConnection, activated_generation: i64)->Result<()>{
+    use schema::dataset_version::dsl::*;
+
+    ensure_generation_exists(db_connection, activated_generation)?;
+    diesel::update(dataset_version)
+        .set((
+            active_generation.eq(activated_generation),
+            // tell the selector that its cached results are outdated
+            generation.eq(generation + 1),
+        ))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    println!("activated generation {}", activated_generation);
+    Ok(())
+}
+
+/// tells the selector that the dataset of the active generation was modified in place, so that it clears its
+/// cached results
+pub fn mark_active_generation_modified(db_connection: &PgConnection)->Result<()>{
+    use schema::dataset_version::dsl::*;
+
+    diesel::update(dataset_version)
+        .set(generation.eq(generation + 1))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
+}
+
+/// activates the newest generation that is older than the active generation
+pub fn rollback_generation(db_connection: &PgConnection)->Result<()>{
+    use schema::dataset_generation::dsl::*;
+
+    let active_generation = fetch_active_generation(db_connection)?;
+    let previous_generation: i64 = dataset_generation
+        .select(id)
+        .filter(id.lt(active_generation))
+        .order(id.desc())
+        .first(db_connection)
+        .optional()
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+        .ok_or_else(|| DataProcessorErrorKind::NoPreviousGeneration.into_empty_data_processor_error())?;
+    activate_generation(db_connection, previous_generation)
+}
+
+/// prints all generations, from the oldest to the newest
+pub fn list_generations(db_connection: &PgConnection)->Result<()>{
+    let active_generation = fetch_active_generation(db_connection)?;
+    let generations: Vec<GenerationSummary> = diesel::sql_query(
+        "SELECT id, command, to_char(created_at, 'YYYY-MM-DD HH24:MI:SS') AS created_at \
+        FROM dataset_generation ORDER BY id"
+    )
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+
+    for generation in generations{
+        let laptops_amount: RowsAmount = diesel::sql_query(format!(
+            "SELECT count(*) AS amount FROM {}.laptop", dataset_schema_name(generation.id)
+        ))
+            .get_result(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+        let active_marker = if generation.id == active_generation { "*" } else { " " };
+        println!(
+            "{} {:>5}  {}  {:>5} laptops  {}",
+            active_marker, generation.id, generation.created_at, laptops_amount.amount, generation.command
+        );
+    }
+    Ok(())
+}
+
+/// deletes all generations other than the active generation and the given amount of newest generations
+pub fn prune_generations(db_connection: &PgConnection, kept_generations: usize)->Result<()>{
+    use schema::dataset_generation::dsl::*;
+
+    let active_generation = fetch_active_generation(db_connection)?;
+    let pruned_generations: Vec<i64> = dataset_generation
+        .select(id)
+        .order(id.desc())
+        .load::<i64>(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+        .into_iter()
+        .filter(|generation| *generation != active_generation)
+        .skip(kept_generations)
+        .collect();
+
+    for generation in &pruned_generations{
+        if *generation == PUBLIC_GENERATION{
+            // the tables of the public schema are the templates of new generations, so only their rows
+            // are deleted, in reverse order so that each table is emptied before the tables it references
+            for table in DATASET_TABLES.iter().rev(){
+                execute(db_connection, format!("DELETE FROM public.{}", table))?;
+            }
+        } else{
+            execute(db_connection, format!("DROP SCHEMA {} CASCADE", dataset_schema_name(*generation)))?;
+        }
+    }
+    diesel::delete(dataset_generation.filter(id.eq_any(&pruned_generations)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    println!("pruned {} generations", pruned_generations.len());
+    Ok(())
+}
+
+fn ensure_generation_exists(db_connection: &PgConnection, generation: i64)->Result<()>{
+    use schema::dataset_generation::dsl::*;
+
+    let exists: bool = diesel::select(diesel::dsl::exists(dataset_generation.find(generation)))
+        .get_result(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    if !exists{
+        return Err(DataProcessorErrorKind::UnknownGeneration{ generation }.into_empty_data_processor_error());
+    }
+    Ok(())
+}
+
+fn execute(db_connection: &PgConnection, query: String)->Result<()>{
+    diesel::sql_query(query)
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_database::{connect_to_test_generations, FIRST_TEST_GENERATION};
+
+    /// the ids of the generations, relative to the first generation of the test, from the oldest to the newest
+    fn generations(db_connection: &PgConnection) -> Vec<i64> {
+        use schema::dataset_generation::dsl::*;
+
+        dataset_generation
+            .select(id)
+            .order(id)
+            .load::<i64>(db_connection)
+            .unwrap()
+            .into_iter()
+            .map(|generation| generation - FIRST_TEST_GENERATION)
+            .collect()
+    }
+
+    /// the ids of the generations whose schemas exist, relative to the first generation of the test
+    fn generation_schemas(db_connection: &PgConnection) -> Vec<i64> {
+        let mut schemas = Vec::new();
+        for generation in FIRST_TEST_GENERATION..FIRST_TEST_GENERATION + 10 {
+            let amount: RowsAmount = diesel::sql_query(format!(
+                "SELECT count(*) AS amount FROM pg_namespace WHERE nspname = '{}'",
+                dataset_schema_name(generation)
+            ))
+            .get_result(db_connection)
+            .unwrap();
+            if amount.amount > 0 {
+                schemas.push(generation - FIRST_TEST_GENERATION);
+            }
+        }
+        schemas
+    }
+
+    /// the active generation, relative to the first generation of the test, and the version of the dataset
+    fn active_generation_and_version(db_connection: &PgConnection) -> (i64, i64) {
+        use schema::dataset_version::dsl::*;
+
+        let (active, version): (i64, i64) = dataset_version
+            .select((active_generation, generation))
+            .first(db_connection)
+            .unwrap();
+        (active - FIRST_TEST_GENERATION, version)
+    }
+
+    #[test]
+    fn pruning_keeps_the_active_generation_and_the_newest_other_generations() {
+        let db_connection = connect_to_test_generations(6, 4);
+        prune_generations(&db_connection, 2).unwrap();
+        // the active generation doesn't count towards the kept generations
+        assert_eq!(generations(&db_connection), vec![3, 4, 5]);
+        assert_eq!(generation_schemas(&db_connection), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn pruning_keeps_an_old_active_generation() {
+        let db_connection = connect_to_test_generations(6, 0);
+        prune_generations(&db_connection, 2).unwrap();
+        assert_eq!(generations(&db_connection), vec![0, 4, 5]);
+        assert_eq!(generation_schemas(&db_connection), vec![0, 4, 5]);
+
+        prune_generations(&db_connection, 0).unwrap();
+        assert_eq!(generations(&db_connection), vec![0]);
+        assert_eq!(generation_schemas(&db_connection), vec![0]);
+    }
+
+    #[test]
+    fn activating_a_generation_tells_the_selector_that_the_dataset_changed() {
+        let db_connection = connect_to_test_generations(3, 2);
+        activate_generation(&db_connection, FIRST_TEST_GENERATION + 1).unwrap();
+        assert_eq!(active_generation_and_version(&db_connection), (1, 2));
+
+        let error = activate_generation(&db_connection, FIRST_TEST_GENERATION + 3).unwrap_err();
+        assert_eq!(
+            error.kind,
+            DataProcessorErrorKind::UnknownGeneration {
+                generation: FIRST_TEST_GENERATION + 3
+            }
+        );
+        assert_eq!(active_generation_and_version(&db_connection), (1, 2));
+
+        mark_active_generation_modified(&db_connection).unwrap();
+        assert_eq!(active_generation_and_version(&db_connection), (1, 3));
+    }
+
+    #[test]
+    fn rolling_back_activates_the_newest_older_generation() {
+        let db_connection = connect_to_test_generations(4, 2);
+        // generation 1 was pruned, so rolling back from generation 2 skips it
+        diesel::delete(schema::dataset_generation::table.find(FIRST_TEST_GENERATION + 1))
+            .execute(&db_connection)
+            .unwrap();
+        rollback_generation(&db_connection).unwrap();
+        assert_eq!(active_generation_and_version(&db_connection), (0, 2));
+
+        let error = rollback_generation(&db_connection).unwrap_err();
+        assert_eq!(error.kind, DataProcessorErrorKind::NoPreviousGeneration);
+        assert_eq!(active_generation_and_version(&db_connection), (0, 2));
+    }
+}
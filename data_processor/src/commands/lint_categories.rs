@@ -0,0 +1,428 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::benchmark_patterns::{BenchmarkPattern, DEFAULT_BENCHMARK_SCORE_IN_CATEGORY};
+use crate::errors::*;
+use crate::input_paths::InputPaths;
+use db_access::{models, schema};
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use super::load_categories::{
+    match_global_benchmarks_with_category, parse_categories_file, validate_categories_file, CategoriesFile,
+};
+use super::ProblemSeverity;
+
+const PU_TYPES: [&str; 2] = ["cpu", "gpu"];
+
+/// a problem that was found in the categories file
+#[derive(Debug)]
+struct CategoriesFileLint {
+    severity: ProblemSeverity,
+    /// the category and pu type of the problem, or None if the problem is not specific to a single one
+    location: Option<(String, &'static str)>,
+    description: String,
+}
+
+impl fmt::Display for CategoriesFileLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if let Some((category_name, pu_type)) = &self.location {
+            write!(f, "{}.{}: ", category_name, pu_type)?;
+        }
+        write!(f, "{}", self.description)
+    }
+}
+
+/// checks the patterns of the categories file against the global benchmarks of the active generation, and
+/// reports mistakes that don't prevent the file from being loaded, but probably make the scores different
+/// than intended
+pub fn lint_categories(db_connection: &PgConnection, input_paths: &InputPaths) -> Result<()> {
+    use schema::global_benchmark::dsl::*;
+
+    println!("loading the categories file...");
+    let categories_file = parse_categories_file(&input_paths.categories_file)?;
+    validate_categories_file(&categories_file)?;
+
+    println!("loading global benchmarks...");
+    let global_benchmarks: Vec<models::GlobalBenchmark> = global_benchmark
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+
+    let lints = lint_categories_file(&categories_file, &global_benchmarks);
+    for lint in &lints {
+        println!("{}", lint);
+    }
+    let errors_amount = lints
+        .iter()
+        .filter(|lint| lint.severity == ProblemSeverity::Error)
+        .count();
+    println!(
+        "found {} errors and {} warnings in the categories file",
+        errors_amount,
+        lints.len() - errors_amount
+    );
+    if errors_amount > 0 {
+        return Err(DataProcessorErrorKind::InvalidCategoriesFile { errors_amount }.into_empty_data_processor_error());
+    }
+    Ok(())
+}
+
+/// finds all problems in the categories file, ordered by category and pu type so that the same problems
+/// are always reported in the same order
+fn lint_categories_file(
+    categories_file: &CategoriesFile,
+    global_benchmarks: &[models::GlobalBenchmark],
+) -> Vec<CategoriesFileLint> {
+    let mut benchmark_names_by_pu_type: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut pu_types_by_global_benchmark_id = HashMap::new();
+    for global_benchmark in global_benchmarks {
+        let pu_type = match global_benchmark.pu_type() {
+            models::PuType::Cpu => "cpu",
+            models::PuType::Gpu => "gpu",
+        };
+        benchmark_names_by_pu_type
+            .entry(pu_type)
+            .or_default()
+            .push(global_benchmark.name_without_prefix());
+        pu_types_by_global_benchmark_id.insert(global_benchmark.id, pu_type);
+    }
+    for benchmark_names in benchmark_names_by_pu_type.values_mut() {
+        benchmark_names.sort_unstable();
+    }
+
+    let mut category_names: Vec<&String> = categories_file.keys().collect();
+    category_names.sort_unstable();
+
+    let mut lints = Vec::new();
+    for category_name in category_names {
+        let category_pattern_scores = &categories_file[category_name];
+        let benchmark_scores_in_category = match_global_benchmarks_with_category(global_benchmarks, category_pattern_scores);
+        for pu_type in PU_TYPES {
+            let patterns_and_scores = match pu_type {
+                "cpu" => &category_pattern_scores.cpu,
+                _ => &category_pattern_scores.gpu,
+            };
+            let benchmark_names = benchmark_names_by_pu_type
+                .get(pu_type)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let mut add_lint = |severity, description| {
+                lints.push(CategoriesFileLint {
+                    severity,
+                    location: Some((category_name.clone(), pu_type)),
+                    description,
+                })
+            };
+
+            // when a benchmark matches several patterns, the first one is used, so a pattern whose benchmarks
+            // all match earlier patterns has no effect
+            let mut used_patterns = vec![false; patterns_and_scores.patterns().len()];
+            for benchmark_name in benchmark_names {
+                if let Some(pattern_index) = patterns_and_scores.find_matching_pattern(benchmark_name) {
+                    used_patterns[pattern_index] = true;
+                }
+            }
+            for (pattern, used) in patterns_and_scores.patterns().iter().zip(used_patterns) {
+                if pattern.is_default() || used {
+                    continue;
+                }
+                let description = if benchmark_names.iter().any(|benchmark_name| pattern.matches(benchmark_name)) {
+                    format!(
+                        "the pattern {} is never used, since all benchmarks that it matches match earlier patterns",
+                        pattern
+                    )
+                } else {
+                    format!("the pattern {} matches no benchmark", pattern)
+                };
+                add_lint(ProblemSeverity::Warning, description);
+            }
+
+            // a benchmark that matches several patterns with different scores gets the score of the first
+            // one, so reordering the patterns changes its score
+            for benchmark_name in benchmark_names {
+                let matching_patterns: Vec<&BenchmarkPattern> = patterns_and_scores
+                    .patterns()
+                    .iter()
+                    .filter(|pattern| pattern.matches(benchmark_name))
+                    .collect();
+                if matching_patterns
+                    .iter()
+                    .any(|pattern| pattern.score() != matching_patterns[0].score())
+                {
+                    let described_patterns: Vec<String> = matching_patterns
+                        .iter()
+                        .map(|pattern| format!("{} ({})", pattern, pattern.score()))
+                        .collect();
+                    add_lint(
+                        ProblemSeverity::Warning,
+                        format!(
+                            "the benchmark \"{}\" matches the patterns {} with different scores, so it gets the \
+                            score of the first one, and reordering the patterns changes its score",
+                            benchmark_name,
+                            described_patterns.join(", ")
+                        ),
+                    );
+                }
+            }
+
+            if patterns_and_scores.is_empty() {
+                add_lint(
+                    ProblemSeverity::Warning,
+                    format!(
+                        "there are no {} patterns, so all {} benchmarks get the default score of {}",
+                        pu_type, pu_type, DEFAULT_BENCHMARK_SCORE_IN_CATEGORY
+                    ),
+                );
+            } else if !benchmark_names.is_empty() {
+                let pu_type_weights_sum: f32 = benchmark_scores_in_category
+                    .iter()
+                    .filter(|(global_benchmark_id, _)| pu_types_by_global_benchmark_id[*global_benchmark_id] == pu_type)
+                    .map(|(_, score)| score)
+                    .sum();
+                if pu_type_weights_sum == 0.0 {
+                    add_lint(
+                        ProblemSeverity::Warning,
+                        format!("the {} patterns give no weight to any {} benchmark", pu_type, pu_type),
+                    );
+                }
+            }
+        }
+
+        let weights_sum: f32 = benchmark_scores_in_category.values().sum();
+        if !weights_sum.is_normal() {
+            lints.push(CategoriesFileLint {
+                severity: ProblemSeverity::Error,
+                location: None,
+                description: format!(
+                    "the weights of the benchmarks in the category {} sum to zero, so it can't be loaded",
+                    category_name
+                ),
+            });
+        }
+    }
+
+    lints.extend(find_near_duplicate_patterns(categories_file));
+    lints
+}
+
+/// finds patterns that differ only in case, whitespace or punctuation, such as "3DMark" and "3D Mark",
+/// which are probably meant to be the same pattern, but usually don't match the same benchmarks
+fn find_near_duplicate_patterns(categories_file: &CategoriesFile) -> Vec<CategoriesFileLint> {
+    // the places where each spelling of each normalized pattern is used, ordered so that the same
+    // problems are always reported in the same order
+    let mut usages_by_normalized_pattern: BTreeMap<String, BTreeMap<&str, Vec<String>>> = BTreeMap::new();
+    for (category_name, category_pattern_scores) in categories_file {
+        for pu_type in PU_TYPES {
+            let patterns_and_scores = match pu_type {
+                "cpu" => &category_pattern_scores.cpu,
+                _ => &category_pattern_scores.gpu,
+            };
+            for pattern in patterns_and_scores.patterns() {
+                let normalized_pattern: String = pattern
+                    .text()
+                    .chars()
+                    .filter(|c| c.is_alphanumeric() || *c == '&')
+                    .flat_map(char::to_lowercase)
+                    .collect();
+                usages_by_normalized_pattern
+                    .entry(normalized_pattern)
+                    .or_default()
+                    .entry(pattern.text())
+                    .or_default()
+                    .push(format!("{}.{}", category_name, pu_type));
+            }
+        }
+    }
+
+    usages_by_normalized_pattern
+        .into_values()
+        .filter(|usages_by_pattern| usages_by_pattern.len() > 1)
+        .map(|usages_by_pattern| {
+            let described_patterns: Vec<String> = usages_by_pattern
+                .into_iter()
+                .map(|(pattern, mut usages)| {
+                    usages.sort_unstable();
+                    format!("\"{}\" (in {})", pattern, usages.join(", "))
+                })
+                .collect();
+            CategoriesFileLint {
+                severity: ProblemSeverity::Warning,
+                location: None,
+                description: format!(
+                    "the patterns {} differ only in case, whitespace or punctuation",
+                    described_patterns.join(" and ")
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use serde_json::json;
+
+    /// global benchmarks with the given names, which start with the pu type prefix
+    fn global_benchmarks(names: &[&str]) -> Vec<models::GlobalBenchmark> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| models::GlobalBenchmark {
+                id: index as i32 + 1,
+                name: name.to_string(),
+                max: 100.0,
+                sum: BigDecimal::from(100),
+                amount: 2,
+            })
+            .collect()
+    }
+
+    /// the location and description of each lint of the given categories file
+    fn lint(categories_file: serde_json::Value, benchmark_names: &[&str]) -> Vec<(ProblemSeverity, Option<String>, String)> {
+        let categories_file: CategoriesFile = serde_json::from_value(categories_file).unwrap();
+        lint_categories_file(&categories_file, &global_benchmarks(benchmark_names))
+            .into_iter()
+            .map(|lint| {
+                let location = lint
+                    .location
+                    .map(|(category_name, pu_type)| format!("{}.{}", category_name, pu_type));
+                (lint.severity, location, lint.description)
+            })
+            .collect()
+    }
+
+    const BENCHMARKS: &[&str] = &["cKraken 1.1 Total Score", "cCinebench R23 Multi", "g3DMark Time Spy Graphics"];
+
+    #[test]
+    fn a_valid_file_has_no_lints() {
+        let lints = lint(
+            json!({"web": {"cpu": {"Kraken": 2, "Cinebench": 1}, "gpu": {"3DMark": 1}}}),
+            BENCHMARKS,
+        );
+        assert!(lints.is_empty(), "{:?}", lints);
+    }
+
+    #[test]
+    fn patterns_that_match_no_benchmark_are_reported() {
+        let lints = lint(
+            json!({"web": {"cpu": {"Mozzila Kraken": 2, "Cinebench": 1}, "gpu": {"3DMark": 1}}}),
+            BENCHMARKS,
+        );
+        assert_eq!(
+            lints,
+            vec![(
+                ProblemSeverity::Warning,
+                Some("web.cpu".to_string()),
+                "the pattern \"Mozzila Kraken\" matches no benchmark".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn patterns_whose_benchmarks_all_match_earlier_patterns_are_reported() {
+        let lints = lint(
+            json!({"web": {
+                "cpu": [{"pattern": "Kraken", "score": 2}, {"pattern": "Cinebench", "score": 1}, {"regex": "Kraken 1[.]1", "score": 2}],
+                "gpu": {"3DMark": 1}
+            }}),
+            BENCHMARKS,
+        );
+        assert_eq!(lints.len(), 1, "{:?}", lints);
+        assert!(lints[0].2.starts_with("the pattern /Kraken 1[.]1/ is never used"), "{:?}", lints);
+    }
+
+    #[test]
+    fn benchmarks_that_match_patterns_with_different_scores_are_reported() {
+        let categories_file = |kraken_score| {
+            json!({"web": {
+                "cpu": [{"pattern": "Kraken", "score": kraken_score}, {"pattern": "Score&&Total", "score": 2}],
+                "gpu": {"3DMark": 1}
+            }})
+        };
+        let benchmarks = ["cKraken 1.1 Total Score", "cOctane V2 Total Score", "g3DMark Time Spy Graphics"];
+        let lints = lint(categories_file(3), &benchmarks);
+        assert_eq!(
+            lints,
+            vec![(
+                ProblemSeverity::Warning,
+                Some("web.cpu".to_string()),
+                "the benchmark \"Kraken 1.1 Total Score\" matches the patterns \"Kraken\" (3), \"Score&&Total\" (2) \
+                with different scores, so it gets the score of the first one, and reordering the patterns changes its score"
+                    .to_string()
+            )]
+        );
+
+        // when the scores are equal, the order of the patterns doesn't matter
+        let lints = lint(categories_file(2), &benchmarks);
+        assert!(lints.is_empty(), "{:?}", lints);
+    }
+
+    #[test]
+    fn missing_pu_patterns_are_reported() {
+        let lints = lint(json!({"web": {"cpu": {"Kraken": 1}, "gpu": {}}}), BENCHMARKS);
+        assert_eq!(
+            lints,
+            vec![(
+                ProblemSeverity::Warning,
+                Some("web.gpu".to_string()),
+                "there are no gpu patterns, so all gpu benchmarks get the default score of 1".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn patterns_that_give_no_weight_to_a_pu_type_are_reported() {
+        let lints = lint(
+            json!({"web": {"cpu": {"Kraken": 1, "*": 0}, "gpu": {"3DMark": 0}}}),
+            BENCHMARKS,
+        );
+        assert_eq!(
+            lints,
+            vec![(
+                ProblemSeverity::Warning,
+                Some("web.gpu".to_string()),
+                "the gpu patterns give no weight to any gpu benchmark".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn categories_whose_weights_sum_to_zero_are_errors() {
+        let lints = lint(json!({"web": {"cpu": {"*": 0}, "gpu": {"*": 0}}}), BENCHMARKS);
+        assert!(lints.contains(&(
+            ProblemSeverity::Error,
+            None,
+            "the weights of the benchmarks in the category web sum to zero, so it can't be loaded".to_string()
+        )));
+    }
+
+    #[test]
+    fn near_duplicate_patterns_are_reported() {
+        let lints = lint(
+            json!({
+                "gaming": {"cpu": {"Kraken": 1}, "gpu": {"3DMark": 1}},
+                "work": {"cpu": {"Kraken": 1}, "gpu": {"3D Mark": 1, "3DMark": 2}}
+            }),
+            BENCHMARKS,
+        );
+        assert_eq!(
+            lints,
+            vec![
+                (
+                    ProblemSeverity::Warning,
+                    Some("work.gpu".to_string()),
+                    "the pattern \"3D Mark\" matches no benchmark".to_string()
+                ),
+                (
+                    ProblemSeverity::Warning,
+                    None,
+                    "the patterns \"3D Mark\" (in work.gpu) and \"3DMark\" (in gaming.gpu, work.gpu) differ only \
+                    in case, whitespace or punctuation"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -1,28 +1,20 @@
+use crate::benchmark_patterns::PatternScores;
 use crate::errors::*;
+use crate::input_paths::InputPaths;
 use db_access::{models,schema};
 use diesel::prelude::*;
 
 use diesel::PgConnection;
-use serde::Deserialize;
-use std::{collections::HashMap, fs::OpenOptions};
-
-const CATEGORIES_FILE_PATH: &str = "categories.json";
-
-/// this is the default pattern that is used when matching the benchmarks with the categories file,
-/// and the benchmark's name does not match any non-default pattern.
-const DEFAULT_PATTERN: &str = "*";
-
-/// this is the score that is used when matching the benchmarks with the categories file, and the benchmark's name
-/// does not match any non-default pattern, and there is no default pattern (no "*" entry).
-const DEFAULT_BENCHMARK_SCORE_IN_CATEGORY: f32 = 1.0;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::OpenOptions, path::Path};
 
 /// the information about benchmark scores in each category loaded from the categories file
-type CategoriesFile = HashMap<String, CategoryPatternScores>;
+pub(super) type CategoriesFile = HashMap<String, CategoryPatternScores>;
 
-#[derive(Debug, Deserialize)]
-struct CategoryPatternScores {
-    cpu: HashMap<String, f32>,
-    gpu: HashMap<String, f32>,
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(super) struct CategoryPatternScores {
+    pub cpu: PatternScores,
+    pub gpu: PatternScores,
 }
 
 /// the score of each benchmark in each category according to the categories.json file, mapped using the categories' ids
@@ -31,10 +23,10 @@ type BenchmarkScoresInEachCategory = HashMap<i32, BenchmarkScoresInCategory>;
 /// the score of each benchmark, in each pu type, in a specific category, according to the categories.json file, mapped
 /// using the global benchmarks' ids. There is no need to seperate pu types here since the key is the id of a global benchmark,
 /// and that global benchmark can be either a cpu benchmark or a gpu benchmark.
-type BenchmarkScoresInCategory = HashMap<i32, f32>;
+pub(super) type BenchmarkScoresInCategory = HashMap<i32, f32>;
 
 /// loads the categories to the database and performs all required calculations
-pub fn load_categories(db_connection: &PgConnection) -> Result<()> {
+pub fn load_categories(db_connection: &PgConnection, input_paths: &InputPaths) -> Result<()> {
     use schema::global_benchmark::dsl::*;
 
     println!("deleting categories and dependents...");
@@ -44,7 +36,8 @@ pub fn load_categories(db_connection: &PgConnection) -> Result<()> {
     super::delete_categories_and_dependents(db_connection)?;
 
     println!("loading the categories file...");
-    let categories_file = parse_categories_file()?;
+    let categories_file = parse_categories_file(&input_paths.categories_file)?;
+    validate_categories_file(&categories_file)?;
 
     println!("inserting and mapping categories...");
     let categories_id_by_name = insert_and_map_categories(&categories_file, db_connection)?;
@@ -68,7 +61,7 @@ pub fn load_categories(db_connection: &PgConnection) -> Result<()> {
 
     println!("normalizing scores...");
     // normalize the matched scores
-    normalize_bencmark_scores(&mut scores);
+    normalize_bencmark_scores(&mut scores, &categories_id_by_name)?;
 
     println!("inserting benchmark scores in each category...");
     // insert the normalized scores to the database
@@ -88,7 +81,7 @@ fn insert_and_map_categories(
     let new_categories: Vec<models::NewCategory> = categories_file
         .keys()
         .map(|category_name| models::NewCategory {
-            name: &category_name,
+            name: category_name,
         })
         .collect();
 
@@ -115,47 +108,8 @@ fn match_global_benchmarks_with_categories(
 ) -> BenchmarkScoresInEachCategory {
     let mut benchmark_scores_in_each_category = BenchmarkScoresInEachCategory::new();
     for (category_name, category_pattern_scores) in categories_file {
-        // record the scores of all benchmarks in the current category, and then add all of these together
-        // to the benchmark_scores_in_each_category
-        let mut benchmark_scores_in_current_category = BenchmarkScoresInCategory::new();
-
-        // find each benchmark's score by first trying to match him with all the patterns,
-        // and otherwise using the default pattern or the default score (if no default pattern exists)
-        for benchmark in &global_benchmarks {
-            // find the correct patterns and scores map, and the correct result scores map, according to
-            // the benchmark's pu type
-            let benchmarks_patterns_and_scores = match benchmark.pu_type() {
-                models::PuType::Cpu => &category_pattern_scores.cpu,
-                models::PuType::Gpu => &category_pattern_scores.gpu,
-            };
-            let mut found_matching_pattern = false;
-            for (pattern, score) in benchmarks_patterns_and_scores {
-                // skip the default pattern, because here we first want to check all the non-default patterns,
-                // and since a hashmap is used the order of the entries in the file is not preserved, so the default
-                // pattern might not be the last. The check for the default is used later, if non of the non-default
-                // pattern worked
-                if pattern == "*" {
-                    continue;
-                }
-                if benchmark_name_matches_pattern(benchmark.name_without_prefix(), &pattern) {
-                    found_matching_pattern = true;
-
-                    benchmark_scores_in_current_category.insert(benchmark.id, *score);
-
-                    break;
-                }
-            }
-            // if none of the non-default patterns worked for the current benchmark, use the default pattern
-            if !found_matching_pattern {
-                // the score is the value of the default pattern, or the default benchmark score,
-                // if there is no default pattern
-                let score = benchmarks_patterns_and_scores
-                    .get(DEFAULT_PATTERN)
-                    .unwrap_or(&DEFAULT_BENCHMARK_SCORE_IN_CATEGORY);
-
-                benchmark_scores_in_current_category.insert(benchmark.id, *score);
-            }
-        }
+        let benchmark_scores_in_current_category =
+            match_global_benchmarks_with_category(&global_benchmarks, category_pattern_scores);
 
         // here we have found the score of each benchmark in the current cateogry, all that's left is to add it to the
         // results map. But the results map is mapped using each category's id, not name, so we must first find the
@@ -166,21 +120,92 @@ fn match_global_benchmarks_with_categories(
     benchmark_scores_in_each_category
 }
 
-/// normalizes the benchmark scores in each category by dividing each score by the sum of all scores
+/// matches each global benchmark with the patterns of a single category, and gives each benchmark the
+/// score of the pattern that it matched.
+pub(super) fn match_global_benchmarks_with_category(
+    global_benchmarks: &[models::GlobalBenchmark],
+    category_pattern_scores: &CategoryPatternScores,
+) -> BenchmarkScoresInCategory {
+    let mut benchmark_scores_in_category = BenchmarkScoresInCategory::new();
+
+    // find each benchmark's score by first trying to match him with all the patterns,
+    // and otherwise using the default pattern or the default score (if no default pattern exists)
+    for benchmark in global_benchmarks {
+        // find the correct patterns and scores map, and the correct result scores map, according to
+        // the benchmark's pu type
+        let benchmarks_patterns_and_scores = match benchmark.pu_type() {
+            models::PuType::Cpu => &category_pattern_scores.cpu,
+            models::PuType::Gpu => &category_pattern_scores.gpu,
+        };
+        // if none of the non-default patterns match the current benchmark, use the value of the
+        // default pattern, or the default benchmark score if there is no default pattern
+        let score = benchmarks_patterns_and_scores.benchmark_score(benchmark.name_without_prefix());
+        benchmark_scores_in_category.insert(benchmark.id, score);
+    }
+    benchmark_scores_in_category
+}
+
+/// makes sure that all scores in the categories file are finite and non-negative, since a negative
+/// score would make a better benchmark result lower the laptop's score.
+pub(super) fn validate_categories_file(categories_file: &CategoriesFile) -> Result<()> {
+    for (category_name, category_pattern_scores) in categories_file {
+        let patterns = category_pattern_scores
+            .cpu
+            .patterns()
+            .iter()
+            .chain(category_pattern_scores.gpu.patterns());
+        for pattern in patterns {
+            if !pattern.score().is_finite() || pattern.score() < 0.0 {
+                return Err(DataProcessorErrorKind::InvalidBenchmarkScoreInCategory {
+                    category_name: category_name.clone(),
+                    pattern: pattern.to_string(),
+                }
+                .into_empty_data_processor_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// normalizes the benchmark scores in each category by dividing each score by the sum of all scores.
 fn normalize_bencmark_scores(
     benchmark_scores_in_each_category: &mut BenchmarkScoresInEachCategory,
-) {
-    for benchmark_scores_in_category in benchmark_scores_in_each_category.values_mut() {
-        let sum: f32 = benchmark_scores_in_category.values().sum();
-        for score in benchmark_scores_in_category.values_mut() {
-            *score /= sum;
+    categories_id_by_name: &HashMap<String, i32>,
+) -> Result<()> {
+    for (category_id, benchmark_scores_in_category) in benchmark_scores_in_each_category {
+        let category_name = categories_id_by_name
+            .iter()
+            .find(|(_, id)| *id == category_id)
+            .map(|(name, _)| name.as_str())
+            .unwrap_or_default();
+        normalize_benchmark_scores_in_category(benchmark_scores_in_category, category_name)?;
+    }
+    Ok(())
+}
+
+/// normalizes the benchmark scores in a single category by dividing each score by the sum of all scores.
+/// if the scores sum to zero, they can't be normalized, and dividing by the sum would make all of them NaN,
+/// so an error is returned instead.
+pub(super) fn normalize_benchmark_scores_in_category(
+    benchmark_scores_in_category: &mut BenchmarkScoresInCategory,
+    category_name: &str,
+) -> Result<()> {
+    let sum: f32 = benchmark_scores_in_category.values().sum();
+    if !sum.is_normal() {
+        return Err(DataProcessorErrorKind::CategoryScoresSumToZero {
+            category_name: category_name.to_string(),
         }
+        .into_empty_data_processor_error());
     }
+    for score in benchmark_scores_in_category.values_mut() {
+        *score /= sum;
+    }
+    Ok(())
 }
 
 /// accepts a hashmap that maps each category id to the scores of the benchmarks in that category,
 /// converts it to a vector of insertable structs, and inserts it into the database
-fn insert_benchmark_scores_in_each_category(
+pub(super) fn insert_benchmark_scores_in_each_category(
     scores: HashMap<i32, BenchmarkScoresInCategory>,
     db_connection: &PgConnection,
 ) -> Result<()> {
@@ -213,21 +238,10 @@ fn insert_benchmark_scores_in_each_category(
     Ok(())
 }
 
-/// checks if the given benchmark name matches the given pattern. The pattern mentioned here is the pattern that is used in the
-/// categories.json file to match benchmark names to a given score.
-fn benchmark_name_matches_pattern(benchmark_name: &str, pattern: &str) -> bool {
-    for required_sequence in pattern.split("&&") {
-        if !benchmark_name.contains(required_sequence) {
-            return false;
-        }
-    }
-    true
-}
-
-fn parse_categories_file() -> Result<CategoriesFile> {
+pub(super) fn parse_categories_file(file_path: &Path) -> Result<CategoriesFile> {
     let categories_file = OpenOptions::new()
         .read(true)
-        .open(CATEGORIES_FILE_PATH)
+        .open(file_path)
         .into_data_processor_result(DataProcessorErrorKind::FailedToOpenCategoriesFile)?;
     serde_json::de::from_reader(categories_file)
         .into_data_processor_result(DataProcessorErrorKind::FailedToDeserializeCategoriesFile)
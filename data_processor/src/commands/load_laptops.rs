@@ -1,9 +1,10 @@
 use crate::errors::*;
+use crate::exchange_rates::ExchangeRates;
+use crate::input_paths::InputPaths;
 use crate::laptop_set::LaptopInformation;
 use crate::laptop_set::LaptopInfosByName;
 use crate::laptop_set::LaptopPuBenchmarksData;
 use crate::laptop_set::LaptopSet;
-use crate::laptop_set::LaptopsFileEntry;
 use bigdecimal::BigDecimal;
 use bigdecimal::Zero;
 use db_access::{models, schema};
@@ -11,27 +12,18 @@ use db_access::models::NewLaptopImage;
 use diesel::prelude::*;
 
 use diesel::PgConnection;
-use lazy_static::lazy_static;
-use regex::Regex;
+use super::validate_laptops::validate_laptops_files;
 use std::collections::HashMap;
-use std::fs::read_dir;
-use std::fs::OpenOptions;
-
-lazy_static! {
-    /// the regex used to match laptops files. examples for a valid file name: ivory-laptops.json
-    static ref LAPTOPS_FILES_REGEX:Regex = Regex::new(r"[a-z]+-laptops[.]json").unwrap();
-}
-
-const LAPTOPS_DIR_PATH: &str = "laptops";
+use std::path::Path;
 
 // the info about each global benchmark info. The name is not included here
 // since this struct is stored in a hashmap that maps each global benchmark's name
 // to this struct which contains its info.
 #[derive(Debug)]
-struct GlobalBenchmarkInfo {
-    max: f32,
-    sum: BigDecimal,
-    amount: i64,
+pub(super) struct GlobalBenchmarkInfo {
+    pub(super) max: f32,
+    pub(super) sum: BigDecimal,
+    pub(super) amount: i64,
 }
 impl GlobalBenchmarkInfo {
     fn new() -> Self {
@@ -54,7 +46,7 @@ impl GlobalBenchmarkInfo {
 
 /// the minimum and maximum laptop price
 #[derive(Debug)]
-struct PriceLimits {
+pub(super) struct PriceLimits {
     max: Option<f32>,
     min: Option<f32>,
 }
@@ -91,8 +83,9 @@ impl PriceLimits {
             }
         }
     }
-    /// attempts to convert this PriceLimits struct to an insertable NewPriceLimits struct
-    pub fn to_insertable(&self) -> Option<models::NewPriceLimits> {
+    /// attempts to convert this PriceLimits struct to an insertable NewPriceLimits struct, whose
+    /// prices are in the given base currency
+    pub fn to_insertable<'a>(&self, base_currency: &'a str) -> Option<models::NewPriceLimits<'a>> {
         Some(models::NewPriceLimits {
             // note that the id is always 0 since we only want one such struct to exist in the
             // database at a time, and to use the ON CONFLICT statement to achieve upsert functionality,
@@ -101,12 +94,13 @@ impl PriceLimits {
             id: 0,
             max_price: self.max?,
             min_price: self.min?,
+            currency: base_currency,
         })
     }
 }
 
 /// loads the laptops to the database and performs all required calculations
-pub fn load_laptops(db_connection: &PgConnection) -> Result<()> {
+pub fn load_laptops(db_connection: &PgConnection, input_paths: &InputPaths) -> Result<()> {
     println!("deleting categories and dependents...");
     // the categories and the benchmark scores in categories are not up to date anymore
     // with the new benchmarks, so we should delete them, and to delete them
@@ -115,13 +109,15 @@ pub fn load_laptops(db_connection: &PgConnection) -> Result<()> {
 
     println!("deleting laptops, benchmarks and global benchmarks...");
     // delete all laptops, benchmarks and global benchmarks from the database,
-    // so that we don't have duplicates. note that no update mechanism is used here
-    // even though it could increase performance, because the data-processor currently
-    // only needs to run once, and performs no recalculations.
+    // so that we don't have duplicates. the `update laptops` command only modifies
+    // the laptops that changed, but this command always rebuilds everything from scratch.
     delete_laptops_and_dependents(db_connection)?;
 
+    println!("loading the exchange rates file...");
+    let exchange_rates = ExchangeRates::load(&input_paths.exchange_rates_file)?;
+
     println!("loading the laptops file...");
-    let laptops = parse_laptops_files()?;
+    let laptops = parse_laptops_files(&input_paths.laptops_dir, &exchange_rates)?;
 
     println!("calculating global benchmarks...");
     // calculate the global benchmarks
@@ -146,8 +142,9 @@ pub fn load_laptops(db_connection: &PgConnection) -> Result<()> {
     )?;
     println!("inserted {} laptops", laptops.len());
 
-    println!("inserting price limits...");
-    insert_price_limits(&price_limits, db_connection)?;
+    println!("inserting price limits and exchange rates...");
+    insert_price_limits(&price_limits, &exchange_rates.base_currency, db_connection)?;
+    insert_exchange_rates(&exchange_rates, db_connection)?;
 
     println!("successfully loaded laptops");
     Ok(())
@@ -187,25 +184,53 @@ fn insert_laptops_and_dependents(
     global_benchmarks_id_by_name: &HashMap<String, i32>,
     db_connection: &PgConnection,
 ) -> Result<PriceLimits> {
-    use schema::benchmark;
-    use schema::laptop;
-    use schema::laptop_image;
-    use schema::laptop_specs;
+    let mut price_limits = PriceLimits::new();
 
-    /// returns a list of insertable laptop images from the laptop
-    fn generate_image_urls_to_insertable_structs(
-        laptop: &LaptopInformation,
-        id: i32,
-    ) -> Vec<NewLaptopImage> {
-        let mut laptop_images = Vec::new();
-        for image_url in &laptop.image_urls {
-            laptop_images.push(models::NewLaptopImage {
-                laptop_id: id,
-                image_url: &image_url,
-            });
-        }
-        laptop_images
+    for (laptop_name, laptop_info) in laptops {
+        // update the price limits according to the laptop's price
+        price_limits.update(laptop_info.price);
+
+        let inserted_laptop_id = insert_laptop(laptop_name, laptop_info, db_connection)?;
+        insert_benchmarks(inserted_laptop_id, laptop_info, global_benchmarks_id_by_name, db_connection)?;
+        insert_images_and_specs(inserted_laptop_id, laptop_info, db_connection)?;
     }
+    Ok(price_limits)
+}
+
+/// inserts a single laptop, without its dependents, and returns its id
+pub(super) fn insert_laptop(
+    laptop_name: &str,
+    laptop_info: &LaptopInformation,
+    db_connection: &PgConnection,
+) -> Result<i32> {
+    use schema::laptop;
+
+    diesel::insert_into(laptop::table)
+        .values(models::NewLaptop {
+            name: laptop_name,
+            url: &laptop_info.url,
+            price: laptop_info.price,
+            cpu: &laptop_info.cpu,
+            gpu: &laptop_info.gpu,
+            brand: laptop_info.brand.as_deref(),
+            model: laptop_info.model.as_deref(),
+            currency: &laptop_info.currency,
+            listed_price: laptop_info.listed_price,
+            store: &laptop_info.store,
+        })
+        .returning(laptop::id)
+        .get_result(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)
+}
+
+/// inserts the benchmarks of the laptop with the given id. the global benchmarks of all of them must already exist.
+pub(super) fn insert_benchmarks(
+    laptop_id: i32,
+    laptop_info: &LaptopInformation,
+    global_benchmarks_id_by_name: &HashMap<String, i32>,
+    db_connection: &PgConnection,
+) -> Result<()> {
+    use schema::benchmark;
 
     /// converts the given benchmarks to the insertable benchmark struct NewBenchmark,
     /// and inserts them into the insertable_structs vector.
@@ -233,69 +258,67 @@ fn insert_laptops_and_dependents(
         }
     }
 
-    let mut price_limits = PriceLimits::new();
-
-    for (laptop_name, laptop_info) in laptops {
-        // update the price limits according to the laptop's price
-        price_limits.update(laptop_info.price);
+    // convert all benchmarks into a vector of insertable benchmark structs
+    // so that we can insert them to the database
+    let mut new_benchmarks = Vec::new();
+    convert_benchmarks_to_insertable_structs(
+        &laptop_info.cpu_bench,
+        &mut new_benchmarks,
+        'c',
+        laptop_id,
+        global_benchmarks_id_by_name,
+    );
+    convert_benchmarks_to_insertable_structs(
+        &laptop_info.gpu_bench,
+        &mut new_benchmarks,
+        'g',
+        laptop_id,
+        global_benchmarks_id_by_name,
+    );
 
-        // insert the laptop and get the laptop's id
-        let inserted_laptop_id: i32 = diesel::insert_into(laptop::table)
-            .values(models::NewLaptop {
-                name: laptop_name,
-                url: &laptop_info.url,
-                price: laptop_info.price,
-                cpu: &laptop_info.cpu,
-                gpu: &laptop_info.gpu,
-            })
-            .returning(laptop::id)
-            .get_result(db_connection)
-            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    // insert the benchmarks into the database
+    diesel::insert_into(benchmark::table)
+        .values(new_benchmarks.as_slice())
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
+}
 
-        // convert all benchmarks into a vector of insertable benchmark structs
-        // so that we can insert them to the database
-        let mut new_benchmarks = Vec::new();
-        convert_benchmarks_to_insertable_structs(
-            &laptop_info.cpu_bench,
-            &mut new_benchmarks,
-            'c',
-            inserted_laptop_id,
-            global_benchmarks_id_by_name,
-        );
-        convert_benchmarks_to_insertable_structs(
-            &laptop_info.gpu_bench,
-            &mut new_benchmarks,
-            'g',
-            inserted_laptop_id,
-            global_benchmarks_id_by_name,
-        );
-
-        // insert the benchmarks into the database
-        diesel::insert_into(benchmark::table)
-            .values(new_benchmarks.as_slice())
-            .execute(db_connection)
-            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+/// inserts the image urls and the specs of the laptop with the given id
+pub(super) fn insert_images_and_specs(
+    laptop_id: i32,
+    laptop_info: &LaptopInformation,
+    db_connection: &PgConnection,
+) -> Result<()> {
+    use schema::laptop_image;
+    use schema::laptop_specs;
 
-        // insert the image urls
-        let new_image_urls =
-            generate_image_urls_to_insertable_structs(laptop_info, inserted_laptop_id);
-        diesel::insert_into(laptop_image::table)
-            .values(new_image_urls.as_slice())
-            .execute(db_connection)
-            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    // insert the image urls
+    let new_image_urls: Vec<NewLaptopImage> = laptop_info
+        .image_urls
+        .iter()
+        .map(|image_url| models::NewLaptopImage {
+            laptop_id,
+            image_url,
+        })
+        .collect();
+    diesel::insert_into(laptop_image::table)
+        .values(new_image_urls.as_slice())
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
 
-        // insert the laptop specs
-        let new_specs = models::NewLaptopSpecs {
-            laptop_id: inserted_laptop_id,
-            ram_gigabytes: laptop_info.ram_gigabytes,
-            weight_grams: laptop_info.weight_grams,
-        };
-        diesel::insert_into(laptop_specs::table)
-            .values([new_specs].as_slice())
-            .execute(db_connection)
-            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
-    }
-    Ok(price_limits)
+    // insert the laptop specs
+    let new_specs = models::NewLaptopSpecs {
+        laptop_id,
+        ram_gigabytes: laptop_info.ram_gigabytes,
+        weight_grams: laptop_info.weight_grams,
+        storage_gigabytes: laptop_info.storage_gigabytes,
+    };
+    diesel::insert_into(laptop_specs::table)
+        .values([new_specs].as_slice())
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
 }
 
 /// calculates the information about each global benchmark according to the benchmarks in the laptops file.
@@ -303,7 +326,7 @@ fn insert_laptops_and_dependents(
 /// benchmarks each time, and instead it precalculates them and inserts them in only a single query. Thus, there
 /// is no need to update the global benchmarks when inserting the benchmarks, and the whole process of calculating
 /// the global benchmarks was redurced to a single query.
-fn calculate_global_benchmarks(
+pub(super) fn calculate_global_benchmarks(
     laptops: &LaptopInfosByName,
 ) -> HashMap<String, GlobalBenchmarkInfo> {
     /// updates the info of the global benchmarks according to each of given the benchmarks
@@ -347,7 +370,7 @@ fn insert_and_map_global_benchmarks(
     let mut new_global_benchmarks = Vec::new();
     for (global_benchmark_name, global_benchmark_info) in &infos {
         new_global_benchmarks.push(models::NewGlobalBenchmark {
-            name: &global_benchmark_name,
+            name: global_benchmark_name,
             max: global_benchmark_info.max,
             sum: &global_benchmark_info.sum,
             amount: global_benchmark_info.amount,
@@ -369,60 +392,35 @@ fn insert_and_map_global_benchmarks(
     Ok(global_benchmarks_map)
 }
 
-fn parse_laptops_files() -> Result<LaptopInfosByName> {
-    let mut laptops = LaptopSet::new();
+/// parses all laptops files, converting the prices of the laptops to the base currency. the files are
+/// validated first, and if any of them has errors, all problems are reported and nothing is parsed.
+pub(super) fn parse_laptops_files(laptops_dir: &Path, exchange_rates: &ExchangeRates) -> Result<LaptopInfosByName> {
+    let validation = validate_laptops_files(laptops_dir, exchange_rates)?;
+    validation.report()?;
 
-    for laptops_dir_entry in read_dir(LAPTOPS_DIR_PATH)
-        .into_data_processor_result(DataProcessorErrorKind::FailedToReadLaptopsDirectory)?
-    {
-        let laptops_dir_entry = laptops_dir_entry
-            .into_data_processor_result(DataProcessorErrorKind::FailedToReadLaptopsDirectory)?;
-
-        // if the entry is a file
-        if laptops_dir_entry.path().is_file() {
-            let file_name_raw = laptops_dir_entry.file_name();
-            let file_name = file_name_raw.to_string_lossy();
-
-            // if the file's name matches the laptops files regex, load the file
-            if LAPTOPS_FILES_REGEX.is_match(&file_name) {
-                // find the path of the file
-                let file_path = format!("{}/{}", LAPTOPS_DIR_PATH, file_name);
-
-                let laptops_file = OpenOptions::new()
-                    .read(true)
-                    .open(&file_path)
-                    .into_data_processor_result(
-                        DataProcessorErrorKind::FailedToOpenLaptopsFile {
-                            name: file_name.to_string(),
-                        },
-                    )?;
-                let new_laptops: Vec<LaptopsFileEntry> = serde_json::de::from_reader(laptops_file)
-                    .into_data_processor_result(
-                        DataProcessorErrorKind::FailedToDeserializeLaptopsFile {
-                            name: file_name.to_string(),
-                        },
-                    )?;
-
-                for new_laptop in new_laptops {
-                    laptops.update(new_laptop);
-                }
-            }
-        }
+    let mut laptops = LaptopSet::new();
+    for valid_entry in validation.valid_entries {
+        laptops.update(valid_entry.entry, valid_entry.price, &valid_entry.store);
     }
-
     Ok(laptops.laptop_infos_by_name())
 }
 
-/// saves the given price limits to the database, if it actually contains the price limits
-/// (the min and max fields are not None)
-fn insert_price_limits(price_limits: &PriceLimits, db_connection: &PgConnection) -> Result<()> {
+/// saves the given price limits, which are in the given base currency, to the database, if it actually
+/// contains the price limits (the min and max fields are not None)
+pub(super) fn insert_price_limits(
+    price_limits: &PriceLimits,
+    base_currency: &str,
+    db_connection: &PgConnection,
+) -> Result<()> {
     // notice the rename here to avoid conflicting with the argument called price_limits
-    use schema::price_limits::dsl::{id, max_price, min_price, price_limits as price_limits_table};
+    use schema::price_limits::dsl::{
+        currency, id, max_price, min_price, price_limits as price_limits_table,
+    };
 
     // in case we failed to convert the price limits to an insertable struct, it means that there is no max or min score,
     // which means that no laptops were inserted. this should never happen, but just in case it does, we should just not
     // save anything in the database, and return.
-    let insertable_price_limits = match price_limits.to_insertable() {
+    let insertable_price_limits = match price_limits.to_insertable(base_currency) {
         Some(v) => v,
         None => return Ok(()),
     };
@@ -434,9 +432,31 @@ fn insert_price_limits(price_limits: &PriceLimits, db_connection: &PgConnection)
         .set((
             max_price.eq(insertable_price_limits.max_price),
             min_price.eq(insertable_price_limits.min_price),
+            currency.eq(insertable_price_limits.currency),
         ))
         .execute(db_connection)
         .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
 
     Ok(())
 }
+
+/// replaces the exchange rates in the database with the given ones, so that the selector could
+/// convert prices from and to the base currency.
+pub(super) fn insert_exchange_rates(exchange_rates: &ExchangeRates, db_connection: &PgConnection) -> Result<()> {
+    use schema::exchange_rate;
+
+    diesel::delete(exchange_rate::table)
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+
+    let new_exchange_rates: Vec<models::NewExchangeRate> = exchange_rates
+        .currencies_and_rates()
+        .into_iter()
+        .map(|(currency, rate)| models::NewExchangeRate { currency, rate })
+        .collect();
+    diesel::insert_into(exchange_rate::table)
+        .values(new_exchange_rates.as_slice())
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
+}
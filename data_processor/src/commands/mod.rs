@@ -2,6 +2,16 @@ mod load_categories;
 mod load_laptops;
 mod calculate_scores;
 mod reload_all;
+mod fit_category_weights;
+mod generations;
+mod update_laptops;
+mod single_category;
+mod reload_dry_run;
+mod validate_laptops;
+mod lint_categories;
+
+use std::{fmt, fs, io};
+use std::path::Path;
 
 use diesel::PgConnection;
 use diesel::prelude::*;
@@ -12,6 +22,13 @@ pub use load_categories::*;
 pub use load_laptops::*;
 pub use calculate_scores::*;
 pub use reload_all::*;
+pub use fit_category_weights::*;
+pub use generations::*;
+pub use update_laptops::*;
+pub use single_category::*;
+pub use reload_dry_run::*;
+pub use validate_laptops::*;
+pub use lint_categories::*;
 
 // this function is placed in this file because 2 different commands are using it 
 /// deletes all categories and all documents that depend on it from the database
@@ -33,3 +50,33 @@ fn delete_categories_and_dependents(db_connection: &PgConnection)->Result<()>{
         .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
     Ok(())
 }
+
+/// writes a report file, creating its directory if it doesn't exist yet. the report of an earlier run is
+/// replaced, so a warning is printed when the file already exists.
+fn write_report_file(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if path.exists() {
+        println!("warning: overwriting the report of an earlier run at {}", path.display());
+    }
+    fs::write(path, contents)
+}
+
+/// how severe a problem that was found in an input file is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProblemSeverity {
+    /// the file can't be loaded until the problem is fixed
+    Error,
+    /// the file can be loaded, but the problem is probably a mistake in it
+    Warning,
+}
+
+impl fmt::Display for ProblemSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemSeverity::Error => write!(f, "error"),
+            ProblemSeverity::Warning => write!(f, "warning"),
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use diesel::PgConnection;
 use crate::errors::*;
+use crate::input_paths::InputPaths;
 use super::calculate_scores;
 use super::load_laptops::load_laptops;
 use super::load_categories::load_categories;
 
-pub fn reload_all(db_connection: &PgConnection)->Result<()>{
-    load_laptops(db_connection)?;
-    load_categories(db_connection)?;
+pub fn reload_all(db_connection: &PgConnection, input_paths: &InputPaths)->Result<()>{
+    load_laptops(db_connection, input_paths)?;
+    load_categories(db_connection, input_paths)?;
     calculate_scores(db_connection)
 }
\ No newline at end of file
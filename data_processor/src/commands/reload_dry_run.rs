@@ -0,0 +1,707 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+
+use crate::errors::*;
+use crate::exchange_rates::ExchangeRates;
+use crate::input_paths::InputPaths;
+use crate::laptop_set::LaptopInfosByName;
+use db_access::{models, schema};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::Serialize;
+
+use super::calculate_scores::{
+    calculate_laptop_scores_in_each_category, map_global_benchmarks, verify_scores_are_finite, MappedBenchmarkScoresInCategories,
+    MappedBenchmarks,
+};
+use super::load_categories::{
+    match_global_benchmarks_with_category, normalize_benchmark_scores_in_category, parse_categories_file,
+    validate_categories_file, CategoriesFile,
+};
+use super::load_laptops::{calculate_global_benchmarks, parse_laptops_files};
+use super::write_report_file;
+
+const TEXT_REPORT_FILE_NAME: &str = "reload_dry_run_report.txt";
+const JSON_REPORT_FILE_NAME: &str = "reload_dry_run_report.json";
+
+/// the max amount of ranking movements that are reported for each category
+const REPORT_MOVEMENTS_AMOUNT: usize = 10;
+
+/// the parts of a dataset that are compared by the dry run, identified by name rather than by id, since
+/// the ids of the reloaded dataset are only known once it is inserted
+#[derive(Debug, Default)]
+struct ComparedDataset {
+    prices_by_laptop_name: HashMap<String, f32>,
+    /// the max of each global benchmark, mapped by the global benchmark's pu type and name without prefix
+    maxes_by_global_benchmark: HashMap<(&'static str, String), f32>,
+    /// the names of the laptops in each category, from best to worst
+    rankings_by_category_name: HashMap<String, Vec<String>>,
+}
+
+/// the changes that a reload would make
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReloadReport {
+    added_laptops: Vec<String>,
+    removed_laptops: Vec<String>,
+    price_changes: Vec<PriceChange>,
+    global_benchmark_max_changes: Vec<GlobalBenchmarkMaxChange>,
+    added_categories: Vec<String>,
+    removed_categories: Vec<String>,
+    ranking_movements: Vec<CategoryRankingMovements>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PriceChange {
+    laptop: String,
+    old_price: f32,
+    new_price: f32,
+}
+
+/// a change of the max of a global benchmark. the max is missing on the side where the global benchmark
+/// doesn't exist.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GlobalBenchmarkMaxChange {
+    pu_type: &'static str,
+    benchmark: String,
+    old_max: Option<f32>,
+    new_max: Option<f32>,
+}
+
+/// the largest movements of the laptops that are ranked in a category both before and after the reload
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CategoryRankingMovements {
+    category: String,
+    movements: Vec<RankingMovement>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RankingMovement {
+    laptop: String,
+    old_rank: usize,
+    new_rank: usize,
+}
+
+/// calculates the dataset that `reload all` would create from the laptops and categories files, without
+/// writing it to the database, and writes a report of how it differs from the dataset that the selector
+/// currently uses, both as text and as json.
+pub fn reload_dry_run(db_connection: &PgConnection, input_paths: &InputPaths) -> Result<()> {
+    println!("loading the exchange rates file...");
+    let exchange_rates = ExchangeRates::load(&input_paths.exchange_rates_file)?;
+
+    println!("loading the laptops files...");
+    let laptops = parse_laptops_files(&input_paths.laptops_dir, &exchange_rates)?;
+
+    println!("loading the categories file...");
+    let categories_file = parse_categories_file(&input_paths.categories_file)?;
+    validate_categories_file(&categories_file)?;
+
+    println!("calculating the reloaded scores...");
+    let reloaded_dataset = calculate_reloaded_dataset(&laptops, &categories_file)?;
+
+    println!("loading the current dataset...");
+    let current_dataset = load_current_dataset(db_connection)?;
+
+    let report = compare_datasets(&current_dataset, &reloaded_dataset);
+
+    println!("writing the reports...");
+    let serialized_report = serde_json::to_string_pretty(&report)
+        .into_data_processor_result(DataProcessorErrorKind::FailedToWriteReloadDryRunReport)?;
+    let json_report_file_path = input_paths.report_file(JSON_REPORT_FILE_NAME);
+    write_report_file(&json_report_file_path, &serialized_report)
+        .into_data_processor_result(DataProcessorErrorKind::FailedToWriteReloadDryRunReport)?;
+    let text_report = format_report(&report);
+    let text_report_file_path = input_paths.report_file(TEXT_REPORT_FILE_NAME);
+    write_report_file(&text_report_file_path, &text_report)
+        .into_data_processor_result(DataProcessorErrorKind::FailedToWriteReloadDryRunReport)?;
+
+    print!("{}", text_report);
+    println!(
+        "successfully wrote {} and {}",
+        text_report_file_path.display(),
+        json_report_file_path.display()
+    );
+    Ok(())
+}
+
+/// performs the calculations of `load laptops`, `load categories` and `calculate scores` in memory. the laptops,
+/// global benchmarks and categories are given ids according to the order of their names.
+fn calculate_reloaded_dataset(laptops: &LaptopInfosByName, categories_file: &CategoriesFile) -> Result<ComparedDataset> {
+    let mut global_benchmark_infos: Vec<_> = calculate_global_benchmarks(laptops).into_iter().collect();
+    global_benchmark_infos.sort_unstable_by(|(name1, _), (name2, _)| name1.cmp(name2));
+    let global_benchmarks: Vec<models::GlobalBenchmark> = global_benchmark_infos
+        .into_iter()
+        .zip(1..)
+        .map(|((name, info), id)| models::GlobalBenchmark {
+            id,
+            name,
+            max: info.max,
+            sum: info.sum,
+            amount: info.amount,
+        })
+        .collect();
+    let global_benchmark_ids_by_name: HashMap<&str, i32> = global_benchmarks
+        .iter()
+        .map(|global_benchmark| (global_benchmark.name.as_str(), global_benchmark.id))
+        .collect();
+
+    let mut laptop_names: Vec<&str> = laptops.keys().map(String::as_str).collect();
+    laptop_names.sort_unstable();
+    let mut mapped_benchmarks = MappedBenchmarks::new();
+    for (laptop_name, laptop_id) in laptop_names.iter().zip(1..) {
+        let laptop_info = &laptops[*laptop_name];
+        let prefixed_benchmarks = laptop_info
+            .cpu_bench
+            .iter()
+            .map(|(benchmark_name, score)| (format!("c{}", benchmark_name), *score))
+            .chain(laptop_info.gpu_bench.iter().map(|(benchmark_name, score)| (format!("g{}", benchmark_name), *score)));
+        // like the stored benchmarks, a laptop without benchmarks has no entry, and thus no scores
+        for (global_benchmark_name, score) in prefixed_benchmarks {
+            mapped_benchmarks
+                .entry(laptop_id)
+                .or_default()
+                .insert(global_benchmark_ids_by_name[global_benchmark_name.as_str()], score);
+        }
+    }
+
+    let mut category_names: Vec<&str> = categories_file.keys().map(String::as_str).collect();
+    category_names.sort_unstable();
+    let mut mapped_benchmark_scores_in_categories = MappedBenchmarkScoresInCategories::new();
+    for (category_name, category_id) in category_names.iter().zip(1..) {
+        let mut benchmark_scores_in_category =
+            match_global_benchmarks_with_category(&global_benchmarks, &categories_file[*category_name]);
+        normalize_benchmark_scores_in_category(&mut benchmark_scores_in_category, category_name)?;
+        mapped_benchmark_scores_in_categories.insert(category_id, benchmark_scores_in_category);
+    }
+
+    let scores = calculate_laptop_scores_in_each_category(
+        &mapped_benchmarks,
+        &mapped_benchmark_scores_in_categories,
+        &map_global_benchmarks(&global_benchmarks),
+    );
+    verify_scores_are_finite(&scores)?;
+
+    let name_by_id = |names: &[&str], id: i32| names[id as usize - 1].to_string();
+    Ok(ComparedDataset {
+        prices_by_laptop_name: laptops
+            .iter()
+            .map(|(laptop_name, laptop_info)| (laptop_name.clone(), laptop_info.price))
+            .collect(),
+        maxes_by_global_benchmark: global_benchmarks
+            .iter()
+            .map(|global_benchmark| (global_benchmark_key(global_benchmark), global_benchmark.max))
+            .collect(),
+        rankings_by_category_name: rank_laptops_in_each_category(
+            scores
+                .into_iter()
+                .map(|score| {
+                    (
+                        name_by_id(&category_names, score.category_id),
+                        name_by_id(&laptop_names, score.laptop_id),
+                        score.score,
+                    )
+                })
+                .collect(),
+        ),
+    })
+}
+
+/// loads the parts of the active generation's dataset that are compared by the dry run
+fn load_current_dataset(db_connection: &PgConnection) -> Result<ComparedDataset> {
+    use schema::{category, global_benchmark, laptop, laptop_score_in_category};
+
+    let laptop_names_and_prices: Vec<(String, f32)> = laptop::table
+        .select((laptop::name, laptop::price))
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let global_benchmarks: Vec<models::GlobalBenchmark> = global_benchmark::table
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let scores: Vec<(String, String, f32)> = laptop_score_in_category::table
+        .inner_join(category::table)
+        .inner_join(laptop::table)
+        .select((category::name, laptop::name, laptop_score_in_category::score))
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let mut rankings_by_category_name = rank_laptops_in_each_category(scores);
+
+    // a category without laptops has no scores, but should still not be reported as added
+    let category_names: Vec<String> = category::table
+        .select(category::name)
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    for category_name in category_names {
+        rankings_by_category_name.entry(category_name).or_default();
+    }
+
+    Ok(ComparedDataset {
+        prices_by_laptop_name: laptop_names_and_prices.into_iter().collect(),
+        maxes_by_global_benchmark: global_benchmarks
+            .iter()
+            .map(|global_benchmark| (global_benchmark_key(global_benchmark), global_benchmark.max))
+            .collect(),
+        rankings_by_category_name,
+    })
+}
+
+/// identifies a global benchmark by its pu type and its name without the pu type prefix
+fn global_benchmark_key(global_benchmark: &models::GlobalBenchmark) -> (&'static str, String) {
+    let pu_type = match global_benchmark.pu_type() {
+        models::PuType::Cpu => "cpu",
+        models::PuType::Gpu => "gpu",
+    };
+    (pu_type, global_benchmark.name_without_prefix().to_string())
+}
+
+/// ranks the laptops in each category by their score, from best to worst, given the category name, laptop
+/// name and score of each laptop score in category. laptops with equal scores are ordered by their name.
+fn rank_laptops_in_each_category(scores: Vec<(String, String, f32)>) -> HashMap<String, Vec<String>> {
+    let mut scores_by_category_name: HashMap<String, Vec<(String, f32)>> = HashMap::new();
+    for (category_name, laptop_name, score) in scores {
+        scores_by_category_name
+            .entry(category_name)
+            .or_default()
+            .push((laptop_name, score));
+    }
+    scores_by_category_name
+        .into_iter()
+        .map(|(category_name, mut scores)| {
+            scores.sort_unstable_by(|(name1, score1), (name2, score2)| score2.total_cmp(score1).then(name1.cmp(name2)));
+            let ranking = scores.into_iter().map(|(laptop_name, _)| laptop_name).collect();
+            (category_name, ranking)
+        })
+        .collect()
+}
+
+/// finds the differences between the current and the reloaded datasets. all lists are sorted so that
+/// the report is stable.
+fn compare_datasets(current: &ComparedDataset, reloaded: &ComparedDataset) -> ReloadReport {
+    let mut report = ReloadReport {
+        added_laptops: missing_keys(&reloaded.prices_by_laptop_name, &current.prices_by_laptop_name),
+        removed_laptops: missing_keys(&current.prices_by_laptop_name, &reloaded.prices_by_laptop_name),
+        added_categories: missing_keys(&reloaded.rankings_by_category_name, &current.rankings_by_category_name),
+        removed_categories: missing_keys(&current.rankings_by_category_name, &reloaded.rankings_by_category_name),
+        ..ReloadReport::default()
+    };
+
+    for (laptop_name, new_price) in &reloaded.prices_by_laptop_name {
+        match current.prices_by_laptop_name.get(laptop_name) {
+            Some(old_price) if old_price != new_price => report.price_changes.push(PriceChange {
+                laptop: laptop_name.clone(),
+                old_price: *old_price,
+                new_price: *new_price,
+            }),
+            _ => {}
+        }
+    }
+    report.price_changes.sort_unstable_by(|change1, change2| change1.laptop.cmp(&change2.laptop));
+
+    // an ordered set is used so that the changes are ordered by pu type and then by name
+    let global_benchmark_keys: BTreeSet<_> = current
+        .maxes_by_global_benchmark
+        .keys()
+        .chain(reloaded.maxes_by_global_benchmark.keys())
+        .collect();
+    for (pu_type, benchmark) in global_benchmark_keys {
+        let key = (*pu_type, benchmark.clone());
+        let old_max = current.maxes_by_global_benchmark.get(&key).copied();
+        let new_max = reloaded.maxes_by_global_benchmark.get(&key).copied();
+        if old_max != new_max {
+            report.global_benchmark_max_changes.push(GlobalBenchmarkMaxChange {
+                pu_type,
+                benchmark: benchmark.clone(),
+                old_max,
+                new_max,
+            });
+        }
+    }
+
+    let mut category_names: Vec<&String> = reloaded
+        .rankings_by_category_name
+        .keys()
+        .filter(|category_name| current.rankings_by_category_name.contains_key(*category_name))
+        .collect();
+    category_names.sort_unstable();
+    for category_name in category_names {
+        let movements = find_largest_movements(
+            &current.rankings_by_category_name[category_name],
+            &reloaded.rankings_by_category_name[category_name],
+        );
+        if !movements.is_empty() {
+            report.ranking_movements.push(CategoryRankingMovements {
+                category: category_name.clone(),
+                movements,
+            });
+        }
+    }
+    report
+}
+
+/// the sorted keys of the first map that the second map doesn't contain
+fn missing_keys<V1, V2>(map: &HashMap<String, V1>, other_map: &HashMap<String, V2>) -> Vec<String> {
+    let mut result: Vec<String> = map.keys().filter(|key| !other_map.contains_key(*key)).cloned().collect();
+    result.sort_unstable();
+    result
+}
+
+/// finds the largest movements of the laptops that are in both rankings. movements of the same size are
+/// ordered by the new rank, so that movements at the top come first.
+fn find_largest_movements(old_ranking: &[String], new_ranking: &[String]) -> Vec<RankingMovement> {
+    let old_ranks: HashMap<&str, usize> = old_ranking
+        .iter()
+        .enumerate()
+        .map(|(rank, laptop_name)| (laptop_name.as_str(), rank + 1))
+        .collect();
+    let mut movements: Vec<RankingMovement> = new_ranking
+        .iter()
+        .enumerate()
+        .filter_map(|(rank, laptop_name)| {
+            let old_rank = *old_ranks.get(laptop_name.as_str())?;
+            Some(RankingMovement {
+                laptop: laptop_name.clone(),
+                old_rank,
+                new_rank: rank + 1,
+            })
+        })
+        .filter(|movement| movement.old_rank != movement.new_rank)
+        .collect();
+    movements.sort_by(|movement1, movement2| {
+        let size = |movement: &RankingMovement| movement.old_rank.abs_diff(movement.new_rank);
+        size(movement2).cmp(&size(movement1)).then(movement1.new_rank.cmp(&movement2.new_rank))
+    });
+    movements.truncate(REPORT_MOVEMENTS_AMOUNT);
+    movements
+}
+
+/// describes the report in a human readable way
+fn format_report(report: &ReloadReport) -> String {
+    fn write_names(text: &mut String, title: &str, names: &[String]) {
+        writeln!(text, "{} ({}):", title, names.len()).unwrap();
+        for name in names {
+            writeln!(text, "  {}", name).unwrap();
+        }
+    }
+    fn format_max(max: Option<f32>) -> String {
+        max.map(|max| max.to_string()).unwrap_or_else(|| "none".to_string())
+    }
+
+    let mut text = String::new();
+    write_names(&mut text, "added laptops", &report.added_laptops);
+    write_names(&mut text, "removed laptops", &report.removed_laptops);
+
+    writeln!(text, "price changes ({}):", report.price_changes.len()).unwrap();
+    for change in &report.price_changes {
+        writeln!(text, "  {}: {} -> {}", change.laptop, change.old_price, change.new_price).unwrap();
+    }
+
+    writeln!(text, "global benchmark max changes ({}):", report.global_benchmark_max_changes.len()).unwrap();
+    for change in &report.global_benchmark_max_changes {
+        writeln!(
+            text,
+            "  {} \"{}\": {} -> {}",
+            change.pu_type,
+            change.benchmark,
+            format_max(change.old_max),
+            format_max(change.new_max)
+        )
+        .unwrap();
+    }
+
+    write_names(&mut text, "added categories", &report.added_categories);
+    write_names(&mut text, "removed categories", &report.removed_categories);
+
+    writeln!(text, "largest ranking movements:").unwrap();
+    if report.ranking_movements.is_empty() {
+        writeln!(text, "  no laptop changes its rank").unwrap();
+    }
+    for category_movements in &report.ranking_movements {
+        writeln!(text, "  {}:", category_movements.category).unwrap();
+        for movement in &category_movements.movements {
+            writeln!(
+                text,
+                "    {} ({} -> {})",
+                movement.laptop, movement.old_rank, movement.new_rank
+            )
+            .unwrap();
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::laptop_set::{LaptopInformation, LaptopPuBenchmarksData};
+    use bigdecimal::BigDecimal;
+    use maplit::hashmap;
+
+    fn laptop_info(price: f32, cpu_bench: LaptopPuBenchmarksData, gpu_bench: LaptopPuBenchmarksData) -> LaptopInformation {
+        LaptopInformation {
+            url: "https://example.com".to_string(),
+            price,
+            listed_price: price,
+            currency: "ILS".to_string(),
+            store: "ivory".to_string(),
+            cpu: "Intel Core i7-1165G7".to_string(),
+            cpu_bench,
+            gpu: "NVIDIA GeForce MX450".to_string(),
+            gpu_bench,
+            image_urls: Vec::new(),
+            ram_gigabytes: 16,
+            weight_grams: 1500.0,
+            storage_gigabytes: None,
+            brand: None,
+            model: None,
+        }
+    }
+
+    fn test_laptops() -> LaptopInfosByName {
+        hashmap! {
+            "A".to_string() => laptop_info(
+                4000.0,
+                hashmap! { "Cinebench".to_string() => 1000.0, "Geekbench".to_string() => 100.0 },
+                hashmap! { "3DMark".to_string() => 1000.0 },
+            ),
+            "B".to_string() => laptop_info(
+                5000.0,
+                hashmap! { "Cinebench".to_string() => 2000.0 },
+                hashmap! { "3DMark".to_string() => 500.0 },
+            ),
+            "C".to_string() => laptop_info(
+                6000.0,
+                hashmap! { "Geekbench".to_string() => 300.0 },
+                hashmap! { "3DMark".to_string() => 2000.0 },
+            ),
+        }
+    }
+
+    fn test_categories_file() -> CategoriesFile {
+        serde_json::from_str(
+            r#"{
+                "dev": {"cpu": {"Cinebench": 3, "*": 1}, "gpu": {"*": 1}},
+                "gaming": {"cpu": {"*": 1}, "gpu": {"3DMark": 10}}
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn dataset(
+        prices: &[(&str, f32)],
+        maxes: &[(&'static str, &str, f32)],
+        rankings: &[(&str, &[&str])],
+    ) -> ComparedDataset {
+        ComparedDataset {
+            prices_by_laptop_name: prices.iter().map(|(name, price)| (name.to_string(), *price)).collect(),
+            maxes_by_global_benchmark: maxes
+                .iter()
+                .map(|(pu_type, benchmark, max)| ((*pu_type, benchmark.to_string()), *max))
+                .collect(),
+            rankings_by_category_name: rankings
+                .iter()
+                .map(|(category_name, ranking)| (category_name.to_string(), strings(ranking)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn the_reloaded_dataset_is_calculated_from_the_input_files() {
+        let reloaded = calculate_reloaded_dataset(&test_laptops(), &test_categories_file()).unwrap();
+        assert_eq!(
+            reloaded.prices_by_laptop_name,
+            hashmap! { "A".to_string() => 4000.0, "B".to_string() => 5000.0, "C".to_string() => 6000.0 }
+        );
+        assert_eq!(
+            reloaded.maxes_by_global_benchmark,
+            hashmap! {
+                ("cpu", "Cinebench".to_string()) => 2000.0,
+                ("cpu", "Geekbench".to_string()) => 300.0,
+                ("gpu", "3DMark".to_string()) => 2000.0,
+            }
+        );
+        // a missing benchmark is imputed with the average, which puts C above B in the dev category even
+        // though it has no cinebench result
+        assert_eq!(
+            reloaded.rankings_by_category_name,
+            hashmap! {
+                "dev".to_string() => strings(&["C", "B", "A"]),
+                "gaming".to_string() => strings(&["C", "A", "B"]),
+            }
+        );
+    }
+
+    #[test]
+    fn the_reloaded_rankings_match_the_stored_score_calculation() {
+        let reloaded = calculate_reloaded_dataset(&test_laptops(), &test_categories_file()).unwrap();
+
+        // the same input, with ids given by hand as `load laptops` and `load categories` would
+        let global_benchmark = |id, name: &str, max, sum, amount| models::GlobalBenchmark {
+            id,
+            name: name.to_string(),
+            max,
+            sum: BigDecimal::from(sum),
+            amount,
+        };
+        let global_benchmarks = vec![
+            global_benchmark(1, "cCinebench", 2000.0, 3000, 2),
+            global_benchmark(2, "cGeekbench", 300.0, 400, 2),
+            global_benchmark(3, "g3DMark", 2000.0, 3500, 3),
+        ];
+        let mapped_benchmarks: MappedBenchmarks = hashmap! {
+            1 => hashmap! { 1 => 1000.0, 2 => 100.0, 3 => 1000.0 },
+            2 => hashmap! { 1 => 2000.0, 3 => 500.0 },
+            3 => hashmap! { 2 => 300.0, 3 => 2000.0 },
+        };
+        let categories_file = test_categories_file();
+        let mut mapped_benchmark_scores_in_categories = MappedBenchmarkScoresInCategories::new();
+        for (category_id, category_name) in [(1, "dev"), (2, "gaming")].iter() {
+            let mut benchmark_scores_in_category =
+                match_global_benchmarks_with_category(&global_benchmarks, &categories_file[*category_name]);
+            normalize_benchmark_scores_in_category(&mut benchmark_scores_in_category, category_name).unwrap();
+            mapped_benchmark_scores_in_categories.insert(*category_id, benchmark_scores_in_category);
+        }
+        let scores = calculate_laptop_scores_in_each_category(
+            &mapped_benchmarks,
+            &mapped_benchmark_scores_in_categories,
+            &map_global_benchmarks(&global_benchmarks),
+        );
+
+        let laptop_names = ["A", "B", "C"];
+        let category_names = ["dev", "gaming"];
+        let named_scores = scores
+            .into_iter()
+            .map(|score| {
+                (
+                    category_names[score.category_id as usize - 1].to_string(),
+                    laptop_names[score.laptop_id as usize - 1].to_string(),
+                    score.score,
+                )
+            })
+            .collect();
+        assert_eq!(reloaded.rankings_by_category_name, rank_laptops_in_each_category(named_scores));
+    }
+
+    #[test]
+    fn laptops_are_ranked_by_score_and_then_by_name() {
+        let rankings = rank_laptops_in_each_category(vec![
+            ("dev".to_string(), "B".to_string(), 0.5),
+            ("dev".to_string(), "C".to_string(), 0.9),
+            ("dev".to_string(), "A".to_string(), 0.5),
+            ("gaming".to_string(), "A".to_string(), 0.1),
+        ]);
+        assert_eq!(
+            rankings,
+            hashmap! {
+                "dev".to_string() => strings(&["C", "A", "B"]),
+                "gaming".to_string() => strings(&["A"]),
+            }
+        );
+    }
+
+    #[test]
+    fn added_and_removed_laptops_and_categories_are_reported() {
+        let current = dataset(&[("A", 1.0), ("B", 1.0)], &[], &[("dev", &["B", "A"]), ("old", &[])]);
+        let reloaded = dataset(&[("B", 1.0), ("D", 1.0), ("C", 1.0)], &[], &[("dev", &["B"]), ("new", &[])]);
+        let report = compare_datasets(&current, &reloaded);
+        assert_eq!(report.added_laptops, strings(&["C", "D"]));
+        assert_eq!(report.removed_laptops, strings(&["A"]));
+        assert_eq!(report.added_categories, strings(&["new"]));
+        assert_eq!(report.removed_categories, strings(&["old"]));
+        assert!(report.price_changes.is_empty());
+        // the only laptop in both rankings keeps its rank
+        assert!(report.ranking_movements.is_empty());
+    }
+
+    #[test]
+    fn only_changed_prices_of_laptops_in_both_datasets_are_reported() {
+        let current = dataset(&[("A", 4000.0), ("B", 5000.0), ("C", 3000.0), ("removed", 1.0)], &[], &[]);
+        let reloaded = dataset(&[("C", 3500.0), ("B", 5000.0), ("A", 3900.0), ("added", 2.0)], &[], &[]);
+        let price_changes: Vec<(String, f32, f32)> = compare_datasets(&current, &reloaded)
+            .price_changes
+            .into_iter()
+            .map(|change| (change.laptop, change.old_price, change.new_price))
+            .collect();
+        assert_eq!(
+            price_changes,
+            vec![("A".to_string(), 4000.0, 3900.0), ("C".to_string(), 3000.0, 3500.0)]
+        );
+    }
+
+    #[test]
+    fn max_changes_include_added_and_removed_global_benchmarks() {
+        let current = dataset(
+            &[],
+            &[("cpu", "Cinebench", 100.0), ("cpu", "Blender", 7.0), ("gpu", "3DMark", 50.0)],
+            &[],
+        );
+        let reloaded = dataset(
+            &[],
+            &[("cpu", "Geekbench", 30.0), ("cpu", "Cinebench", 120.0), ("cpu", "Blender", 7.0)],
+            &[],
+        );
+        let max_changes: Vec<(&str, String, Option<f32>, Option<f32>)> = compare_datasets(&current, &reloaded)
+            .global_benchmark_max_changes
+            .into_iter()
+            .map(|change| (change.pu_type, change.benchmark, change.old_max, change.new_max))
+            .collect();
+        assert_eq!(
+            max_changes,
+            vec![
+                ("cpu", "Cinebench".to_string(), Some(100.0), Some(120.0)),
+                ("cpu", "Geekbench".to_string(), None, Some(30.0)),
+                ("gpu", "3DMark".to_string(), Some(50.0), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn laptops_that_are_only_in_one_ranking_have_no_movement() {
+        let movements = find_largest_movements(&strings(&["A", "removed", "B"]), &strings(&["added", "B", "A"]));
+        let movements: Vec<(String, usize, usize)> = movements
+            .into_iter()
+            .map(|movement| (movement.laptop, movement.old_rank, movement.new_rank))
+            .collect();
+        assert_eq!(movements, vec![("A".to_string(), 1, 3), ("B".to_string(), 3, 2)]);
+    }
+
+    #[test]
+    fn the_largest_movements_come_first_and_are_truncated() {
+        let old_ranking: Vec<String> = (1..=15).map(|rank| rank.to_string()).collect();
+        let new_ranking: Vec<String> = old_ranking.iter().rev().cloned().collect();
+        let movements = find_largest_movements(&old_ranking, &new_ranking);
+        assert_eq!(movements.len(), REPORT_MOVEMENTS_AMOUNT);
+
+        let ranks: Vec<(usize, usize)> = movements
+            .iter()
+            .map(|movement| (movement.old_rank, movement.new_rank))
+            .collect();
+        // movements of the same size are ordered by their new rank
+        assert_eq!(
+            ranks,
+            vec![(15, 1), (1, 15), (14, 2), (2, 14), (13, 3), (3, 13), (12, 4), (4, 12), (11, 5), (5, 11)]
+        );
+        for movement in &movements {
+            assert_eq!(movement.laptop, movement.old_rank.to_string());
+        }
+    }
+
+    #[test]
+    fn ranking_movements_are_reported_for_categories_in_both_datasets() {
+        let current = dataset(&[], &[], &[("gaming", &["A", "B"]), ("dev", &["A", "B"]), ("same", &["A", "B"])]);
+        let reloaded = dataset(&[], &[], &[("gaming", &["B", "A"]), ("dev", &["B", "A"]), ("same", &["A", "B"])]);
+        let report = compare_datasets(&current, &reloaded);
+        let categories: Vec<&str> = report
+            .ranking_movements
+            .iter()
+            .map(|category_movements| category_movements.category.as_str())
+            .collect();
+        assert_eq!(categories, vec!["dev", "gaming"]);
+    }
+}
@@ -0,0 +1,140 @@
+use db_access::{models, schema};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use maplit::hashmap;
+
+use super::calculate_scores::calculate_scores_in_category;
+use super::load_categories::{
+    insert_benchmark_scores_in_each_category, match_global_benchmarks_with_category,
+    normalize_benchmark_scores_in_category, parse_categories_file, validate_categories_file, CategoryPatternScores,
+};
+use crate::errors::*;
+use crate::input_paths::InputPaths;
+
+/// adds a single category from the categories file, and calculates the scores of all laptops in it,
+/// without touching the other categories.
+pub fn add_category(db_connection: &PgConnection, input_paths: &InputPaths, category_name: &str) -> Result<()> {
+    use schema::category;
+
+    if find_category_id(db_connection, category_name)?.is_some() {
+        return Err(DataProcessorErrorKind::CategoryAlreadyExists {
+            category_name: category_name.to_string(),
+        }
+        .into_empty_data_processor_error());
+    }
+    let category_pattern_scores = load_category_from_categories_file(input_paths, category_name)?;
+
+    println!("inserting the category...");
+    let category_id: i32 = diesel::insert_into(category::table)
+        .values(&models::NewCategory { name: category_name })
+        .returning(category::id)
+        .get_result(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+
+    calculate_category(db_connection, category_id, category_name, &category_pattern_scores)?;
+    println!("successfully added category {}", category_name);
+    Ok(())
+}
+
+/// replaces the benchmark scores of a single category with the ones in the categories file, and
+/// recalculates the scores of all laptops in it, without touching the other categories.
+pub fn update_category(db_connection: &PgConnection, input_paths: &InputPaths, category_name: &str) -> Result<()> {
+    use schema::benchmark_score_in_category;
+
+    let category_id = find_existing_category_id(db_connection, category_name)?;
+    let category_pattern_scores = load_category_from_categories_file(input_paths, category_name)?;
+
+    println!("deleting the category's benchmark scores...");
+    diesel::delete(
+        benchmark_score_in_category::table.filter(benchmark_score_in_category::category_id.eq(category_id)),
+    )
+    .execute(db_connection)
+    .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+
+    calculate_category(db_connection, category_id, category_name, &category_pattern_scores)?;
+    println!("successfully updated category {}", category_name);
+    Ok(())
+}
+
+/// deletes a single category along with its benchmark scores and laptop scores
+pub fn remove_category(db_connection: &PgConnection, category_name: &str) -> Result<()> {
+    use schema::{benchmark_score_in_category, category, laptop_score_in_category};
+
+    let category_id = find_existing_category_id(db_connection, category_name)?;
+    diesel::delete(
+        benchmark_score_in_category::table.filter(benchmark_score_in_category::category_id.eq(category_id)),
+    )
+    .execute(db_connection)
+    .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(laptop_score_in_category::table.filter(laptop_score_in_category::category_id.eq(category_id)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(category::table.find(category_id))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    println!("successfully removed category {}", category_name);
+    Ok(())
+}
+
+/// matches the global benchmarks with the patterns of the category, inserts the normalized benchmark scores
+/// in the category, and calculates the scores of all laptops in the category.
+fn calculate_category(
+    db_connection: &PgConnection,
+    category_id: i32,
+    category_name: &str,
+    category_pattern_scores: &CategoryPatternScores,
+) -> Result<()> {
+    use schema::global_benchmark::dsl::*;
+
+    println!("matching global benchmarks with the category...");
+    let global_benchmarks: Vec<models::GlobalBenchmark> = global_benchmark
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let mut benchmark_scores_in_category =
+        match_global_benchmarks_with_category(&global_benchmarks, category_pattern_scores);
+    normalize_benchmark_scores_in_category(&mut benchmark_scores_in_category, category_name)?;
+    insert_benchmark_scores_in_each_category(
+        hashmap! { category_id => benchmark_scores_in_category },
+        db_connection,
+    )?;
+
+    println!("calculating laptop scores in the category...");
+    calculate_scores_in_category(db_connection, category_id)
+}
+
+/// loads the patterns of a single category from the categories file, making sure that they are valid.
+/// the other categories in the file are ignored.
+fn load_category_from_categories_file(input_paths: &InputPaths, category_name: &str) -> Result<CategoryPatternScores> {
+    println!("loading the categories file...");
+    let mut categories_file = parse_categories_file(&input_paths.categories_file)?;
+    let category_pattern_scores = categories_file.remove(category_name).ok_or_else(|| {
+        DataProcessorErrorKind::CategoryNotInCategoriesFile {
+            category_name: category_name.to_string(),
+        }
+        .into_empty_data_processor_error()
+    })?;
+
+    let mut category_file = hashmap! { category_name.to_string() => category_pattern_scores };
+    validate_categories_file(&category_file)?;
+    Ok(category_file.remove(category_name).unwrap())
+}
+
+fn find_category_id(db_connection: &PgConnection, category_name: &str) -> Result<Option<i32>> {
+    use schema::category::dsl::*;
+
+    category
+        .filter(name.eq(category_name))
+        .select(id)
+        .first(db_connection)
+        .optional()
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)
+}
+
+fn find_existing_category_id(db_connection: &PgConnection, category_name: &str) -> Result<i32> {
+    find_category_id(db_connection, category_name)?.ok_or_else(|| {
+        DataProcessorErrorKind::UnknownCategory {
+            category_name: category_name.to_string(),
+        }
+        .into_empty_data_processor_error()
+    })
+}
@@ -0,0 +1,851 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::BigDecimal;
+use bigdecimal::Zero;
+use db_access::{models, schema};
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use super::calculate_scores::{calculate_scores, calculate_scores_of_laptops};
+use super::load_categories::{
+    insert_benchmark_scores_in_each_category, match_global_benchmarks_with_category,
+    normalize_benchmark_scores_in_category, parse_categories_file, validate_categories_file,
+    BenchmarkScoresInCategory,
+};
+use super::load_laptops::{
+    insert_benchmarks, insert_exchange_rates, insert_images_and_specs, insert_laptop, insert_price_limits,
+    parse_laptops_files, PriceLimits,
+};
+use crate::errors::*;
+use crate::exchange_rates::ExchangeRates;
+use crate::input_paths::InputPaths;
+use crate::laptop_set::LaptopInformation;
+
+/// the scores of a laptop's benchmarks, mapped by the names of their global benchmarks, which start
+/// with the pu type prefix
+type BenchmarksByGlobalBenchmarkName = HashMap<String, f32>;
+
+/// a laptop as it is currently stored in the database
+struct StoredLaptop {
+    laptop: models::Laptop,
+    image_urls: Vec<String>,
+    specs: Option<models::LaptopSpecs>,
+    benchmarks: BenchmarksByGlobalBenchmarkName,
+}
+impl StoredLaptop {
+    /// checks if any of the stored information about the laptop, other than its benchmarks, differs
+    /// from the given information.
+    fn differs_from(&self, laptop_info: &LaptopInformation) -> bool {
+        let laptop = &self.laptop;
+        let specs_differ = match &self.specs {
+            Some(specs) => {
+                specs.ram_gigabytes != laptop_info.ram_gigabytes
+                    || specs.weight_grams != laptop_info.weight_grams
+                    || specs.storage_gigabytes != laptop_info.storage_gigabytes
+            }
+            None => true,
+        };
+        laptop.url != laptop_info.url
+            || laptop.price != laptop_info.price
+            || laptop.listed_price != laptop_info.listed_price
+            || laptop.currency != laptop_info.currency
+            || laptop.store != laptop_info.store
+            || laptop.cpu != laptop_info.cpu
+            || laptop.gpu != laptop_info.gpu
+            || laptop.brand != laptop_info.brand
+            || laptop.model != laptop_info.model
+            || self.image_urls != laptop_info.image_urls
+            || specs_differ
+    }
+}
+
+/// a global benchmark whose max, sum and amount are adjusted as the benchmarks of the laptops are
+/// added and removed.
+struct GlobalBenchmarkUpdate {
+    /// the id of the global benchmark, or None if it is a new global benchmark that wasn't inserted yet
+    id: Option<i32>,
+    max: f32,
+    sum: BigDecimal,
+    amount: i64,
+    /// whether a score that was equal to the max was removed, in which case the max might have decreased,
+    /// and it must be recalculated from the remaining scores
+    max_removed: bool,
+}
+impl GlobalBenchmarkUpdate {
+    fn new() -> Self {
+        Self {
+            id: None,
+            max: 0.0,
+            sum: BigDecimal::zero(),
+            amount: 0,
+            max_removed: false,
+        }
+    }
+
+    fn add(&mut self, score: f32) {
+        self.sum += BigDecimal::from(score);
+        self.amount += 1;
+        if score > self.max {
+            self.max = score;
+        }
+    }
+
+    fn remove(&mut self, score: f32) {
+        self.sum -= BigDecimal::from(score);
+        self.amount -= 1;
+        if score >= self.max {
+            self.max_removed = true;
+        }
+    }
+}
+
+/// how the global benchmarks changed after the laptops were updated
+#[derive(Debug, Default)]
+struct GlobalBenchmarkChanges {
+    /// whether any global benchmark was added
+    added: bool,
+    /// whether any global benchmark was removed
+    removed: bool,
+    /// whether the max of any remaining global benchmark changed
+    max_changed: bool,
+    /// the ids of the remaining global benchmarks whose average changed
+    average_changed: HashSet<i32>,
+}
+
+/// updates the laptops in the database according to the laptops files, by only inserting, updating and
+/// deleting the laptops that changed, instead of reloading all of them. the global benchmarks are adjusted
+/// according to the changed benchmarks, and only the scores that are affected by the changes are recalculated.
+pub fn update_laptops(db_connection: &PgConnection, input_paths: &InputPaths) -> Result<()> {
+    println!("loading the exchange rates file...");
+    let exchange_rates = ExchangeRates::load(&input_paths.exchange_rates_file)?;
+
+    println!("loading the laptops file...");
+    let laptops = parse_laptops_files(&input_paths.laptops_dir, &exchange_rates)?;
+
+    println!("loading the stored laptops and global benchmarks...");
+    let mut stored_laptops = load_stored_laptops(db_connection)?;
+    let old_global_benchmarks: Vec<models::GlobalBenchmark> = {
+        use schema::global_benchmark::dsl::*;
+        global_benchmark
+            .load(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+    };
+    let mut global_benchmark_updates: HashMap<String, GlobalBenchmarkUpdate> = old_global_benchmarks
+        .iter()
+        .map(|global_benchmark| {
+            let update = GlobalBenchmarkUpdate {
+                id: Some(global_benchmark.id),
+                max: global_benchmark.max,
+                sum: global_benchmark.sum.clone(),
+                amount: global_benchmark.amount,
+                max_removed: false,
+            };
+            (global_benchmark.name.clone(), update)
+        })
+        .collect();
+
+    println!("comparing the laptops...");
+    // the removed laptops are the ones that are left in the stored laptops after taking out all the listed laptops
+    let mut listed_laptops = Vec::new();
+    for (laptop_name, laptop_info) in &laptops {
+        let benchmarks = benchmarks_by_global_benchmark_name(laptop_info);
+        listed_laptops.push((laptop_name, laptop_info, stored_laptops.remove(laptop_name), benchmarks));
+    }
+    let removed_laptops: Vec<StoredLaptop> = stored_laptops.into_values().collect();
+
+    // adjust the global benchmarks according to the benchmarks that are removed and added
+    for removed_laptop in &removed_laptops {
+        for (global_benchmark_name, score) in &removed_laptop.benchmarks {
+            global_benchmark_updates.get_mut(global_benchmark_name).unwrap().remove(*score);
+        }
+    }
+    for (_, _, stored_laptop, benchmarks) in &listed_laptops {
+        if let Some(stored_laptop) = stored_laptop {
+            if stored_laptop.benchmarks == *benchmarks {
+                continue;
+            }
+            for (global_benchmark_name, score) in &stored_laptop.benchmarks {
+                global_benchmark_updates.get_mut(global_benchmark_name).unwrap().remove(*score);
+            }
+        }
+        for (global_benchmark_name, score) in benchmarks {
+            global_benchmark_updates
+                .entry(global_benchmark_name.clone())
+                .or_insert_with(GlobalBenchmarkUpdate::new)
+                .add(*score);
+        }
+    }
+
+    // the benchmarks can only be inserted once all of their global benchmarks exist
+    let global_benchmarks_id_by_name = insert_new_global_benchmarks(&mut global_benchmark_updates, db_connection)?;
+
+    println!("deleting {} removed laptops...", removed_laptops.len());
+    let removed_laptop_ids: Vec<i32> = removed_laptops.iter().map(|laptop| laptop.laptop.id).collect();
+    delete_laptops_and_dependents(&removed_laptop_ids, db_connection)?;
+
+    println!("inserting and updating laptops...");
+    // the laptops whose benchmarks changed, including the new laptops, must have their scores recalculated
+    let mut laptops_with_changed_benchmarks = Vec::new();
+    let mut laptop_ids_and_benchmarks = Vec::new();
+    let mut added_laptops_amount = 0;
+    let mut updated_laptops_amount = 0;
+    let mut price_limits = PriceLimits::new();
+    for (laptop_name, laptop_info, stored_laptop, benchmarks) in listed_laptops {
+        price_limits.update(laptop_info.price);
+        let laptop_id = match stored_laptop {
+            Some(stored_laptop) => {
+                let laptop_id = stored_laptop.laptop.id;
+                let information_changed = stored_laptop.differs_from(laptop_info);
+                let benchmarks_changed = stored_laptop.benchmarks != benchmarks;
+                if information_changed {
+                    update_laptop(laptop_id, laptop_info, db_connection)?;
+                }
+                if benchmarks_changed {
+                    replace_benchmarks(laptop_id, laptop_info, &global_benchmarks_id_by_name, db_connection)?;
+                    laptops_with_changed_benchmarks.push(laptop_id);
+                }
+                if information_changed || benchmarks_changed {
+                    updated_laptops_amount += 1;
+                }
+                laptop_id
+            }
+            None => {
+                let laptop_id = insert_laptop(laptop_name, laptop_info, db_connection)?;
+                insert_benchmarks(laptop_id, laptop_info, &global_benchmarks_id_by_name, db_connection)?;
+                insert_images_and_specs(laptop_id, laptop_info, db_connection)?;
+                laptops_with_changed_benchmarks.push(laptop_id);
+                added_laptops_amount += 1;
+                laptop_id
+            }
+        };
+        laptop_ids_and_benchmarks.push((laptop_id, benchmarks));
+    }
+    println!(
+        "added {} laptops, updated {} laptops and removed {} laptops",
+        added_laptops_amount,
+        updated_laptops_amount,
+        removed_laptops.len()
+    );
+
+    println!("updating global benchmarks...");
+    let global_benchmark_changes =
+        update_global_benchmarks(global_benchmark_updates, &old_global_benchmarks, db_connection)?;
+
+    println!("inserting price limits and exchange rates...");
+    insert_price_limits(&price_limits, &exchange_rates.base_currency, db_connection)?;
+    insert_exchange_rates(&exchange_rates, db_connection)?;
+
+    let categories_amount: i64 = schema::category::table
+        .count()
+        .get_result(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    if categories_amount == 0 {
+        println!("there are no categories, so there are no scores to calculate");
+    } else if global_benchmark_changes.added {
+        // the scores of the benchmarks in each category are normalized using all global benchmarks, and new
+        // benchmarks can only be scored using the patterns of the categories file, which changes all laptop scores
+        println!("benchmarks were added, matching the global benchmarks with the categories file and recalculating all scores...");
+        match_global_benchmarks_with_stored_categories(db_connection, input_paths)?;
+        calculate_scores(db_connection)?;
+    } else if global_benchmark_changes.removed {
+        // the scores of the removed benchmarks were already deleted, so the remaining scores only need to be
+        // normalized again, which changes all laptop scores
+        println!("benchmarks were removed, normalizing the remaining benchmark scores in categories and recalculating all scores...");
+        renormalize_benchmark_scores_in_categories(db_connection)?;
+        calculate_scores(db_connection)?;
+    } else if global_benchmark_changes.max_changed {
+        // the max of each global benchmark normalizes the scores of all laptops in it
+        println!("the max score of some benchmarks changed, recalculating all scores...");
+        calculate_scores(db_connection)?;
+    } else {
+        let average_changed_names: Vec<&str> = old_global_benchmarks
+            .iter()
+            .filter(|global_benchmark| global_benchmark_changes.average_changed.contains(&global_benchmark.id))
+            .map(|global_benchmark| global_benchmark.name.as_str())
+            .collect();
+        let affected_laptops =
+            find_affected_laptops(laptops_with_changed_benchmarks, &laptop_ids_and_benchmarks, &average_changed_names);
+        println!("recalculating the scores of {} laptops...", affected_laptops.len());
+        calculate_scores_of_laptops(db_connection, &affected_laptops)?;
+    }
+
+    println!("successfully updated laptops");
+    Ok(())
+}
+
+/// finds the laptops whose scores must be recalculated when no global benchmark was added or removed, and no max
+/// changed. these are the laptops whose benchmarks changed, and the laptops that are missing a benchmark whose
+/// average changed, since they are given the benchmark's average score. the ids are sorted.
+fn find_affected_laptops(
+    laptops_with_changed_benchmarks: Vec<i32>,
+    laptop_ids_and_benchmarks: &[(i32, BenchmarksByGlobalBenchmarkName)],
+    average_changed_names: &[&str],
+) -> Vec<i32> {
+    let mut affected_laptops: HashSet<i32> = laptops_with_changed_benchmarks.into_iter().collect();
+    for (laptop_id, benchmarks) in laptop_ids_and_benchmarks {
+        if average_changed_names.iter().any(|name| !benchmarks.contains_key(*name)) {
+            affected_laptops.insert(*laptop_id);
+        }
+    }
+    let mut affected_laptops: Vec<i32> = affected_laptops.into_iter().collect();
+    affected_laptops.sort_unstable();
+    affected_laptops
+}
+
+/// replaces the benchmark scores of each stored category with the ones that the patterns of the category in the
+/// categories file give to the current global benchmarks, the same way `update category` does.
+fn match_global_benchmarks_with_stored_categories(db_connection: &PgConnection, input_paths: &InputPaths) -> Result<()> {
+    use schema::{benchmark_score_in_category, category, global_benchmark};
+
+    println!("loading the categories file...");
+    let categories_file = parse_categories_file(&input_paths.categories_file)?;
+    validate_categories_file(&categories_file)?;
+
+    let categories: Vec<models::Category> = category::table
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let global_benchmarks: Vec<models::GlobalBenchmark> = global_benchmark::table
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let mut benchmark_scores_in_each_category = HashMap::new();
+    for stored_category in categories {
+        let category_pattern_scores = categories_file.get(&stored_category.name).ok_or_else(|| {
+            DataProcessorErrorKind::CategoryNotInCategoriesFile {
+                category_name: stored_category.name.clone(),
+            }
+            .into_empty_data_processor_error()
+        })?;
+        let mut benchmark_scores_in_category =
+            match_global_benchmarks_with_category(&global_benchmarks, category_pattern_scores);
+        normalize_benchmark_scores_in_category(&mut benchmark_scores_in_category, &stored_category.name)?;
+        benchmark_scores_in_each_category.insert(stored_category.id, benchmark_scores_in_category);
+    }
+
+    diesel::delete(benchmark_score_in_category::table)
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    insert_benchmark_scores_in_each_category(benchmark_scores_in_each_category, db_connection)
+}
+
+/// normalizes the stored benchmark scores of each category again, so that they sum to 1 after the scores of the
+/// removed global benchmarks were deleted. since the stored scores are the scores of the categories file divided
+/// by their sum, this gives the same scores as matching the remaining global benchmarks with the categories file.
+fn renormalize_benchmark_scores_in_categories(db_connection: &PgConnection) -> Result<()> {
+    use schema::{benchmark_score_in_category, category};
+
+    let categories: Vec<models::Category> = category::table
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let benchmark_scores_in_categories: Vec<models::BenchmarkScoreInCategory> = benchmark_score_in_category::table
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let mut benchmark_scores_in_each_category: HashMap<i32, BenchmarkScoresInCategory> = categories
+        .iter()
+        .map(|stored_category| (stored_category.id, BenchmarkScoresInCategory::new()))
+        .collect();
+    for benchmark_score_in_category in benchmark_scores_in_categories {
+        benchmark_scores_in_each_category
+            .entry(benchmark_score_in_category.category_id)
+            .or_default()
+            .insert(benchmark_score_in_category.global_benchmark_id, benchmark_score_in_category.score);
+    }
+    for stored_category in &categories {
+        normalize_benchmark_scores_in_category(
+            benchmark_scores_in_each_category.get_mut(&stored_category.id).unwrap(),
+            &stored_category.name,
+        )?;
+    }
+
+    diesel::delete(benchmark_score_in_category::table)
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    insert_benchmark_scores_in_each_category(benchmark_scores_in_each_category, db_connection)
+}
+
+/// maps the benchmarks of the laptop by the names of their global benchmarks
+fn benchmarks_by_global_benchmark_name(laptop_info: &LaptopInformation) -> BenchmarksByGlobalBenchmarkName {
+    let cpu_benchmarks = laptop_info
+        .cpu_bench
+        .iter()
+        .map(|(benchmark_name, score)| (format!("c{}", benchmark_name), *score));
+    let gpu_benchmarks = laptop_info
+        .gpu_bench
+        .iter()
+        .map(|(benchmark_name, score)| (format!("g{}", benchmark_name), *score));
+    cpu_benchmarks.chain(gpu_benchmarks).collect()
+}
+
+/// loads all laptops along with their images, specs and benchmarks, mapped by the laptops' names
+fn load_stored_laptops(db_connection: &PgConnection) -> Result<HashMap<String, StoredLaptop>> {
+    use schema::{benchmark, global_benchmark, laptop, laptop_image, laptop_specs};
+
+    let laptops: Vec<models::Laptop> = laptop::table
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    let mut stored_laptops_by_id: HashMap<i32, StoredLaptop> = laptops
+        .into_iter()
+        .map(|laptop| {
+            let stored_laptop = StoredLaptop {
+                laptop,
+                image_urls: Vec::new(),
+                specs: None,
+                benchmarks: BenchmarksByGlobalBenchmarkName::new(),
+            };
+            (stored_laptop.laptop.id, stored_laptop)
+        })
+        .collect();
+
+    // the images are ordered by their ids, which is the order in which they were inserted
+    let laptop_images: Vec<models::LaptopImage> = laptop_image::table
+        .order(laptop_image::id)
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    for laptop_image in laptop_images {
+        if let Some(stored_laptop) = stored_laptops_by_id.get_mut(&laptop_image.laptop_id) {
+            stored_laptop.image_urls.push(laptop_image.image_url);
+        }
+    }
+
+    let laptops_specs: Vec<models::LaptopSpecs> = laptop_specs::table
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    for laptop_specs in laptops_specs {
+        if let Some(stored_laptop) = stored_laptops_by_id.get_mut(&laptop_specs.laptop_id) {
+            stored_laptop.specs = Some(laptop_specs);
+        }
+    }
+
+    let benchmarks: Vec<(i32, String, f32)> = benchmark::table
+        .inner_join(global_benchmark::table)
+        .select((benchmark::laptop_id, global_benchmark::name, benchmark::score))
+        .load(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    for (laptop_id, global_benchmark_name, score) in benchmarks {
+        if let Some(stored_laptop) = stored_laptops_by_id.get_mut(&laptop_id) {
+            stored_laptop.benchmarks.insert(global_benchmark_name, score);
+        }
+    }
+
+    Ok(stored_laptops_by_id
+        .into_values()
+        .map(|stored_laptop| (stored_laptop.laptop.name.clone(), stored_laptop))
+        .collect())
+}
+
+/// inserts the global benchmarks that don't exist in the database yet, and returns the ids of all global
+/// benchmarks mapped by their names. the inserted global benchmarks are empty until they are updated.
+fn insert_new_global_benchmarks(
+    global_benchmark_updates: &mut HashMap<String, GlobalBenchmarkUpdate>,
+    db_connection: &PgConnection,
+) -> Result<HashMap<String, i32>> {
+    use schema::global_benchmark;
+
+    let zero = BigDecimal::zero();
+    let new_global_benchmarks: Vec<models::NewGlobalBenchmark> = global_benchmark_updates
+        .iter()
+        .filter(|(_, update)| update.id.is_none())
+        .map(|(global_benchmark_name, _)| models::NewGlobalBenchmark {
+            name: global_benchmark_name,
+            max: 0.0,
+            sum: &zero,
+            amount: 0,
+        })
+        .collect();
+    let inserted_names_and_ids: Vec<(String, i32)> = diesel::insert_into(global_benchmark::table)
+        .values(new_global_benchmarks.as_slice())
+        .returning((global_benchmark::name, global_benchmark::id))
+        .get_results(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    for (global_benchmark_name, global_benchmark_id) in inserted_names_and_ids {
+        global_benchmark_updates.get_mut(&global_benchmark_name).unwrap().id = Some(global_benchmark_id);
+    }
+
+    Ok(global_benchmark_updates
+        .iter()
+        .map(|(global_benchmark_name, update)| (global_benchmark_name.clone(), update.id.unwrap()))
+        .collect())
+}
+
+/// stores the adjusted global benchmarks, recalculating the max of the global benchmarks whose max score
+/// was removed, and deleting the global benchmarks that no laptop has a score in anymore. must be called
+/// after the benchmarks of the laptops were updated.
+fn update_global_benchmarks(
+    global_benchmark_updates: HashMap<String, GlobalBenchmarkUpdate>,
+    old_global_benchmarks: &[models::GlobalBenchmark],
+    db_connection: &PgConnection,
+) -> Result<GlobalBenchmarkChanges> {
+    use schema::{benchmark, benchmark_score_in_category, global_benchmark};
+
+    let old_global_benchmarks_by_id: HashMap<i32, &models::GlobalBenchmark> = old_global_benchmarks
+        .iter()
+        .map(|global_benchmark| (global_benchmark.id, global_benchmark))
+        .collect();
+    let mut changes = GlobalBenchmarkChanges::default();
+    let mut deleted_global_benchmark_ids = Vec::new();
+    for (_, mut update) in global_benchmark_updates {
+        let global_benchmark_id = update.id.unwrap();
+        let old_global_benchmark = old_global_benchmarks_by_id.get(&global_benchmark_id);
+        if old_global_benchmark.is_none() {
+            changes.added = true;
+        }
+        if update.amount == 0 {
+            deleted_global_benchmark_ids.push(global_benchmark_id);
+            changes.removed = true;
+            continue;
+        }
+        if update.max_removed {
+            update.max = benchmark::table
+                .filter(benchmark::global_benchmark_id.eq(global_benchmark_id))
+                .select(diesel::dsl::max(benchmark::score))
+                .first::<Option<f32>>(db_connection)
+                .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?
+                .unwrap_or(0.0);
+        }
+        if let Some(old_global_benchmark) = old_global_benchmark {
+            if old_global_benchmark.max == update.max
+                && old_global_benchmark.sum == update.sum
+                && old_global_benchmark.amount == update.amount
+            {
+                continue;
+            }
+            if old_global_benchmark.max != update.max {
+                changes.max_changed = true;
+            }
+            if old_global_benchmark.sum != update.sum || old_global_benchmark.amount != update.amount {
+                changes.average_changed.insert(global_benchmark_id);
+            }
+        }
+        diesel::update(global_benchmark::table.find(global_benchmark_id))
+            .set((
+                global_benchmark::max.eq(update.max),
+                global_benchmark::sum.eq(&update.sum),
+                global_benchmark::amount.eq(update.amount),
+            ))
+            .execute(db_connection)
+            .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    }
+
+    diesel::delete(
+        benchmark_score_in_category::table
+            .filter(benchmark_score_in_category::global_benchmark_id.eq_any(&deleted_global_benchmark_ids)),
+    )
+    .execute(db_connection)
+    .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(global_benchmark::table.filter(global_benchmark::id.eq_any(&deleted_global_benchmark_ids)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(changes)
+}
+
+/// updates the information of a stored laptop, including its images and specs, but not its benchmarks
+fn update_laptop(laptop_id: i32, laptop_info: &LaptopInformation, db_connection: &PgConnection) -> Result<()> {
+    use schema::{laptop, laptop_image, laptop_specs};
+
+    diesel::update(laptop::table.find(laptop_id))
+        .set((
+            laptop::url.eq(&laptop_info.url),
+            laptop::price.eq(laptop_info.price),
+            laptop::listed_price.eq(laptop_info.listed_price),
+            laptop::currency.eq(&laptop_info.currency),
+            laptop::store.eq(&laptop_info.store),
+            laptop::cpu.eq(&laptop_info.cpu),
+            laptop::gpu.eq(&laptop_info.gpu),
+            laptop::brand.eq(&laptop_info.brand),
+            laptop::model.eq(&laptop_info.model),
+        ))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+
+    // the images and specs are small, so they are just replaced
+    diesel::delete(laptop_image::table.filter(laptop_image::laptop_id.eq(laptop_id)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(laptop_specs::table.filter(laptop_specs::laptop_id.eq(laptop_id)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    insert_images_and_specs(laptop_id, laptop_info, db_connection)
+}
+
+/// replaces the benchmarks of a stored laptop with its new benchmarks
+fn replace_benchmarks(
+    laptop_id: i32,
+    laptop_info: &LaptopInformation,
+    global_benchmarks_id_by_name: &HashMap<String, i32>,
+    db_connection: &PgConnection,
+) -> Result<()> {
+    use schema::benchmark;
+
+    diesel::delete(benchmark::table.filter(benchmark::laptop_id.eq(laptop_id)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    insert_benchmarks(laptop_id, laptop_info, global_benchmarks_id_by_name, db_connection)
+}
+
+/// deletes the laptops with the given ids, and everything that depends on them
+fn delete_laptops_and_dependents(laptop_ids: &[i32], db_connection: &PgConnection) -> Result<()> {
+    use schema::{benchmark, laptop, laptop_image, laptop_score_in_category, laptop_specs};
+
+    diesel::delete(laptop_score_in_category::table.filter(laptop_score_in_category::laptop_id.eq_any(laptop_ids)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(laptop_specs::table.filter(laptop_specs::laptop_id.eq_any(laptop_ids)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(laptop_image::table.filter(laptop_image::laptop_id.eq_any(laptop_ids)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(benchmark::table.filter(benchmark::laptop_id.eq_any(laptop_ids)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    diesel::delete(laptop::table.filter(laptop::id.eq_any(laptop_ids)))
+        .execute(db_connection)
+        .into_data_processor_result(DataProcessorErrorKind::DatabaseError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_database::{connect_to_test_dataset, execute};
+    use crate::laptop_set::LaptopPuBenchmarksData;
+    use maplit::{hashmap, hashset};
+
+    fn laptop_info(price: f32, cpu_bench: LaptopPuBenchmarksData) -> LaptopInformation {
+        LaptopInformation {
+            url: "https://example.com".to_string(),
+            price,
+            listed_price: price,
+            currency: "ILS".to_string(),
+            store: "ivory".to_string(),
+            cpu: "Intel Core i7-1165G7".to_string(),
+            cpu_bench,
+            gpu: "NVIDIA GeForce MX450".to_string(),
+            gpu_bench: hashmap! { "3DMark".to_string() => 1688.0 },
+            image_urls: vec!["https://example.com/image.png".to_string()],
+            ram_gigabytes: 16,
+            weight_grams: 1500.0,
+            storage_gigabytes: Some(512),
+            brand: Some("Lenovo".to_string()),
+            model: Some("IdeaPad 3".to_string()),
+        }
+    }
+
+    /// the laptop as it would be stored after loading the given information
+    fn stored_laptop(laptop_info: &LaptopInformation) -> StoredLaptop {
+        StoredLaptop {
+            laptop: models::Laptop {
+                id: 1,
+                name: "Lenovo IdeaPad 3".to_string(),
+                url: laptop_info.url.clone(),
+                price: laptop_info.price,
+                cpu: laptop_info.cpu.clone(),
+                gpu: laptop_info.gpu.clone(),
+                brand: laptop_info.brand.clone(),
+                model: laptop_info.model.clone(),
+                currency: laptop_info.currency.clone(),
+                listed_price: laptop_info.listed_price,
+                store: laptop_info.store.clone(),
+            },
+            image_urls: laptop_info.image_urls.clone(),
+            specs: Some(models::LaptopSpecs {
+                id: 1,
+                laptop_id: 1,
+                ram_gigabytes: laptop_info.ram_gigabytes,
+                weight_grams: laptop_info.weight_grams,
+                storage_gigabytes: laptop_info.storage_gigabytes,
+            }),
+            benchmarks: benchmarks_by_global_benchmark_name(laptop_info),
+        }
+    }
+
+    #[test]
+    fn an_unchanged_laptop_is_not_updated() {
+        let info = laptop_info(4000.0, hashmap! { "Cinebench".to_string() => 5076.0 });
+        let stored = stored_laptop(&info);
+        assert!(!stored.differs_from(&info));
+        assert_eq!(stored.benchmarks, benchmarks_by_global_benchmark_name(&info));
+    }
+
+    #[test]
+    fn a_price_change_is_an_update_without_a_benchmark_change() {
+        let stored = stored_laptop(&laptop_info(4000.0, hashmap! { "Cinebench".to_string() => 5076.0 }));
+        let info = laptop_info(3500.0, hashmap! { "Cinebench".to_string() => 5076.0 });
+        assert!(stored.differs_from(&info));
+        assert_eq!(stored.benchmarks, benchmarks_by_global_benchmark_name(&info));
+    }
+
+    #[test]
+    fn a_benchmark_change_is_not_an_information_change() {
+        let stored = stored_laptop(&laptop_info(4000.0, hashmap! { "Cinebench".to_string() => 5076.0 }));
+        let info = laptop_info(4000.0, hashmap! { "Cinebench".to_string() => 5100.0 });
+        assert!(!stored.differs_from(&info));
+        assert_ne!(stored.benchmarks, benchmarks_by_global_benchmark_name(&info));
+        // the benchmarks are mapped by their global benchmarks' names, which start with the pu type prefix
+        assert_eq!(
+            benchmarks_by_global_benchmark_name(&info),
+            hashmap! { "cCinebench".to_string() => 5100.0, "g3DMark".to_string() => 1688.0 }
+        );
+    }
+
+    #[test]
+    fn removing_the_max_score_requires_recalculating_the_max() {
+        let mut update = GlobalBenchmarkUpdate::new();
+        update.add(50.0);
+        update.add(100.0);
+        update.remove(50.0);
+        assert!(!update.max_removed);
+        update.remove(100.0);
+        assert!(update.max_removed);
+    }
+
+    #[test]
+    fn removing_and_adding_a_score_restores_the_sum_and_amount() {
+        let mut update = GlobalBenchmarkUpdate::new();
+        update.add(50.5);
+        update.add(100.25);
+        let (sum, amount) = (update.sum.clone(), update.amount);
+        update.remove(50.5);
+        assert_eq!(update.amount, 1);
+        update.add(50.5);
+        assert_eq!((update.sum, update.amount), (sum, amount));
+        assert_eq!(update.max, 100.25);
+    }
+
+    #[test]
+    fn laptops_missing_a_benchmark_whose_average_changed_are_recalculated() {
+        let laptop_ids_and_benchmarks = vec![
+            (1, hashmap! { "cCinebench".to_string() => 5076.0, "cGeekbench".to_string() => 1500.0 }),
+            (2, hashmap! { "cCinebench".to_string() => 4000.0 }),
+            (3, hashmap! { "cGeekbench".to_string() => 1200.0 }),
+            (4, hashmap! { "cCinebench".to_string() => 3000.0 }),
+        ];
+        // laptop 3 changed, so the average of geekbench changed, which changes the scores of the laptops without it
+        assert_eq!(find_affected_laptops(vec![3], &laptop_ids_and_benchmarks, &["cGeekbench"]), vec![2, 3, 4]);
+        assert_eq!(find_affected_laptops(vec![3], &laptop_ids_and_benchmarks, &[]), vec![3]);
+        assert_eq!(find_affected_laptops(vec![], &laptop_ids_and_benchmarks, &["cCinebench"]), vec![3]);
+    }
+
+    /// an update of the global benchmark with the given id, as it is stored in the database
+    fn stored_update(id: i32, scores: &[f32]) -> GlobalBenchmarkUpdate {
+        let mut update = GlobalBenchmarkUpdate::new();
+        for score in scores {
+            update.add(*score);
+        }
+        update.id = Some(id);
+        update
+    }
+
+    fn load_global_benchmarks(db_connection: &PgConnection) -> Vec<models::GlobalBenchmark> {
+        schema::global_benchmark::table
+            .order(schema::global_benchmark::id)
+            .load(db_connection)
+            .unwrap()
+    }
+
+    #[test]
+    fn the_max_is_recalculated_from_the_remaining_scores() {
+        let db_connection = connect_to_test_dataset();
+        execute(
+            &db_connection,
+            "INSERT INTO global_benchmark (id, name, max, sum, amount) VALUES \
+            (1, 'cCinebench', 100, 220, 3), (2, 'cGeekbench', 10, 10, 1)",
+        );
+        // the scores that remain after the laptop with the max cinebench score was removed
+        execute(
+            &db_connection,
+            "INSERT INTO benchmark (laptop_id, global_benchmark_id, score) VALUES (1, 1, 50), (2, 1, 70), (1, 2, 10)",
+        );
+        execute(
+            &db_connection,
+            "INSERT INTO benchmark_score_in_category (category_id, global_benchmark_id, score) VALUES (1, 1, 0.5), (1, 2, 0.5)",
+        );
+        let old_global_benchmarks = load_global_benchmarks(&db_connection);
+
+        let mut cinebench = stored_update(1, &[50.0, 70.0, 100.0]);
+        cinebench.remove(100.0);
+        let mut geekbench = stored_update(2, &[10.0]);
+        geekbench.remove(10.0);
+        let updates = hashmap! { "cCinebench".to_string() => cinebench, "cGeekbench".to_string() => geekbench };
+        let changes = update_global_benchmarks(updates, &old_global_benchmarks, &db_connection).unwrap();
+
+        assert!(changes.max_changed);
+        assert!(changes.removed);
+        assert!(!changes.added);
+        assert_eq!(changes.average_changed, hashset! { 1 });
+        let global_benchmarks = load_global_benchmarks(&db_connection);
+        assert_eq!(global_benchmarks.len(), 1);
+        assert_eq!(
+            (global_benchmarks[0].max, &global_benchmarks[0].sum, global_benchmarks[0].amount),
+            (70.0, &BigDecimal::from(120), 2)
+        );
+        // the removed global benchmark has no scores in the categories anymore
+        let benchmark_scores_in_category: Vec<models::BenchmarkScoreInCategory> =
+            schema::benchmark_score_in_category::table.load(&db_connection).unwrap();
+        assert_eq!(benchmark_scores_in_category.len(), 1);
+        assert_eq!(benchmark_scores_in_category[0].global_benchmark_id, 1);
+    }
+
+    #[test]
+    fn the_remaining_benchmark_scores_in_categories_are_normalized_again() {
+        let db_connection = connect_to_test_dataset();
+        execute(&db_connection, "INSERT INTO category (id, name) VALUES (1, 'dev'), (2, 'gaming')");
+        // the scores 1, 3 and 4 in dev, and 2 and 2 in gaming, after the benchmark with the score 4 was removed
+        execute(
+            &db_connection,
+            "INSERT INTO benchmark_score_in_category (category_id, global_benchmark_id, score) VALUES \
+            (1, 1, 0.125), (1, 2, 0.375), (2, 1, 0.5)",
+        );
+        renormalize_benchmark_scores_in_categories(&db_connection).unwrap();
+
+        let mut benchmark_scores_in_categories: Vec<(i32, i32, f32)> = schema::benchmark_score_in_category::table
+            .select((
+                schema::benchmark_score_in_category::category_id,
+                schema::benchmark_score_in_category::global_benchmark_id,
+                schema::benchmark_score_in_category::score,
+            ))
+            .load(&db_connection)
+            .unwrap();
+        benchmark_scores_in_categories.sort_by_key(|(category_id, global_benchmark_id, _)| (*category_id, *global_benchmark_id));
+        assert_eq!(benchmark_scores_in_categories, vec![(1, 1, 0.25), (1, 2, 0.75), (2, 1, 1.0)]);
+    }
+
+    #[test]
+    fn added_benchmarks_are_scored_using_the_categories_file() {
+        let db_connection = connect_to_test_dataset();
+        let input_paths = InputPaths {
+            categories_file: std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("categories.json"),
+            ..InputPaths::default()
+        };
+        execute(&db_connection, "INSERT INTO category (id, name) VALUES (1, 'dev')");
+        // in dev, "Cinebench&&Multi" scores 5 and "7-Zip" scores 8, and the stored scores are outdated
+        execute(
+            &db_connection,
+            "INSERT INTO global_benchmark (id, name, max, sum, amount) VALUES             (1, 'cCinebench R23 Multi', 100, 100, 1), (2, 'c7-Zip 18.03', 100, 100, 1)",
+        );
+        execute(
+            &db_connection,
+            "INSERT INTO benchmark_score_in_category (category_id, global_benchmark_id, score) VALUES (1, 1, 1)",
+        );
+        match_global_benchmarks_with_stored_categories(&db_connection, &input_paths).unwrap();
+
+        let mut benchmark_scores_in_category: Vec<(i32, f32)> = schema::benchmark_score_in_category::table
+            .select((
+                schema::benchmark_score_in_category::global_benchmark_id,
+                schema::benchmark_score_in_category::score,
+            ))
+            .load(&db_connection)
+            .unwrap();
+        benchmark_scores_in_category.sort_by_key(|(global_benchmark_id, _)| *global_benchmark_id);
+        assert_eq!(benchmark_scores_in_category, vec![(1, 5.0 / 13.0), (2, 8.0 / 13.0)]);
+
+        // a stored category that the categories file doesn't have can't be matched
+        execute(&db_connection, "INSERT INTO category (id, name) VALUES (2, 'photography')");
+        let error = match_global_benchmarks_with_stored_categories(&db_connection, &input_paths).unwrap_err();
+        assert_eq!(
+            error.kind,
+            DataProcessorErrorKind::CategoryNotInCategoriesFile {
+                category_name: "photography".to_string()
+            }
+        );
+    }
+}
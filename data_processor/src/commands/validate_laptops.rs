@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{read_dir, File};
+use std::path::Path;
+
+use crate::errors::*;
+use crate::exchange_rates::{ConvertedPrice, ExchangeRates};
+use crate::input_paths::InputPaths;
+use crate::laptop_set::LaptopsFileEntry;
+use super::ProblemSeverity;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+
+lazy_static! {
+    /// the regex used to match laptops files. examples for a valid file name: ivory-laptops.json.
+    /// the prefix of the file name is the name of the store that listed the laptops. the regex is anchored,
+    /// so that backups such as ivory-laptops.json.bak aren't loaded as laptops files.
+    static ref LAPTOPS_FILES_REGEX:Regex = Regex::new(r"^([a-z]+)-laptops[.]json$").unwrap();
+}
+
+/// a problem that was found in a laptops file
+#[derive(Debug)]
+struct LaptopsFileProblem {
+    severity: ProblemSeverity,
+    file_name: String,
+    /// the index of the entry in the file, or None if the problem is with the file as a whole
+    entry_index: Option<usize>,
+    /// the name of the laptop of the entry, if it is known
+    laptop_name: Option<String>,
+    /// the field of the entry, or None if the problem is with the entry as a whole
+    field: Option<String>,
+    description: String,
+}
+
+impl fmt::Display for LaptopsFileProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.file_name)?;
+        if let Some(entry_index) = self.entry_index {
+            write!(f, "[{}]", entry_index)?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, ".{}", field)?;
+        }
+        if let Some(laptop_name) = &self.laptop_name {
+            write!(f, " ({})", laptop_name)?;
+        }
+        write!(f, ": {}", self.description)
+    }
+}
+
+/// an entry of a laptops file that has no errors, along with its price in the base currency
+#[derive(Debug)]
+pub(super) struct ValidLaptopsFileEntry {
+    pub(super) entry: LaptopsFileEntry,
+    pub(super) price: ConvertedPrice,
+    /// the store that listed the laptop
+    pub(super) store: String,
+}
+
+/// the result of validating all laptops files
+#[derive(Debug, Default)]
+pub(super) struct LaptopsFilesValidation {
+    pub(super) valid_entries: Vec<ValidLaptopsFileEntry>,
+    problems: Vec<LaptopsFileProblem>,
+}
+
+impl LaptopsFilesValidation {
+    fn problems_amount(&self, severity: ProblemSeverity) -> usize {
+        self.problems
+            .iter()
+            .filter(|problem| problem.severity == severity)
+            .count()
+    }
+
+    /// prints all problems, and returns an error if any of them is an error
+    pub(super) fn report(&self) -> Result<()> {
+        for problem in &self.problems {
+            println!("{}", problem);
+        }
+        let errors_amount = self.problems_amount(ProblemSeverity::Error);
+        if !self.problems.is_empty() {
+            println!(
+                "found {} errors and {} warnings in the laptops files",
+                errors_amount,
+                self.problems_amount(ProblemSeverity::Warning)
+            );
+        }
+        if errors_amount > 0 {
+            return Err(DataProcessorErrorKind::InvalidLaptopsFiles { errors_amount }.into_empty_data_processor_error());
+        }
+        Ok(())
+    }
+
+    fn add_problem(
+        &mut self,
+        severity: ProblemSeverity,
+        file_name: &str,
+        entry_index: Option<usize>,
+        laptop_name: Option<&str>,
+        field: Option<&str>,
+        description: String,
+    ) {
+        self.problems.push(LaptopsFileProblem {
+            severity,
+            file_name: file_name.to_string(),
+            entry_index,
+            laptop_name: laptop_name.map(str::to_string),
+            field: field.map(str::to_string),
+            description,
+        });
+    }
+}
+
+/// validates the laptops files without loading them, and reports all problems that were found
+pub fn validate_laptops(input_paths: &InputPaths) -> Result<()> {
+    println!("loading the exchange rates file...");
+    let exchange_rates = ExchangeRates::load(&input_paths.exchange_rates_file)?;
+
+    println!("validating the laptops files...");
+    let validation = validate_laptops_files(&input_paths.laptops_dir, &exchange_rates)?;
+    validation.report()?;
+    println!("the laptops files can be loaded");
+    Ok(())
+}
+
+/// deserializes and validates the entries of all laptops files, collecting every problem instead of
+/// stopping at the first one. the files are validated in the order of their names, so that the same
+/// problems are always reported in the same order.
+pub(super) fn validate_laptops_files(laptops_dir: &Path, exchange_rates: &ExchangeRates) -> Result<LaptopsFilesValidation> {
+    let mut laptops_file_paths = Vec::new();
+    for laptops_dir_entry in read_dir(laptops_dir)
+        .into_data_processor_result(DataProcessorErrorKind::FailedToReadLaptopsDirectory)?
+    {
+        let laptops_dir_entry = laptops_dir_entry
+            .into_data_processor_result(DataProcessorErrorKind::FailedToReadLaptopsDirectory)?;
+        let file_name = laptops_dir_entry.file_name().to_string_lossy().to_string();
+
+        // only files whose names match the laptops files regex are laptops files
+        if laptops_dir_entry.path().is_file() && LAPTOPS_FILES_REGEX.is_match(&file_name) {
+            laptops_file_paths.push((file_name, laptops_dir_entry.path()));
+        }
+    }
+    laptops_file_paths.sort_unstable();
+
+    let mut validation = LaptopsFilesValidation::default();
+    // the file name and entry index of the first entry with each url
+    let mut first_entry_by_url: HashMap<String, (String, usize)> = HashMap::new();
+    for (file_name, file_path) in laptops_file_paths {
+        let store = LAPTOPS_FILES_REGEX.captures(&file_name).unwrap()[1].to_string();
+
+        // the file is first deserialized as a list of json values, so that an invalid entry doesn't
+        // prevent the other entries from being validated
+        let entries: Vec<Value> = match File::open(&file_path) {
+            Ok(laptops_file) => match serde_json::de::from_reader(laptops_file) {
+                Ok(entries) => entries,
+                Err(error) => {
+                    validation.add_problem(ProblemSeverity::Error, &file_name, None, None, None, error.to_string());
+                    continue;
+                }
+            },
+            Err(error) => {
+                validation.add_problem(
+                    ProblemSeverity::Error,
+                    &file_name,
+                    None,
+                    None,
+                    None,
+                    format!("failed to open the file: {}", error),
+                );
+                continue;
+            }
+        };
+
+        for (entry_index, entry) in entries.into_iter().enumerate() {
+            let invalid_field = LaptopsFileEntry::find_invalid_field(&entry);
+            let laptop_name = entry.get("name").and_then(Value::as_str).map(str::to_string);
+            let entry: LaptopsFileEntry = match serde_json::from_value(entry) {
+                Ok(entry) => entry,
+                Err(error) => {
+                    let (field, description) = match invalid_field {
+                        Some((field, description)) => (Some(field), description),
+                        None => (None, error.to_string()),
+                    };
+                    validation.add_problem(
+                        ProblemSeverity::Error,
+                        &file_name,
+                        Some(entry_index),
+                        laptop_name.as_deref(),
+                        field.as_deref(),
+                        description,
+                    );
+                    continue;
+                }
+            };
+
+            let errors_amount_before_entry = validation.problems_amount(ProblemSeverity::Error);
+            let mut add_problem = |severity, field, description| {
+                validation.add_problem(
+                    severity,
+                    &file_name,
+                    Some(entry_index),
+                    Some(entry.name()),
+                    Some(field),
+                    description,
+                )
+            };
+
+            let non_finite_field = entry.find_non_finite_field();
+            if let Some(field) = &non_finite_field {
+                add_problem(ProblemSeverity::Error, field, "the number is not finite".to_string());
+            }
+            if entry.price().is_finite() && entry.price() <= 0.0 {
+                add_problem(
+                    ProblemSeverity::Error,
+                    "price",
+                    format!("the price must be positive, but it is {}", entry.price()),
+                );
+            }
+            if entry.ram_gigabytes() <= 0 {
+                add_problem(
+                    ProblemSeverity::Error,
+                    "ram",
+                    format!("the ram must be positive, but it is {}", entry.ram_gigabytes()),
+                );
+            }
+            if entry.url().trim().is_empty() {
+                add_problem(ProblemSeverity::Error, "url", "the url is empty".to_string());
+            } else {
+                match first_entry_by_url.get(entry.url()) {
+                    Some((first_file_name, first_entry_index)) => add_problem(
+                        ProblemSeverity::Warning,
+                        "url",
+                        format!("the url is also used by {}[{}]", first_file_name, first_entry_index),
+                    ),
+                    None => {
+                        first_entry_by_url.insert(entry.url().to_string(), (file_name.clone(), entry_index));
+                    }
+                }
+            }
+            if !entry.has_benchmarks() {
+                add_problem(
+                    ProblemSeverity::Warning,
+                    "cpu_bench",
+                    "the laptop has no benchmarks, so it will have no scores".to_string(),
+                );
+            }
+            let price = exchange_rates.convert(entry.price(), entry.currency());
+            if price.is_none() {
+                add_problem(
+                    ProblemSeverity::Error,
+                    "currency",
+                    format!("unknown currency {}", entry.currency().unwrap_or_default()),
+                );
+            }
+
+            if validation.problems_amount(ProblemSeverity::Error) == errors_amount_before_entry {
+                validation.valid_entries.push(ValidLaptopsFileEntry {
+                    entry,
+                    // the price is only missing if the currency is unknown, which is an error
+                    price: price.unwrap(),
+                    store: store.clone(),
+                });
+            }
+        }
+    }
+    Ok(validation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// a laptops directory in the temporary directory, which is deleted when it is dropped
+    struct TestLaptopsDir(PathBuf);
+    impl TestLaptopsDir {
+        fn new(test_name: &str, files: &[(&str, Value)]) -> Self {
+            let path = std::env::temp_dir().join(format!("data-processor-{}-{}", test_name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            for (file_name, entries) in files {
+                fs::write(path.join(file_name), entries.to_string()).unwrap();
+            }
+            Self(path)
+        }
+    }
+    impl Drop for TestLaptopsDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// a valid laptops file entry with the given name and url
+    fn entry(name: &str, url: &str) -> Value {
+        json!({
+            "name": name,
+            "url": url,
+            "price": 4000.0,
+            "cpu": "Intel Core i7-1165G7",
+            "cpu_bench": {"Cinebench R23 CPU (Multi Core)": 5076.0},
+            "gpu": "NVIDIA GeForce MX450",
+            "gpu_bench": {"3DMark Time Spy Graphics": 1688.0},
+            "image_urls": [],
+            "ram": 16,
+            "weight": 1500.0
+        })
+    }
+
+    /// an entry with the given field replaced
+    fn entry_with(name: &str, url: &str, field: &str, value: Value) -> Value {
+        let mut entry = entry(name, url);
+        entry[field] = value;
+        entry
+    }
+
+    /// validates the given laptops files, with prices in the default base currency
+    fn validate(test_name: &str, files: &[(&str, Value)]) -> LaptopsFilesValidation {
+        let laptops_dir = TestLaptopsDir::new(test_name, files);
+        let exchange_rates = ExchangeRates::load(&laptops_dir.0.join("exchange_rates.json")).unwrap();
+        validate_laptops_files(&laptops_dir.0, &exchange_rates).unwrap()
+    }
+
+    /// the severity, file, entry index and field of each problem
+    fn problems(validation: &LaptopsFilesValidation) -> Vec<(ProblemSeverity, &str, Option<usize>, Option<&str>)> {
+        validation
+            .problems
+            .iter()
+            .map(|problem| {
+                (
+                    problem.severity,
+                    problem.file_name.as_str(),
+                    problem.entry_index,
+                    problem.field.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    fn valid_names(validation: &LaptopsFilesValidation) -> Vec<&str> {
+        validation.valid_entries.iter().map(|valid| valid.entry.name()).collect()
+    }
+
+    #[test]
+    fn only_laptops_files_are_loaded() {
+        let validation = validate(
+            "only-laptops-files",
+            &[
+                ("ivory-laptops.json", json!([entry("a", "https://a")])),
+                ("ivory-laptops.json.bak", json!([entry("b", "https://b")])),
+                ("old-ivory-laptops.json", json!([entry("c", "https://c")])),
+                ("Ivory-laptops.json", json!([entry("d", "https://d")])),
+            ],
+        );
+        assert!(problems(&validation).is_empty());
+        assert_eq!(valid_names(&validation), vec!["a"]);
+        assert_eq!(validation.valid_entries[0].store, "ivory");
+    }
+
+    #[test]
+    fn non_positive_prices_are_errors() {
+        let validation = validate(
+            "non-positive-prices",
+            &[(
+                "ivory-laptops.json",
+                json!([
+                    entry_with("a", "https://a", "price", json!(0.0)),
+                    entry_with("b", "https://b", "price", json!(-100.0)),
+                ]),
+            )],
+        );
+        assert_eq!(
+            problems(&validation),
+            vec![
+                (ProblemSeverity::Error, "ivory-laptops.json", Some(0), Some("price")),
+                (ProblemSeverity::Error, "ivory-laptops.json", Some(1), Some("price")),
+            ]
+        );
+        assert!(validation.valid_entries.is_empty());
+    }
+
+    #[test]
+    fn zero_ram_is_an_error() {
+        let validation = validate(
+            "zero-ram",
+            &[("ivory-laptops.json", json!([entry_with("a", "https://a", "ram", json!(0))]))],
+        );
+        assert_eq!(
+            problems(&validation),
+            vec![(ProblemSeverity::Error, "ivory-laptops.json", Some(0), Some("ram"))]
+        );
+        assert!(validation.valid_entries.is_empty());
+    }
+
+    #[test]
+    fn empty_urls_are_errors() {
+        let validation = validate(
+            "empty-urls",
+            &[("ivory-laptops.json", json!([entry("a", " ")]))],
+        );
+        assert_eq!(
+            problems(&validation),
+            vec![(ProblemSeverity::Error, "ivory-laptops.json", Some(0), Some("url"))]
+        );
+        assert!(validation.valid_entries.is_empty());
+    }
+
+    #[test]
+    fn duplicate_urls_are_warnings() {
+        let validation = validate(
+            "duplicate-urls",
+            &[
+                ("bug-laptops.json", json!([entry("a", "https://a")])),
+                ("ivory-laptops.json", json!([entry("b", "https://b"), entry("c", "https://a")])),
+            ],
+        );
+        assert_eq!(
+            problems(&validation),
+            vec![(ProblemSeverity::Warning, "ivory-laptops.json", Some(1), Some("url"))]
+        );
+        assert!(validation.problems[0].description.contains("bug-laptops.json[0]"));
+        // warnings don't prevent the entries from being loaded
+        assert_eq!(valid_names(&validation), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn unknown_currencies_are_errors() {
+        let validation = validate(
+            "unknown-currencies",
+            &[(
+                "ivory-laptops.json",
+                json!([
+                    entry_with("a", "https://a", "currency", json!("XYZ")),
+                    entry_with("b", "https://b", "currency", json!("ils")),
+                ]),
+            )],
+        );
+        assert_eq!(
+            problems(&validation),
+            vec![(ProblemSeverity::Error, "ivory-laptops.json", Some(0), Some("currency"))]
+        );
+        // currency codes are case insensitive
+        assert_eq!(valid_names(&validation), vec!["b"]);
+    }
+
+    #[test]
+    fn a_bad_entry_doesnt_prevent_validating_the_others() {
+        let validation = validate(
+            "bad-entry",
+            &[(
+                "ivory-laptops.json",
+                json!([
+                    entry("a", "https://a"),
+                    entry_with("b", "https://b", "price", json!("cheap")),
+                    entry("c", "https://c"),
+                    entry_with("d", "https://d", "ram", json!(-8)),
+                ]),
+            )],
+        );
+        assert_eq!(
+            problems(&validation),
+            vec![
+                (ProblemSeverity::Error, "ivory-laptops.json", Some(1), Some("price")),
+                (ProblemSeverity::Error, "ivory-laptops.json", Some(3), Some("ram")),
+            ]
+        );
+        assert_eq!(validation.problems[0].laptop_name.as_deref(), Some("b"));
+        assert_eq!(valid_names(&validation), vec!["a", "c"]);
+        assert!(validation.report().is_err());
+    }
+}
@@ -5,10 +5,38 @@ pub enum DataProcessorErrorKind {
     FailedToOpenCategoriesFile,
     FailedToDeserializeCategoriesFile,
     FailedToReadLaptopsDirectory,
-    FailedToOpenLaptopsFile { name: String },
-    FailedToDeserializeLaptopsFile { name: String },
     DatabaseError,
+    InvalidBenchmarkScoreInCategory { category_name: String, pattern: String },
+    CategoryScoresSumToZero { category_name: String },
+    NonFiniteLaptopScore { laptop_id: i32, category_id: i32 },
+    FailedToOpenExchangeRatesFile,
+    FailedToDeserializeExchangeRatesFile,
+    InvalidExchangeRate { currency: String },
+    FailedToWriteProposedCategoriesFile,
+    FailedToWriteCategoryWeightsReport,
+    UnknownGeneration { generation: i64 },
+    NoPreviousGeneration,
+    MissingCommandArgument { name: String },
+    InvalidCommandArgument { name: String, value: String },
+    UnexpectedCommandArgument { value: String },
+    CategoryNotInCategoriesFile { category_name: String },
+    CategoryAlreadyExists { category_name: String },
+    UnknownCategory { category_name: String },
+    FailedToWriteReloadDryRunReport,
+    InvalidLaptopsFiles { errors_amount: usize },
+    InvalidCategoriesFile { errors_amount: usize },
 }
 
 generate_error_types! {data_processor}
 
+
+// diesel's transactions require the error type to be convertible from a diesel error, which is
+// returned when beginning, committing or rolling back the transaction fails.
+impl From<diesel::result::Error> for DataProcessorError {
+    fn from(error: diesel::result::Error) -> Self {
+        Self {
+            kind: DataProcessorErrorKind::DatabaseError,
+            inner: Some(Box::new(error)),
+        }
+    }
+}
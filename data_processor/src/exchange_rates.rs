@@ -0,0 +1,108 @@
+use std::{collections::HashMap, fs::OpenOptions, path::Path};
+
+use crate::errors::*;
+use serde::Deserialize;
+
+/// the currency of all prices when there is no exchange rates file
+const DEFAULT_BASE_CURRENCY: &str = "ILS";
+
+/// the exchange rates file, which describes how prices in different currencies are converted to the
+/// base currency, in which all prices are stored in the database. for example:
+/// `{"baseCurrency": "ILS", "rates": {"USD": 3.25, "EUR": 3.8}}`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeRates {
+    pub base_currency: String,
+    /// the value of a single unit of each currency in the base currency, mapped by the currency code
+    rates: HashMap<String, f32>,
+}
+
+/// a price that was converted to the base currency
+#[derive(Debug)]
+pub struct ConvertedPrice {
+    /// the price in the base currency
+    pub price: f32,
+    /// the original price, in the original currency
+    pub listed_price: f32,
+    /// the code of the original currency
+    pub currency: String,
+}
+
+impl ExchangeRates {
+    /// loads and validates the exchange rates file at the given path. if there is no such file, all
+    /// prices are assumed to be in the default base currency.
+    pub fn load(file_path: &Path) -> Result<Self> {
+        if !file_path.exists() {
+            println!(
+                "no exchange rates file found at {}, assuming all prices are in {}",
+                file_path.display(),
+                DEFAULT_BASE_CURRENCY
+            );
+            return Ok(Self {
+                base_currency: DEFAULT_BASE_CURRENCY.to_string(),
+                rates: HashMap::new(),
+            });
+        }
+
+        let exchange_rates_file = OpenOptions::new()
+            .read(true)
+            .open(file_path)
+            .into_data_processor_result(DataProcessorErrorKind::FailedToOpenExchangeRatesFile)?;
+        let exchange_rates: Self = serde_json::de::from_reader(exchange_rates_file)
+            .into_data_processor_result(
+                DataProcessorErrorKind::FailedToDeserializeExchangeRatesFile,
+            )?;
+
+        // currency codes are case insensitive, so they are always stored in upper case
+        let base_currency = exchange_rates.base_currency.to_uppercase();
+        let mut rates = HashMap::new();
+        for (currency, rate) in exchange_rates.rates {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(DataProcessorErrorKind::InvalidExchangeRate { currency }
+                    .into_empty_data_processor_error());
+            }
+            rates.insert(currency.to_uppercase(), rate);
+        }
+        Ok(Self {
+            base_currency,
+            rates,
+        })
+    }
+
+    /// returns the value of a single unit of the currency with the given code in the base
+    /// currency, or None if the currency is unknown
+    fn rate(&self, currency: &str) -> Option<f32> {
+        if currency == self.base_currency {
+            Some(1.0)
+        } else {
+            self.rates.get(currency).copied()
+        }
+    }
+
+    /// converts a price in the currency with the given code, or in the base currency if no code
+    /// was given, to the base currency. returns None if the currency is unknown.
+    pub fn convert(&self, price: f32, currency: Option<&str>) -> Option<ConvertedPrice> {
+        let currency = match currency {
+            Some(currency) => currency.to_uppercase(),
+            None => self.base_currency.clone(),
+        };
+        let rate = self.rate(&currency)?;
+        Some(ConvertedPrice {
+            price: price * rate,
+            listed_price: price,
+            currency,
+        })
+    }
+
+    /// returns the code and rate of every known currency, including the base currency
+    pub fn currencies_and_rates(&self) -> Vec<(&str, f32)> {
+        let mut currencies_and_rates = vec![(self.base_currency.as_str(), 1.0)];
+        currencies_and_rates.extend(
+            self.rates
+                .iter()
+                .filter(|(currency, _)| **currency != self.base_currency)
+                .map(|(currency, rate)| (currency.as_str(), *rate)),
+        );
+        currencies_and_rates
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+const DEFAULT_LAPTOPS_DIR_PATH: &str = "laptops";
+const DEFAULT_CATEGORIES_FILE_PATH: &str = "categories.json";
+const DEFAULT_EXCHANGE_RATES_FILE_PATH: &str = "exchange_rates.json";
+const DEFAULT_REPORTS_DIR_PATH: &str = ".";
+
+/// the paths of the files that the commands load, and of the directory to which they write their
+/// reports. by default, these are paths relative to the working directory, but they can be changed
+/// using command line flags.
+#[derive(Debug, Clone)]
+pub struct InputPaths {
+    /// the directory containing the laptops files
+    pub laptops_dir: PathBuf,
+    pub categories_file: PathBuf,
+    /// the optional exchange rates file
+    pub exchange_rates_file: PathBuf,
+    /// the directory to which the commands that produce reports write them
+    pub reports_dir: PathBuf,
+}
+
+impl Default for InputPaths {
+    fn default() -> Self {
+        Self {
+            laptops_dir: PathBuf::from(DEFAULT_LAPTOPS_DIR_PATH),
+            categories_file: PathBuf::from(DEFAULT_CATEGORIES_FILE_PATH),
+            exchange_rates_file: PathBuf::from(DEFAULT_EXCHANGE_RATES_FILE_PATH),
+            reports_dir: PathBuf::from(DEFAULT_REPORTS_DIR_PATH),
+        }
+    }
+}
+
+impl InputPaths {
+    /// the path of the report file with the given name in the reports directory
+    pub fn report_file(&self, file_name: &str) -> PathBuf {
+        self.reports_dir.join(file_name)
+    }
+}
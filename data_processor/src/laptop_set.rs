@@ -1,6 +1,18 @@
 use std::collections::{hash_map::Entry, HashMap};
 
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use serde_json::Value;
+
+use crate::exchange_rates::ConvertedPrice;
+
+lazy_static! {
+    /// the regex used to extract the storage size from the storage description of a laptop.
+    /// examples for a valid storage description: 512GB SSD, 1TB SSD M.2 2280 PCIe 3.0x4 NVMe
+    static ref STORAGE_SIZE_REGEX: Regex = Regex::new(r"(\d+)\s*(GB|TB)").unwrap();
+}
 
 /// represents a single entry in a laptops file
 #[derive(Debug, Deserialize)]
@@ -19,13 +31,28 @@ pub struct LaptopsFileEntry {
 
     #[serde(rename = "weight")]
     weight_grams: f32,
+
+    /// the description of the laptop's storage, for example: 512GB SSD.
+    /// not all stores provide this field.
+    #[serde(rename = "ssd", default)]
+    storage: Option<String>,
+
+    /// the brand and model of the laptop. laptops with the same brand and model are variants
+    /// of the same model family. not all stores provide these fields.
+    #[serde(default)]
+    brand: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+
+    /// the code of the currency of the price. if not provided, the price is in the base currency.
+    #[serde(default)]
+    currency: Option<String>,
 }
 impl LaptopsFileEntry {
-    fn name_and_information(self) -> (String, LaptopInformation) {
+    fn name_and_information(self, price: ConvertedPrice, store: &str) -> (String, LaptopInformation) {
         let LaptopsFileEntry {
             name,
             url,
-            price,
             cpu,
             cpu_bench,
             gpu,
@@ -33,12 +60,19 @@ impl LaptopsFileEntry {
             image_urls,
             weight_grams,
             ram_gigabytes,
+            storage,
+            brand,
+            model,
+            ..
         } = self;
         (
             name,
             LaptopInformation {
                 url,
-                price,
+                price: price.price,
+                listed_price: price.listed_price,
+                currency: price.currency,
+                store: store.to_string(),
                 cpu,
                 cpu_bench,
                 gpu,
@@ -46,15 +80,133 @@ impl LaptopsFileEntry {
                 image_urls,
                 ram_gigabytes,
                 weight_grams,
+                storage_gigabytes: storage.as_deref().and_then(parse_storage_gigabytes),
+                brand,
+                model,
             },
         )
     }
+
+    /// returns the name of the laptop
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// returns the price of the laptop, in the currency of the entry
+    pub fn price(&self) -> f32 {
+        self.price
+    }
+
+    /// returns the code of the currency of the entry's price, if it was provided
+    pub fn currency(&self) -> Option<&str> {
+        self.currency.as_deref()
+    }
+
+    /// returns the url of the laptop's page in the store
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// returns the amount of ram of the laptop, in gigabytes
+    pub fn ram_gigabytes(&self) -> i32 {
+        self.ram_gigabytes
+    }
+
+    /// whether the entry has any cpu or gpu benchmarks. a laptop without benchmarks can't be scored.
+    pub fn has_benchmarks(&self) -> bool {
+        !self.cpu_bench.is_empty() || !self.gpu_bench.is_empty()
+    }
+
+    /// finds the field that prevents the given json value from being deserialized as an entry, and
+    /// describes why. this is needed because the deserialization error of a whole entry doesn't say
+    /// which of its fields is invalid.
+    pub fn find_invalid_field(entry: &Value) -> Option<(String, String)> {
+        /// checks whether a value of a field has the field's type, and describes why if it doesn't
+        type FieldCheck = fn(&Value) -> Option<String>;
+        fn check<T: DeserializeOwned>(value: &Value) -> Option<String> {
+            T::deserialize(value).err().map(|error| error.to_string())
+        }
+
+        // the name of each field in the laptops file, whether it is required, and a function that
+        // checks whether its value has the right type
+        let fields: [(&str, bool, FieldCheck); 14] = [
+            ("name", true, check::<String>),
+            ("url", true, check::<String>),
+            ("price", true, check::<f32>),
+            ("cpu", true, check::<String>),
+            ("cpu_bench", true, check::<LaptopPuBenchmarksData>),
+            ("gpu", true, check::<String>),
+            ("gpu_bench", true, check::<LaptopPuBenchmarksData>),
+            ("image_urls", true, check::<Vec<String>>),
+            ("ram", true, check::<i32>),
+            ("weight", true, check::<f32>),
+            ("ssd", false, check::<Option<String>>),
+            ("brand", false, check::<Option<String>>),
+            ("model", false, check::<Option<String>>),
+            ("currency", false, check::<Option<String>>),
+        ];
+        let entry = entry.as_object()?;
+        for (field, required, check) in fields {
+            match entry.get(field) {
+                Some(value) => {
+                    if let Some(reason) = check(value) {
+                        return Some((field.to_string(), reason));
+                    }
+                }
+                None if required => return Some((field.to_string(), "the field is missing".to_string())),
+                None => {}
+            }
+        }
+        None
+    }
+
+    /// finds the first number in the entry that is not finite, and returns the name of its field.
+    /// such numbers can't be compared or summed in a meaningful way, so a single one of them would
+    /// poison the global benchmarks and every score that is calculated from them.
+    pub fn find_non_finite_field(&self) -> Option<String> {
+        if !self.price.is_finite() {
+            return Some("price".to_string());
+        }
+        if !self.weight_grams.is_finite() {
+            return Some("weight".to_string());
+        }
+        let benchmarks = [("cpu_bench", &self.cpu_bench), ("gpu_bench", &self.gpu_bench)];
+        for (field, benchmarks) in benchmarks {
+            // the benchmarks are sorted by name so that the same field is always reported
+            let mut benchmark_names: Vec<&String> = benchmarks
+                .iter()
+                .filter(|(_, score)| !score.is_finite())
+                .map(|(benchmark_name, _)| benchmark_name)
+                .collect();
+            benchmark_names.sort();
+            if let Some(benchmark_name) = benchmark_names.first() {
+                return Some(format!("{}.{}", field, benchmark_name));
+            }
+        }
+        None
+    }
+}
+
+/// extracts the storage size in gigabytes from the storage description of a laptop
+fn parse_storage_gigabytes(storage: &str) -> Option<i32> {
+    let captures = STORAGE_SIZE_REGEX.captures(storage)?;
+    let size: i32 = captures[1].parse().ok()?;
+    match &captures[2] {
+        "TB" => Some(size * 1024),
+        _ => Some(size),
+    }
 }
 
 #[derive(Debug)]
 pub struct LaptopInformation {
     pub url: String,
+    /// the price in the base currency
     pub price: f32,
+    /// the price in the currency in which the store listed the laptop
+    pub listed_price: f32,
+    pub currency: String,
+    /// the store that listed the laptop at its price
+    pub store: String,
     pub cpu: String,
     pub cpu_bench: LaptopPuBenchmarksData,
     pub gpu: String,
@@ -62,6 +214,9 @@ pub struct LaptopInformation {
     pub image_urls: Vec<String>,
     pub ram_gigabytes: i32,
     pub weight_grams: f32,
+    pub storage_gigabytes: Option<i32>,
+    pub brand: Option<String>,
+    pub model: Option<String>,
 }
 
 /// the benchmarks of the cpu or gpu in a laptop object from the laptops.json file,
@@ -82,9 +237,10 @@ impl LaptopSet {
     /// updates the laptop set with a new laptop. the laptop set will add the laptop to the
     /// map if no lapotp with this name was previously added, otherwise it will update the
     /// previously added laptop with any missing fields that were found in the new given
-    /// laptop.
-    pub fn update(&mut self, laptop: LaptopsFileEntry) {
-        let (name, new_laptop_information) = laptop.name_and_information();
+    /// laptop. the price of the laptop must already be converted to the base currency, so that
+    /// the prices of laptops from stores that list them in different currencies can be compared.
+    pub fn update(&mut self, laptop: LaptopsFileEntry, price: ConvertedPrice, store: &str) {
+        let (name, new_laptop_information) = laptop.name_and_information(price, store);
         match self.laptop_infos_by_name.entry(name) {
             Entry::Occupied(mut occupied_entry) => {
                 let existing_laptop = occupied_entry.get_mut();
@@ -93,10 +249,29 @@ impl LaptopSet {
                 let LaptopInformation {
                     image_urls: new_image_urls,
                     price: new_price,
+                    listed_price: new_listed_price,
+                    currency: new_currency,
+                    store: new_store,
                     url: new_url,
+                    storage_gigabytes: new_storage_gigabytes,
+                    brand: new_brand,
+                    model: new_model,
                     ..
                 } = new_laptop_information;
 
+                // if only the new laptop's store provided the brand or the model, use them
+                if existing_laptop.brand.is_none() {
+                    existing_laptop.brand = new_brand;
+                }
+                if existing_laptop.model.is_none() {
+                    existing_laptop.model = new_model;
+                }
+
+                // if only the new laptop's store provided the storage size, use it
+                if existing_laptop.storage_gigabytes.is_none() {
+                    existing_laptop.storage_gigabytes = new_storage_gigabytes;
+                }
+
                 // if the new laptop has image urls, while the existing one doesn't,
                 // add the image urls of the new one to the existing laptop
                 if existing_laptop.image_urls.is_empty() && !new_image_urls.is_empty() {
@@ -105,10 +280,13 @@ impl LaptopSet {
 
                 // if the new laptop's price is lower than the existing laptop's price,
                 // use the price of the new laptop, which is the lower price.
-                // if we use the price of the new laptop, we must also update the url
-                // to the url of the new laptop
+                // if we use the price of the new laptop, we must also update the url,
+                // the listing currency and the store to those of the new laptop
                 if new_price < existing_laptop.price {
                     existing_laptop.price = new_price;
+                    existing_laptop.listed_price = new_listed_price;
+                    existing_laptop.currency = new_currency;
+                    existing_laptop.store = new_store;
                     existing_laptop.url = new_url;
                 }
             }
@@ -1,42 +1,147 @@
+// diesel 1.x derives expand to impl blocks nested inside constants, which newer
+// compilers flag as non-local definitions.
+#![allow(non_local_definitions)]
+
+mod benchmark_patterns;
 mod cli;
 mod commands;
 mod errors;
+mod exchange_rates;
+mod input_paths;
 mod laptop_set;
+#[cfg(test)]
+mod test_database;
 
-use std::{time::Instant};
+use std::{process, time::Instant};
 
-use commands::{calculate_scores, load_categories, load_laptops, reload_all};
+use commands::{
+    activate_generation, add_category, remove_category, update_category, calculate_scores, create_generation, fit_category_weights, list_generations, lint_categories, load_categories,
+    load_laptops, mark_active_generation_modified, prune_generations, reload_all, reload_dry_run, rollback_generation, update_laptops, use_active_generation, validate_laptops,
+    DEFAULT_KEPT_GENERATIONS,
+};
+use diesel::{Connection, PgConnection};
+use errors::Result;
+use input_paths::InputPaths;
 
-use crate::cli::{DataProcessorCliCommand, create_data_processor_cli};
+use crate::cli::{DataProcessorCliCommand, USAGE, create_data_processor_cli, parse_command_line_arguments};
 
 fn main() {
-    let mut cli = create_data_processor_cli();
+    let arguments = match parse_command_line_arguments(std::env::args().skip(1)){
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if arguments.help{
+        println!("{}", USAGE);
+        return;
+    }
+
     let db_connection = db_access::get_db_connection();
+    match arguments.command{
+        // when a command is given it is executed once, so that reloads can be automated
+        Some(command) => {
+            if execute_command(command, &arguments.command_arguments, &arguments.input_paths, &db_connection).is_err(){
+                process::exit(1);
+            }
+        }
+        None => run_shell(&arguments.input_paths, &db_connection),
+    }
+}
 
+/// reads commands from the user and executes them until the user exits
+fn run_shell(input_paths: &InputPaths, db_connection: &PgConnection){
+    let mut cli = create_data_processor_cli();
     loop{
         // read the command from the user
-        let command = cli.get_next_command();
-
-        // start measuring the time that takes to execute the command
-        let start = Instant::now();
-
-        // execute the command
-        let result = match command {
-            DataProcessorCliCommand::LoadCategories => load_categories(&db_connection),
-            DataProcessorCliCommand::LoadLaptops => load_laptops(&db_connection),
-            DataProcessorCliCommand::CalculateScores => calculate_scores(&db_connection),
-            DataProcessorCliCommand::ReloadAll => reload_all(&db_connection),
-            DataProcessorCliCommand::Exit => break
+        let (command, command_arguments) = cli.get_next_command();
+        if command == DataProcessorCliCommand::Exit{
+            break;
+        }
+
+        // the error was already reported, and the user may just try again
+        let _ = execute_command(command, &command_arguments, input_paths, db_connection);
+    }
+}
+
+/// executes a command, reporting its elapsed time or its error
+fn execute_command(
+    command: DataProcessorCliCommand,
+    command_arguments: &[String],
+    input_paths: &InputPaths,
+    db_connection: &PgConnection,
+)->Result<()>{
+    // start measuring the time that takes to execute the command
+    let start = Instant::now();
+
+    // execute the command, along with the activation of the generation that it created, in a single
+    // transaction, so that if it fails midway, the database is rolled back to the previous dataset
+    // instead of being left with a half filled generation.
+    let result = db_connection.transaction(|| {
+        command.validate_arguments(command_arguments)?;
+        let created_generation = if command.creates_generation(){
+            Some(create_generation(db_connection, &command.name())?)
+        } else{
+            // commands that only read the dataset, or only make small changes to it, use the one that the
+            // selector uses
+            use_active_generation(db_connection)?;
+            None
         };
 
-        match result{
-            Ok(())=>{
-                let elapsed = Instant::now() - start;
-                println!("elapsed: {:?}", elapsed);
-            },
-            Err(error) => {
-                eprintln!("Error: {:?}", error);
+        match command {
+            DataProcessorCliCommand::LoadCategories => load_categories(db_connection, input_paths),
+            DataProcessorCliCommand::LoadLaptops => load_laptops(db_connection, input_paths),
+            DataProcessorCliCommand::UpdateLaptops => update_laptops(db_connection, input_paths),
+            DataProcessorCliCommand::CalculateScores => calculate_scores(db_connection),
+            DataProcessorCliCommand::ReloadAll => reload_all(db_connection, input_paths),
+            DataProcessorCliCommand::ReloadDryRun => reload_dry_run(db_connection, input_paths),
+            DataProcessorCliCommand::ValidateLaptops => validate_laptops(input_paths),
+            DataProcessorCliCommand::LintCategories => lint_categories(db_connection, input_paths),
+            DataProcessorCliCommand::FitCategoryWeights => fit_category_weights(db_connection, input_paths),
+            DataProcessorCliCommand::ListGenerations => list_generations(db_connection),
+            DataProcessorCliCommand::ActivateGeneration => {
+                activate_generation(db_connection, command.parse_required_argument(command_arguments)?)
+            }
+            DataProcessorCliCommand::RollbackGeneration => rollback_generation(db_connection),
+            DataProcessorCliCommand::AddCategory => {
+                let category_name: String = command.parse_required_argument(command_arguments)?;
+                add_category(db_connection, input_paths, &category_name)
+            }
+            DataProcessorCliCommand::UpdateCategory => {
+                let category_name: String = command.parse_required_argument(command_arguments)?;
+                update_category(db_connection, input_paths, &category_name)
             }
+            DataProcessorCliCommand::RemoveCategory => {
+                let category_name: String = command.parse_required_argument(command_arguments)?;
+                remove_category(db_connection, &category_name)
+            }
+            DataProcessorCliCommand::PruneGenerations => {
+                let kept_generations = command.parse_argument(command_arguments)?.unwrap_or(DEFAULT_KEPT_GENERATIONS);
+                prune_generations(db_connection, kept_generations)
+            }
+            DataProcessorCliCommand::Exit => Ok(()),
+        }?;
+
+        // make the selector use the new generation
+        if let Some(generation) = created_generation{
+            activate_generation(db_connection, generation)?;
+        } else if command.modifies_active_generation(){
+            mark_active_generation_modified(db_connection)?;
+        }
+        Ok(())
+    });
+
+    match result{
+        Ok(())=>{
+            let elapsed = Instant::now() - start;
+            println!("elapsed: {:?}", elapsed);
+            Ok(())
+        },
+        Err(error) => {
+            eprintln!("Error: {:?}", error);
+            eprintln!("all changes were rolled back");
+            Err(error)
         }
     }
 }
@@ -0,0 +1,77 @@
+//! fixtures for the tests that query the development database. each test gets a connection whose
+//! dataset tables are replaced by empty temporary tables, which shadow the tables of the public schema
+//! until the connection's test transaction is rolled back, so the tests never see or modify the
+//! actual dataset.
+
+use diesel::{Connection, PgConnection, RunQueryDsl};
+
+/// the tables that store the dataset, which are replaced by temporary tables
+const DATASET_TABLES: &[&str] = &[
+    "laptop",
+    "laptop_image",
+    "laptop_specs",
+    "global_benchmark",
+    "benchmark",
+    "category",
+    "benchmark_score_in_category",
+    "laptop_score_in_category",
+    "price_limits",
+    "exchange_rate",
+];
+
+/// connects to the development database, and creates an empty dataset
+pub fn connect_to_test_dataset() -> PgConnection {
+    let db_connection = db_access::get_db_connection();
+    db_connection
+        .begin_test_transaction()
+        .expect("failed to begin the test transaction");
+    for table in DATASET_TABLES {
+        execute(
+            &db_connection,
+            &format!("CREATE TEMPORARY TABLE {0} (LIKE public.{0} INCLUDING ALL)", table),
+        );
+    }
+    db_connection
+}
+
+/// executes a statement that modifies the test database
+pub fn execute(db_connection: &PgConnection, statement: &str) {
+    diesel::sql_query(statement)
+        .execute(db_connection)
+        .unwrap_or_else(|error| panic!("failed to execute {}: {}", statement, error));
+}
+
+/// the first id of the generations of the tests, which is far beyond the ids of the actual generations, so that
+/// the schemas of the tests' generations don't collide with theirs
+pub const FIRST_TEST_GENERATION: i64 = 1_000_000_001;
+
+/// connects to the development database, and replaces the generations with the given amount of empty generations,
+/// whose ids start from `FIRST_TEST_GENERATION`, where the generation with the given index is active. the
+/// generations' schemas are created, but they are empty.
+pub fn connect_to_test_generations(generations_amount: i64, active_generation_index: i64) -> PgConnection {
+    let db_connection = db_access::get_db_connection();
+    db_connection
+        .begin_test_transaction()
+        .expect("failed to begin the test transaction");
+    for table in ["dataset_generation", "dataset_version"] {
+        execute(
+            &db_connection,
+            &format!("CREATE TEMPORARY TABLE {0} (LIKE public.{0} INCLUDING ALL)", table),
+        );
+    }
+    for generation in FIRST_TEST_GENERATION..FIRST_TEST_GENERATION + generations_amount {
+        execute(
+            &db_connection,
+            &format!("INSERT INTO dataset_generation (id, command) VALUES ({}, 'reload all')", generation),
+        );
+        execute(&db_connection, &format!("CREATE SCHEMA dataset_{}", generation));
+    }
+    execute(
+        &db_connection,
+        &format!(
+            "INSERT INTO dataset_version (id, generation, active_generation) VALUES (1, 1, {})",
+            FIRST_TEST_GENERATION + active_generation_index
+        ),
+    );
+    db_connection
+}
@@ -0,0 +1,2 @@
+DROP INDEX laptop_score_in_category_category_index;
+DROP INDEX laptop_price_index;
@@ -0,0 +1,7 @@
+-- used by the database side ranking to filter the laptops by price
+CREATE INDEX laptop_price_index ON laptop (price, id);
+
+-- used by the database side ranking to find the scores of the laptops in the categories that the
+-- user has selected, without having to read the table itself. `INCLUDE` requires PostgreSQL 11 or newer.
+CREATE INDEX laptop_score_in_category_category_index
+	ON laptop_score_in_category (category_id, laptop_id) INCLUDE (score);
@@ -0,0 +1 @@
+ALTER TABLE laptop_specs DROP COLUMN storage_gigabytes;
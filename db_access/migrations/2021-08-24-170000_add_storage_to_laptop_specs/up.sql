@@ -0,0 +1 @@
+ALTER TABLE laptop_specs ADD COLUMN storage_gigabytes INTEGER;
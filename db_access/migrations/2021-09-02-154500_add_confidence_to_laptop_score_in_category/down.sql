@@ -0,0 +1 @@
+ALTER TABLE laptop_score_in_category DROP COLUMN confidence;
@@ -0,0 +1,3 @@
+-- the share of the category's benchmark weights that is backed by benchmarks that were actually
+-- measured for the laptop, rather than filled with the average score of the benchmark.
+ALTER TABLE laptop_score_in_category ADD COLUMN confidence REAL NOT NULL DEFAULT 1;
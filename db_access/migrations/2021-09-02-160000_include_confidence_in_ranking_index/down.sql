@@ -0,0 +1,3 @@
+DROP INDEX laptop_score_in_category_category_index;
+CREATE INDEX laptop_score_in_category_category_index
+	ON laptop_score_in_category (category_id, laptop_id) INCLUDE (score);
@@ -0,0 +1,7 @@
+-- the database side ranking also reads the confidence of each score, so it is included in the index
+-- as well, so that the ranking still doesn't have to read the table itself. the tables of the dataset
+-- generations copy the indexes of the public tables, so generations that were created before this
+-- migration keep the old index until they are reloaded.
+DROP INDEX laptop_score_in_category_category_index;
+CREATE INDEX laptop_score_in_category_category_index
+	ON laptop_score_in_category (category_id, laptop_id) INCLUDE (score, confidence);
@@ -0,0 +1,2 @@
+ALTER TABLE laptop DROP COLUMN model;
+ALTER TABLE laptop DROP COLUMN brand;
@@ -0,0 +1,4 @@
+-- the brand and model of the laptop, as provided by the stores. laptops that share the same brand and
+-- model are variants of the same model family, which differ in specs such as ram, storage or color.
+ALTER TABLE laptop ADD COLUMN brand TEXT;
+ALTER TABLE laptop ADD COLUMN model TEXT;
@@ -0,0 +1,8 @@
+-- a single document holding the generation of the dataset, which the data processor increments
+-- whenever it modifies the data, so that the selector knows when its cached results are outdated.
+CREATE TABLE dataset_version (
+	id INTEGER PRIMARY KEY,
+	generation BIGINT NOT NULL
+);
+
+INSERT INTO dataset_version (id, generation) VALUES (0, 0);
@@ -0,0 +1,4 @@
+ALTER TABLE laptop DROP COLUMN listed_price;
+ALTER TABLE laptop DROP COLUMN currency;
+ALTER TABLE price_limits DROP COLUMN currency;
+DROP TABLE exchange_rate;
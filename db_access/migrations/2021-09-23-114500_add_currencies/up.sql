@@ -0,0 +1,18 @@
+-- the value of a single unit of each currency in the base currency, which is the currency of the price
+-- limits. the base currency itself is included with a rate of 1.
+CREATE TABLE exchange_rate (
+	currency TEXT PRIMARY KEY,
+	rate REAL NOT NULL
+);
+
+-- the currency in which all prices are stored, which so far were always shekels
+ALTER TABLE price_limits ADD COLUMN currency TEXT NOT NULL DEFAULT 'ILS';
+
+-- the price of a laptop is always converted to the base currency, but the price and the currency in which
+-- the store listed the laptop are kept too.
+ALTER TABLE laptop ADD COLUMN currency TEXT NOT NULL DEFAULT 'ILS';
+ALTER TABLE laptop ADD COLUMN listed_price REAL;
+UPDATE laptop SET listed_price = price;
+ALTER TABLE laptop ALTER COLUMN listed_price SET NOT NULL;
+
+INSERT INTO exchange_rate (currency, rate) VALUES ('ILS', 1);
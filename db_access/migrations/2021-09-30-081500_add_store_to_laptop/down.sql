@@ -0,0 +1 @@
+ALTER TABLE laptop DROP COLUMN store;
@@ -0,0 +1,5 @@
+-- the store that listed the laptop, which is the prefix of the laptops file that it was loaded from,
+-- for example `ivory` for `ivory-laptops.json`. existing laptops take it from the host of their url.
+ALTER TABLE laptop ADD COLUMN store TEXT;
+UPDATE laptop SET store = COALESCE(substring(url from '^[a-z]+://(?:www\.)?([a-z0-9-]+)\.'), '');
+ALTER TABLE laptop ALTER COLUMN store SET NOT NULL;
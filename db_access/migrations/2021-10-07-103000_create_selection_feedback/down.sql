@@ -0,0 +1 @@
+DROP TABLE selection_feedback;
@@ -0,0 +1,10 @@
+-- feedback about the laptops that a selection returned. the laptop is recorded by its name rather
+-- than by its id, since the ids change whenever the laptops are reloaded.
+CREATE TABLE selection_feedback (
+    id SERIAL PRIMARY KEY,
+    laptop_name TEXT NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('clicked', 'liked', 'dismissed')),
+    -- the json parameters of the selection request that returned the laptop
+    selection_parameters TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,12 @@
+ALTER TABLE dataset_version DROP COLUMN active_generation;
+DROP TABLE dataset_generation;
+
+-- drop the schemas of all generations other than the public one
+DO $$
+DECLARE
+	generation_schema TEXT;
+BEGIN
+	FOR generation_schema IN SELECT nspname FROM pg_namespace WHERE nspname LIKE 'dataset\_%' LOOP
+		EXECUTE format('DROP SCHEMA %I CASCADE', generation_schema);
+	END LOOP;
+END $$;
@@ -0,0 +1,16 @@
+-- the dataset generations that are kept side by side, so that a bad reload can be rolled back. the dataset
+-- of generation 0 is stored in the public schema, where the data was stored before datasets were versioned,
+-- and the dataset of every other generation is stored in its own schema, named `dataset_<id>`. the tables
+-- in the public schema are also the templates of the tables of new generations.
+CREATE TABLE dataset_generation (
+	id BIGSERIAL PRIMARY KEY,
+	-- the data processor command that created the generation
+	command TEXT NOT NULL,
+	created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+INSERT INTO dataset_generation (id, command) VALUES (0, 'initial dataset');
+
+-- the generation whose dataset the selector uses
+ALTER TABLE dataset_version
+	ADD COLUMN active_generation BIGINT NOT NULL DEFAULT 0 REFERENCES dataset_generation(id);
@@ -0,0 +1,34 @@
+use diesel::{PgConnection, QueryResult, RunQueryDsl};
+
+/// the generation whose dataset is stored in the public schema, which is where the dataset was stored
+/// before datasets were versioned.
+pub const PUBLIC_GENERATION: i64 = 0;
+
+/// returns the name of the schema that stores the dataset of the given generation.
+pub fn dataset_schema_name(generation: i64) -> String {
+    if generation == PUBLIC_GENERATION {
+        "public".to_string()
+    } else {
+        format!("dataset_{}", generation)
+    }
+}
+
+/// makes the unqualified table names used by the connection refer to the tables of the given generation.
+/// tables that don't belong to the dataset, like the dataset version, are still found in the public schema.
+///
+/// if `transaction_local` is set, the connection only uses the generation until the end of the current
+/// transaction.
+pub fn use_dataset_generation(
+    db_connection: &PgConnection,
+    generation: i64,
+    transaction_local: bool,
+) -> QueryResult<()> {
+    let scope = if transaction_local { "LOCAL" } else { "SESSION" };
+    diesel::sql_query(format!(
+        "SET {} search_path TO {}, public",
+        scope,
+        dataset_schema_name(generation)
+    ))
+    .execute(db_connection)?;
+    Ok(())
+}
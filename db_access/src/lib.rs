@@ -1,9 +1,14 @@
+// diesel 1.x derives expand to impl blocks nested inside constants, which newer
+// compilers flag as non-local definitions.
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate diesel;
 
 pub mod models;
 pub mod schema;
 pub mod error_types_generator;
+pub mod dataset_generations;
 
 use diesel::{Connection, PgConnection};
 
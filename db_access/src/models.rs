@@ -1,3 +1,5 @@
+use std::time::SystemTime;
+
 use bigdecimal::BigDecimal;
 
 use crate::schema::*;
@@ -16,17 +18,31 @@ pub struct Laptop {
     pub price: f32,
     pub cpu: String,
     pub gpu: String,
+    pub brand: Option<String>,
+    pub model: Option<String>,
+    /// the currency in which the store listed the laptop
+    pub currency: String,
+    /// the price of the laptop in the currency in which the store listed it, while the price
+    /// is always in the base currency
+    pub listed_price: f32,
+    /// the store that listed the laptop, which is the prefix of the laptops file that it was loaded from
+    pub store: String,
 }
 
 
 #[derive(Debug, Insertable)]
 #[table_name="laptop"]
-pub struct NewLaptop<'a, 'b, 'c, 'd> {
+pub struct NewLaptop<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h> {
     pub name: &'a str,
     pub url: &'b str,
     pub price: f32,
     pub cpu: &'c str,
     pub gpu: &'d str,
+    pub brand: Option<&'e str>,
+    pub model: Option<&'f str>,
+    pub currency: &'g str,
+    pub listed_price: f32,
+    pub store: &'h str,
 }
 
 #[derive(Debug, Queryable, Identifiable)]
@@ -121,6 +137,7 @@ pub struct LaptopScoreInCategory{
     pub score: f32,
     pub laptop_id: i32,
     pub category_id: i32,
+    pub confidence: f32,
 }
 
 #[derive(Debug, Insertable)]
@@ -129,6 +146,7 @@ pub struct NewLaptopScoreInCategory {
     pub score: f32,
     pub laptop_id: i32,
     pub category_id: i32,
+    pub confidence: f32,
 }
 
 #[derive(Debug, Queryable, Identifiable)]
@@ -137,14 +155,85 @@ pub struct PriceLimits {
     pub id: i32,
     pub max_price: f32,
     pub min_price: f32,
+    /// the base currency, in which all prices are stored
+    pub currency: String,
 }
 
 #[derive(Debug, Insertable)]
 #[table_name = "price_limits"]
-pub struct NewPriceLimits {
+pub struct NewPriceLimits<'a> {
     pub id: i32,
     pub max_price: f32,
     pub min_price: f32,
+    pub currency: &'a str,
+}
+
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "exchange_rate"]
+#[primary_key(currency)]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// the value of a single unit of the currency in the base currency
+    pub rate: f32,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "exchange_rate"]
+pub struct NewExchangeRate<'a> {
+    pub currency: &'a str,
+    pub rate: f32,
+}
+
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "dataset_version"]
+pub struct DatasetVersion {
+    pub id: i32,
+    /// incremented whenever the data that the selector uses changes
+    pub generation: i64,
+    /// the id of the dataset generation that the selector uses
+    pub active_generation: i64,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "dataset_version"]
+pub struct NewDatasetVersion {
+    pub id: i32,
+    pub generation: i64,
+}
+
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "dataset_generation"]
+pub struct DatasetGeneration {
+    pub id: i64,
+    /// the data processor command that created the generation
+    pub command: String,
+    pub created_at: SystemTime,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "dataset_generation"]
+pub struct NewDatasetGeneration<'a> {
+    pub command: &'a str,
+}
+
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "selection_feedback"]
+pub struct SelectionFeedback {
+    pub id: i32,
+    pub laptop_name: String,
+    /// one of `clicked`, `liked` or `dismissed`
+    pub action: String,
+    /// the json parameters of the selection request that returned the laptop
+    pub selection_parameters: String,
+    pub created_at: SystemTime,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "selection_feedback"]
+pub struct NewSelectionFeedback<'a> {
+    pub laptop_name: &'a str,
+    pub action: &'a str,
+    pub selection_parameters: &'a str,
 }
 
 #[derive(Debug, Identifiable, Queryable, Associations)]
@@ -171,6 +260,7 @@ pub struct LaptopSpecs{
     pub laptop_id: i32,
     pub ram_gigabytes: i32,
     pub weight_grams: f32,
+    pub storage_gigabytes: Option<i32>,
 }
 
 #[derive(Debug, Insertable)]
@@ -179,4 +269,5 @@ pub struct NewLaptopSpecs{
     pub laptop_id: i32,
     pub ram_gigabytes: i32,
     pub weight_grams: f32,
+    pub storage_gigabytes: Option<i32>,
 }
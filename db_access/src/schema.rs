@@ -23,6 +23,29 @@ table! {
     }
 }
 
+table! {
+    dataset_generation (id) {
+        id -> Int8,
+        command -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    dataset_version (id) {
+        id -> Int4,
+        generation -> Int8,
+        active_generation -> Int8,
+    }
+}
+
+table! {
+    exchange_rate (currency) {
+        currency -> Text,
+        rate -> Float4,
+    }
+}
+
 table! {
     global_benchmark (id) {
         id -> Int4,
@@ -41,6 +64,11 @@ table! {
         price -> Float4,
         cpu -> Text,
         gpu -> Text,
+        brand -> Nullable<Text>,
+        model -> Nullable<Text>,
+        currency -> Text,
+        listed_price -> Float4,
+        store -> Text,
     }
 }
 
@@ -58,6 +86,7 @@ table! {
         score -> Float4,
         laptop_id -> Int4,
         category_id -> Int4,
+        confidence -> Float4,
     }
 }
 
@@ -67,6 +96,7 @@ table! {
         laptop_id -> Int4,
         ram_gigabytes -> Int4,
         weight_grams -> Float4,
+        storage_gigabytes -> Nullable<Int4>,
     }
 }
 
@@ -75,9 +105,21 @@ table! {
         id -> Int4,
         max_price -> Float4,
         min_price -> Float4,
+        currency -> Text,
+    }
+}
+
+table! {
+    selection_feedback (id) {
+        id -> Int4,
+        laptop_name -> Text,
+        action -> Text,
+        selection_parameters -> Text,
+        created_at -> Timestamp,
     }
 }
 
+joinable!(dataset_version -> dataset_generation (active_generation));
 joinable!(benchmark -> global_benchmark (global_benchmark_id));
 joinable!(benchmark -> laptop (laptop_id));
 joinable!(benchmark_score_in_category -> category (category_id));
@@ -91,10 +133,14 @@ allow_tables_to_appear_in_same_query!(
     benchmark,
     benchmark_score_in_category,
     category,
+    dataset_generation,
+    dataset_version,
+    exchange_rate,
     global_benchmark,
     laptop,
     laptop_image,
     laptop_score_in_category,
     laptop_specs,
     price_limits,
+    selection_feedback,
 );
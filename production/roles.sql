@@ -24,9 +24,18 @@ GRANT favory_default TO favory_readonly;
 GRANT favory_default TO favory_readwrite;
 
 -- readonly permissions
-GRANT SELECT ON category, laptop, laptop_image, laptop_score_in_category, price_limits TO favory_readonly;
+-- the upgrade recommendations read the benchmarks, to score a cpu and a gpu that aren't in the catalog. the
+-- data processor grants the same access to the tables of each generation's schema.
+GRANT SELECT ON benchmark, benchmark_score_in_category, category, dataset_version, exchange_rate, global_benchmark,
+    laptop, laptop_image, laptop_score_in_category, laptop_specs, price_limits TO favory_readonly;
+-- the selector records the feedback of its users
+GRANT INSERT ON selection_feedback TO favory_readonly;
+GRANT USAGE ON SEQUENCE selection_feedback_id_seq TO favory_readonly;
 
 -- readwrite permissions
+-- the data processor creates a schema for each generation of the dataset, and grants the readonly role
+-- access to it
+GRANT CREATE ON DATABASE favory TO favory_readwrite;
 GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO favory_readwrite;
 GRANT USAGE ON ALL SEQUENCES IN SCHEMA public TO favory_readwrite;
 
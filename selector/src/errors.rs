@@ -1,3 +1,4 @@
+use crate::selection::PreferenceSpec;
 use db_access::generate_error_types;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -11,6 +12,30 @@ pub enum SelectorErrorKind{
     FailedToDeserializeClientRequest,
     FailedToSerializeResponse,
     TcpStreamError,
+    UnknownStrategy(String),
+    InvalidAbSplit(String),
+    StrategyDoesNotSupportDatabaseRanking(String),
+    LaptopHasNoSpecs { laptop_id: i32 },
+    InvalidPreference(PreferenceSpec),
+    PreferencesNotSupportedByDatabaseRanking,
+    InvalidMinConfidence,
+    InvalidCategoryScore(String),
+    InvalidMaxPrice,
+    NonFiniteLaptopScore { laptop_id: i32, category_id: i32 },
+    SelectedLaptopNotFound { laptop_id: i32 },
+    GroupingNotSupportedByDatabaseRanking,
+    InvalidPriceBrackets,
+    InvalidMinImprovement,
+    UnknownLaptop(String),
+    UnknownCpu(String),
+    UnknownGpu(String),
+    CurrentLaptopHasNoScores,
+    UnknownCurrency(String),
+    UnknownCategoryId(i32),
+    InvalidExclusion(String),
+    InvalidFeedbackSelection,
+    InvalidStabilityParameters,
+    StabilityNotSupportedByDatabaseRanking,
 }
 
 generate_error_types!{selector}
\ No newline at end of file
@@ -1,8 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::{errors::*, selection::ScoresInCategoriesOfLaptop, SelectorDBConnection};
+use crate::{
+    errors::*,
+    selection::{
+        CandidateSpecs, Currency, ExclusionCandidate, LaptopStability, PreferenceAdjustment,
+        RankedLaptop, RankedVariant,
+    },
+    SelectorDBConnection,
+};
 use db_access::{models, schema};
-use diesel::{BelongingToDsl, ExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -13,45 +20,149 @@ pub struct CategoryNamesAndPriceLimitsData {
     pub max_price: f32,
     #[serde(rename = "minPrice")]
     pub min_price: f32,
+    /// the base currency, which is the currency of the price limits
+    pub currency: String,
 }
 
-/// information about a laptop's score in a category
+/// information about a laptop's score in a category, along with the laptop's price
 #[derive(Debug, Queryable)]
 pub struct LaptopScoreInCategoryInfo {
     pub score: f32,
     pub laptop_id: i32,
     pub category_id: i32,
+    /// the share of the score that is backed by measured benchmarks rather than average scores
+    pub confidence: f32,
+    pub price: f32,
+}
+
+/// the brand and model of a laptop
+#[derive(Debug, Queryable)]
+pub struct LaptopBrandAndModel {
+    pub laptop_id: i32,
+    pub brand: Option<String>,
+    pub model: Option<String>,
 }
 
 /// information about a selected laptop
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SelectedLaptop {
+    /// the id of the laptop, which can be used for excluding it from future selections
+    pub id: i32,
     pub name: String,
     pub url: String,
     pub price: f32,
+    /// the currency of the laptop's price and of the prices of its variants
+    pub currency: String,
+    /// the price in the currency in which the store listed the laptop
+    pub listed_price: f32,
+    pub listed_currency: String,
+    /// the store that listed the laptop at its price
+    pub store: String,
     pub cpu: String,
     pub gpu: String,
     pub image_urls: Vec<String>,
     pub ram_gigabytes: i32,
     pub weight_grams: f32,
-    pub scores_in_categories: HashMap<String, f32>,
+    pub storage_gigabytes: Option<i32>,
+    /// the scores in categories, ordered by category name so that identical requests are
+    /// always serialized identically
+    pub scores_in_categories: BTreeMap<String, f32>,
     pub score: f32,
+    /// the share of the laptop's scores, weighted by the user's category scores, that is backed
+    /// by measured benchmarks rather than average scores
+    pub confidence: f32,
+    pub confidence_in_categories: BTreeMap<String, f32>,
+    /// the bonuses and penalties that were applied to the score due to the user's soft preferences
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub preference_adjustments: Vec<PreferenceAdjustment>,
+    pub brand: Option<String>,
+    pub model: Option<String>,
+    /// when the laptops are grouped by model family, the other variants of this laptop's family,
+    /// from best to worst
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<LaptopVariant>,
+    /// how stable the laptop's position is when the category scores are perturbed, only present if
+    /// a stability analysis was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stability: Option<LaptopStability>,
+}
+
+/// another variant of a selected laptop's model family, along with the differences between
+/// its specs and the specs of the selected laptop
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaptopVariant {
+    pub name: String,
+    pub url: String,
+    pub price: f32,
+    pub score: f32,
+    /// the price of this variant minus the price of the selected laptop
+    pub price_difference: f32,
+    pub ram_gigabytes_difference: i32,
+    pub weight_grams_difference: f32,
+    /// the difference in storage, if the storage of both laptops is known
+    pub storage_gigabytes_difference: Option<i32>,
+    /// the cpu of this variant, if it is different from the selected laptop's cpu
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<String>,
+    /// the gpu of this variant, if it is different from the selected laptop's gpu
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu: Option<String>,
+}
+impl LaptopVariant {
+    /// describes a variant of a selected laptop relative to the selected laptop, with prices in the
+    /// given currency
+    fn new(
+        variant: models::Laptop,
+        variant_specs: &models::LaptopSpecs,
+        score: f32,
+        selected_laptop: &models::Laptop,
+        selected_laptop_specs: &models::LaptopSpecs,
+        currency: &Currency,
+    ) -> Self {
+        Self {
+            price_difference: currency.price_from_base(variant.price - selected_laptop.price),
+            ram_gigabytes_difference: variant_specs.ram_gigabytes
+                - selected_laptop_specs.ram_gigabytes,
+            weight_grams_difference: variant_specs.weight_grams
+                - selected_laptop_specs.weight_grams,
+            storage_gigabytes_difference: variant_specs
+                .storage_gigabytes
+                .zip(selected_laptop_specs.storage_gigabytes)
+                .map(|(variant_storage, selected_storage)| variant_storage - selected_storage),
+            cpu: Some(variant.cpu).filter(|cpu| *cpu != selected_laptop.cpu),
+            gpu: Some(variant.gpu).filter(|gpu| *gpu != selected_laptop.gpu),
+            name: variant.name,
+            url: variant.url,
+            price: currency.price_from_base(variant.price),
+            score,
+        }
+    }
 }
 
 pub trait FetchData {
     fn fetch_category_names_and_price_limits(&self) -> Result<CategoryNamesAndPriceLimitsData>;
+    fn fetch_price_limits(&self) -> Result<models::PriceLimits>;
+    fn fetch_exchange_rate(&self, currency_code: &str) -> Result<Option<f32>>;
+    fn fetch_dataset_version(&self) -> Result<models::DatasetVersion>;
     fn fetch_all_categories(&self) -> Result<Vec<models::Category>>;
     fn fetch_laptop_scores_in_categories(
         &self,
         max_price: f32,
     ) -> Result<Vec<LaptopScoreInCategoryInfo>>;
-    fn fetch_laptop_prices(&self) -> Result<HashMap<i32, f32>>;
-    fn fetch_selected_laptops(
+    fn fetch_laptop_scores_in_categories_of_laptops(
         &self,
         ids: &[i32],
-        id_to_scores_map: &HashMap<i32, (f32, ScoresInCategoriesOfLaptop)>,
+    ) -> Result<Vec<LaptopScoreInCategoryInfo>>;
+    fn fetch_candidate_specs(&self, max_price: f32) -> Result<Vec<CandidateSpecs>>;
+    fn fetch_laptop_brands_and_models(&self, max_price: f32) -> Result<Vec<LaptopBrandAndModel>>;
+    fn fetch_exclusion_candidates(&self, max_price: f32) -> Result<Vec<ExclusionCandidate>>;
+    fn fetch_selected_laptops(
+        &self,
+        ranked_laptops: Vec<RankedLaptop>,
         category_id_to_name_map: &HashMap<i32, String>,
+        currency: &Currency,
     ) -> Result<Vec<SelectedLaptop>>;
     fn fetch_category_names(&self) -> Result<HashMap<i32, String>>;
 }
@@ -65,21 +176,42 @@ impl FetchData for SelectorDBConnection {
                 .into_selector_result(SelectorErrorKind::DatabaseError)?
         };
 
-        let price_limits: models::PriceLimits = {
-            use schema::price_limits::dsl::*;
-            // the price limits table only contains a single document representing the price limits,
-            // thus we use the `first` method here.
-            price_limits
-                .first(&self.0)
-                .into_selector_result(SelectorErrorKind::DatabaseError)?
-        };
+        let price_limits = self.fetch_price_limits()?;
 
         Ok(CategoryNamesAndPriceLimitsData {
             category_names,
             max_price: price_limits.max_price,
             min_price: price_limits.min_price,
+            currency: price_limits.currency,
         })
     }
+    fn fetch_price_limits(&self) -> Result<models::PriceLimits> {
+        use schema::price_limits::dsl::*;
+
+        // the price limits table only contains a single document representing the price limits,
+        // thus we use the `first` method here.
+        price_limits
+            .first(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)
+    }
+    fn fetch_exchange_rate(&self, currency_code: &str) -> Result<Option<f32>> {
+        use schema::exchange_rate::dsl::*;
+
+        exchange_rate
+            .find(currency_code)
+            .select(rate)
+            .first(&self.0)
+            .optional()
+            .into_selector_result(SelectorErrorKind::DatabaseError)
+    }
+    fn fetch_dataset_version(&self) -> Result<models::DatasetVersion> {
+        use schema::dataset_version::dsl::*;
+
+        // the dataset version table only contains a single document too
+        dataset_version
+            .first(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)
+    }
     fn fetch_all_categories(&self) -> Result<Vec<models::Category>> {
         use schema::category::dsl::*;
 
@@ -102,70 +234,195 @@ impl FetchData for SelectorDBConnection {
                 laptop_score_in_category::score,
                 laptop_score_in_category::laptop_id,
                 laptop_score_in_category::category_id,
+                laptop_score_in_category::confidence,
+                laptop::price,
             ))
             .load(&self.0)
             .into_selector_result(SelectorErrorKind::DatabaseError)
     }
-    fn fetch_laptop_prices(&self) -> Result<HashMap<i32, f32>> {
+    fn fetch_laptop_scores_in_categories_of_laptops(
+        &self,
+        ids: &[i32],
+    ) -> Result<Vec<LaptopScoreInCategoryInfo>> {
         use schema::laptop;
+        use schema::laptop_score_in_category;
 
-        let laptop_scores: Vec<(i32, f32)> = laptop::table
-            .select((laptop::id, laptop::price))
+        laptop_score_in_category::table
+            .inner_join(laptop::table)
+            .filter(laptop::id.eq_any(ids))
+            .select((
+                laptop_score_in_category::score,
+                laptop_score_in_category::laptop_id,
+                laptop_score_in_category::category_id,
+                laptop_score_in_category::confidence,
+                laptop::price,
+            ))
             .load(&self.0)
-            .into_selector_result(SelectorErrorKind::DatabaseError)?;
+            .into_selector_result(SelectorErrorKind::DatabaseError)
+    }
+    fn fetch_candidate_specs(&self, max_price: f32) -> Result<Vec<CandidateSpecs>> {
+        use schema::laptop;
+        use schema::laptop_specs;
 
-        // convert it to a hashmap mapping each laptop's id to its price
-        Ok(laptop_scores.into_iter().collect())
+        // load the specs of all laptops where the price is lower or equal to the given max price
+        laptop_specs::table
+            .inner_join(laptop::table)
+            .filter(laptop::price.le(max_price))
+            .select((
+                laptop_specs::laptop_id,
+                laptop_specs::ram_gigabytes,
+                laptop_specs::weight_grams,
+                laptop_specs::storage_gigabytes,
+            ))
+            .load(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)
+    }
+    fn fetch_laptop_brands_and_models(&self, max_price: f32) -> Result<Vec<LaptopBrandAndModel>> {
+        use schema::laptop;
+
+        // load the brands and models of all laptops where the price is lower or equal to the given max price
+        laptop::table
+            .filter(laptop::price.le(max_price))
+            .select((laptop::id, laptop::brand, laptop::model))
+            .load(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)
+    }
+    fn fetch_exclusion_candidates(&self, max_price: f32) -> Result<Vec<ExclusionCandidate>> {
+        use schema::laptop;
+
+        // load the information of all laptops where the price is lower or equal to the given max price
+        laptop::table
+            .filter(laptop::price.le(max_price))
+            .select((laptop::id, laptop::name, laptop::store, laptop::brand))
+            .load(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)
     }
     fn fetch_selected_laptops(
         &self,
-        ids: &[i32],
-        id_to_scores_map: &HashMap<i32, (f32, ScoresInCategoriesOfLaptop)>,
+        ranked_laptops: Vec<RankedLaptop>,
         category_id_to_name_map: &HashMap<i32, String>,
+        currency: &Currency,
     ) -> Result<Vec<SelectedLaptop>> {
-        if ids.is_empty() {
+        if ranked_laptops.is_empty() {
             return Ok(Vec::new());
         }
 
         use schema::laptop;
         use schema::laptop_image;
+        use schema::laptop_specs;
 
-        let laptops: Vec<models::Laptop> = laptop::table
-            .filter(laptop::id.eq_any(ids))
+        let ids: Vec<i32> = ranked_laptops
+            .iter()
+            .map(|ranked_laptop| ranked_laptop.laptop_id)
+            .collect();
+
+        // the laptops and the specs of the selected laptops and of their variants are loaded
+        // together, so that the amount of queries doesn't depend on the amount of laptops
+        let ids_including_variants: Vec<i32> = ranked_laptops
+            .iter()
+            .flat_map(|ranked_laptop| &ranked_laptop.variants)
+            .map(|variant| variant.laptop_id)
+            .chain(ids.iter().copied())
+            .collect();
+        let mut laptops_by_id: HashMap<i32, models::Laptop> = laptop::table
+            .filter(laptop::id.eq_any(&ids_including_variants))
+            .load::<models::Laptop>(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)?
+            .into_iter()
+            .map(|laptop| (laptop.id, laptop))
+            .collect();
+        let specs_by_id: HashMap<i32, models::LaptopSpecs> = laptop_specs::table
+            .filter(laptop_specs::laptop_id.eq_any(&ids_including_variants))
+            .load::<models::LaptopSpecs>(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)?
+            .into_iter()
+            .map(|laptop_specs| (laptop_specs.laptop_id, laptop_specs))
+            .collect();
+
+        // only the selected laptops show their images. the images are ordered by their ids, so
+        // that each laptop's images keep the order in which they were inserted.
+        let laptop_ids_and_image_urls: Vec<(i32, String)> = laptop_image::table
+            .filter(laptop_image::laptop_id.eq_any(&ids))
+            .order(laptop_image::id)
+            .select((laptop_image::laptop_id, laptop_image::image_url))
             .load(&self.0)
             .into_selector_result(SelectorErrorKind::DatabaseError)?;
-        let mut selected_laptops = Vec::new();
-        for laptop in laptops {
-            // load the image urls
-            let image_urls: Vec<String> = models::LaptopImage::belonging_to(&laptop)
-                .select(laptop_image::image_url)
-                .load(&self.0)
-                .into_selector_result(SelectorErrorKind::DatabaseError)?;
+        let mut image_urls_by_id: HashMap<i32, Vec<String>> = HashMap::new();
+        for (laptop_id, image_url) in laptop_ids_and_image_urls {
+            image_urls_by_id.entry(laptop_id).or_default().push(image_url);
+        }
 
-            // load the specs
-            let laptop_specs: models::LaptopSpecs = models::LaptopSpecs::belonging_to(&laptop)
-                .first(&self.0)
-                .into_selector_result(SelectorErrorKind::DatabaseError)?;
+        // takes the loaded laptop with the given id, along with its specs
+        let mut take_laptop_and_specs = |laptop_id: i32| -> Result<(models::Laptop, &models::LaptopSpecs)> {
+            let laptop = laptops_by_id.remove(&laptop_id).ok_or_else(|| {
+                SelectorErrorKind::SelectedLaptopNotFound { laptop_id }.into_empty_selector_error()
+            })?;
+            let laptop_specs = specs_by_id.get(&laptop_id).ok_or_else(|| {
+                SelectorErrorKind::LaptopHasNoSpecs { laptop_id }.into_empty_selector_error()
+            })?;
+            Ok((laptop, laptop_specs))
+        };
+        let category_name = |category_id: i32| -> Result<String> {
+            category_id_to_name_map
+                .get(&category_id)
+                .cloned()
+                .ok_or_else(|| {
+                    SelectorErrorKind::UnknownCategoryId(category_id).into_empty_selector_error()
+                })
+        };
 
-            // find the score and socres in categories
-            let (score, scores_in_categories) = &id_to_scores_map[&laptop.id];
+        // the laptops are returned from the database in no particular order, so iterate over
+        // the ranked laptops to keep the selected laptops in the order of the ranking
+        let mut selected_laptops = Vec::with_capacity(ranked_laptops.len());
+        for ranked_laptop in ranked_laptops {
+            let (laptop, laptop_specs) = take_laptop_and_specs(ranked_laptop.laptop_id)?;
+
+            let mut variants = Vec::with_capacity(ranked_laptop.variants.len());
+            for RankedVariant { laptop_id, score } in ranked_laptop.variants {
+                let (variant, variant_specs) = take_laptop_and_specs(laptop_id)?;
+                variants.push(LaptopVariant::new(
+                    variant,
+                    variant_specs,
+                    score,
+                    &laptop,
+                    laptop_specs,
+                    currency,
+                ));
+            }
 
             selected_laptops.push(SelectedLaptop {
+                image_urls: image_urls_by_id.remove(&laptop.id).unwrap_or_default(),
+                id: laptop.id,
                 name: laptop.name,
                 url: laptop.url,
                 cpu: laptop.cpu,
                 gpu: laptop.gpu,
-                price: laptop.price,
-                score: *score,
-                scores_in_categories: scores_in_categories
-                    .clone()
+                price: currency.price_from_base(laptop.price),
+                currency: currency.code.clone(),
+                listed_price: laptop.listed_price,
+                listed_currency: laptop.currency,
+                store: laptop.store,
+                score: ranked_laptop.score,
+                scores_in_categories: ranked_laptop
+                    .scores_in_categories
                     .inner()
                     .into_iter()
-                    .map(|(id, score)| (category_id_to_name_map[&id].clone(), score))
-                    .collect(),
-                image_urls,
+                    .map(|(id, score)| Ok((category_name(id)?, score)))
+                    .collect::<Result<_>>()?,
+                confidence: ranked_laptop.confidence,
+                confidence_in_categories: ranked_laptop
+                    .confidence_in_categories
+                    .into_iter()
+                    .map(|(id, confidence)| Ok((category_name(id)?, confidence)))
+                    .collect::<Result<_>>()?,
                 ram_gigabytes: laptop_specs.ram_gigabytes,
                 weight_grams: laptop_specs.weight_grams,
+                storage_gigabytes: laptop_specs.storage_gigabytes,
+                preference_adjustments: ranked_laptop.preference_adjustments,
+                brand: laptop.brand,
+                model: laptop.model,
+                variants,
+                stability: ranked_laptop.stability,
             });
         }
         Ok(selected_laptops)
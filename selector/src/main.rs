@@ -1,7 +1,13 @@
+// diesel 1.x derives expand to impl blocks nested inside constants, which newer
+// compilers flag as non-local definitions.
+#![allow(non_local_definitions)]
+
 use std::fs::OpenOptions;
 
 use diesel::PgConnection;
 use log::LevelFilter;
+use selection::{AbSplit, StrategyRegistry};
+use server::ResultCache;
 use simplelog::{Config, SimpleLogger, WriteLogger};
 
 #[macro_use]
@@ -11,6 +17,22 @@ mod errors;
 mod fetch_data;
 mod selection;
 mod server;
+#[cfg(test)]
+mod test_database;
+
+/// the environment variable containing the a/b split of clients between strategies,
+/// for example: `weightedSum=90,valueForMoney=10`
+const AB_SPLIT_ENV_VAR: &str = "SELECTOR_AB_SPLIT";
+
+/// the environment variable containing the max amount of responses in the result cache.
+/// setting it to 0 disables the cache.
+const CACHE_MAX_ENTRIES_ENV_VAR: &str = "SELECTOR_CACHE_MAX_ENTRIES";
+
+/// the environment variable containing the max total size in bytes of the responses in the result cache
+const CACHE_MAX_BYTES_ENV_VAR: &str = "SELECTOR_CACHE_MAX_BYTES";
+
+const DEFAULT_CACHE_MAX_ENTRIES: usize = 1024;
+const DEFAULT_CACHE_MAX_BYTES: usize = 64 * 1024 * 1024;
 
 pub struct SelectorDBConnection(PgConnection);
 
@@ -21,13 +43,31 @@ fn main() {
         SimpleLogger::init(LevelFilter::max(), Config::default()).expect("failed to initialize logger");
     } else{
         // in release mode print logs to file
-        let config_file = OpenOptions::new().write(true).create(true).open("log").expect("failed to open log file");
+        let config_file = OpenOptions::new().write(true).create(true).truncate(false).open("log").expect("failed to open log file");
         WriteLogger::init(LevelFilter::Warn, Config::default(), config_file).expect("failed to initialize logger");
     }
 
+    let mut strategies = StrategyRegistry::with_builtin_strategies();
+    if let Ok(ab_split) = std::env::var(AB_SPLIT_ENV_VAR) {
+        let ab_split = AbSplit::parse(&ab_split).expect("failed to parse the a/b split");
+        strategies
+            .set_ab_split(ab_split)
+            .expect("the a/b split contains an unknown strategy");
+    }
+
+    let cache_max_entries = match std::env::var(CACHE_MAX_ENTRIES_ENV_VAR) {
+        Ok(max_entries) => max_entries.parse().expect("failed to parse the cache max entries"),
+        Err(_) => DEFAULT_CACHE_MAX_ENTRIES,
+    };
+    let cache_max_bytes = match std::env::var(CACHE_MAX_BYTES_ENV_VAR) {
+        Ok(max_bytes) => max_bytes.parse().expect("failed to parse the cache max bytes"),
+        Err(_) => DEFAULT_CACHE_MAX_BYTES,
+    };
+    let cache = ResultCache::new(cache_max_entries, cache_max_bytes);
+
     let db_connection = SelectorDBConnection(db_access::get_db_connection());
 
-    if let Err(e) = server::start_server(&db_connection) {
+    if let Err(e) = server::start_server(&db_connection, &strategies, cache) {
         eprintln!("error: {:?}", e);
     }
 }
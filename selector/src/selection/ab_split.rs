@@ -0,0 +1,128 @@
+use crate::errors::*;
+
+/// the FNV-1a offset basis and prime for 64 bit hashes
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// an a/b split of clients between strategies. each strategy gets a weight, and each client
+/// is deterministically assigned to a bucket according to a hash of its client id, so that
+/// the same client always gets the same strategy, even across restarts of the selector.
+#[derive(Debug)]
+pub struct AbSplit {
+    /// the strategy names and their weights, in the order they were specified
+    buckets: Vec<(String, u64)>,
+    total_weight: u64,
+}
+impl AbSplit {
+    /// parses an a/b split from its textual representation, which is a comma separated list
+    /// of strategy names and weights, for example: `weightedSum=90,valueForMoney=10`
+    pub fn parse(split: &str) -> Result<Self> {
+        let invalid_split_error =
+            || SelectorErrorKind::InvalidAbSplit(split.to_string()).into_empty_selector_error();
+
+        let mut buckets = Vec::new();
+        for bucket in split.split(',') {
+            let (strategy_name, weight) = match bucket.split_once('=') {
+                Some(name_and_weight) => name_and_weight,
+                None => return Err(invalid_split_error()),
+            };
+            let weight: u64 = weight.trim().parse().map_err(|_| invalid_split_error())?;
+            buckets.push((strategy_name.trim().to_string(), weight));
+        }
+
+        let total_weight = buckets.iter().map(|(_, weight)| weight).sum();
+        if total_weight == 0 {
+            return Err(invalid_split_error());
+        }
+
+        Ok(Self {
+            buckets,
+            total_weight,
+        })
+    }
+
+    /// returns the names of all strategies that take part in the split
+    pub fn strategy_names(&self) -> impl Iterator<Item = &str> {
+        self.buckets.iter().map(|(strategy_name, _)| strategy_name.as_str())
+    }
+
+    /// returns the name of the strategy that the given client is assigned to
+    pub fn strategy_for_client(&self, client_id: &str) -> &str {
+        let mut position = fnv1a_hash(client_id.as_bytes()) % self.total_weight;
+        for (strategy_name, weight) in &self.buckets {
+            if position < *weight {
+                return strategy_name;
+            }
+            position -= weight;
+        }
+        // the position is always smaller than the total weight, so one of the buckets
+        // must have been returned
+        unreachable!()
+    }
+}
+
+/// the std hasher is not guaranteed to be stable between rust versions, so FNV-1a is used
+/// instead to keep the assignment of clients to buckets stable.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_client_always_gets_the_same_strategy() {
+        let ab_split = AbSplit::parse("weightedSum=50,valueForMoney=50").unwrap();
+        let same_ab_split = AbSplit::parse("weightedSum=50,valueForMoney=50").unwrap();
+        for client_index in 0..100 {
+            let client_id = format!("client-{}", client_index);
+            let strategy_name = ab_split.strategy_for_client(&client_id);
+            assert_eq!(ab_split.strategy_for_client(&client_id), strategy_name);
+            assert_eq!(same_ab_split.strategy_for_client(&client_id), strategy_name);
+        }
+        // the hash is stable, so the assignments never change between versions of the selector
+        assert_eq!(fnv1a_hash(b""), FNV_OFFSET_BASIS);
+        assert_eq!(fnv1a_hash(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn clients_are_split_according_to_the_weights() {
+        let ab_split = AbSplit::parse(" weightedSum = 90 , valueForMoney=10,unused=0").unwrap();
+        assert_eq!(
+            ab_split.strategy_names().collect::<Vec<_>>(),
+            ["weightedSum", "valueForMoney", "unused"]
+        );
+
+        let clients_amount = 10000;
+        let mut weighted_sum_clients = 0;
+        for client_index in 0..clients_amount {
+            match ab_split.strategy_for_client(&format!("client-{}", client_index)) {
+                "weightedSum" => weighted_sum_clients += 1,
+                "valueForMoney" => {}
+                strategy_name => panic!("a client was assigned to {}", strategy_name),
+            }
+        }
+        let weighted_sum_share = weighted_sum_clients as f64 / clients_amount as f64;
+        assert!((weighted_sum_share - 0.9).abs() < 0.02, "{}", weighted_sum_share);
+    }
+
+    #[test]
+    fn malformed_splits_are_rejected() {
+        for split in &[
+            "",
+            "weightedSum",
+            "weightedSum=",
+            "weightedSum=ninety",
+            "weightedSum=-1",
+            "weightedSum=1.5",
+            "weightedSum=90,,valueForMoney=10",
+            "weightedSum=0,valueForMoney=0",
+        ] {
+            let error = AbSplit::parse(split).unwrap_err();
+            assert_eq!(error.kind, SelectorErrorKind::InvalidAbSplit(split.to_string()));
+        }
+    }
+}
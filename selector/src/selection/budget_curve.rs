@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    currency::Currency,
+    preferences::SoftPreferences,
+    ranking_criteria::RankingCriteria,
+    score_matrix::{LaptopRow, LaptopScoreMatrix},
+    strategies::SelectionStrategy,
+    top_laptops::RankedLaptop,
+    user_category_scores::UserCategoryScoresByName,
+};
+use crate::{
+    errors::*,
+    fetch_data::{FetchData, SelectedLaptop},
+    SelectorDBConnection,
+};
+
+/// the parameters of a request for the budget sensitivity curve
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetCurveParameters {
+    pub category_scores: UserCategoryScoresByName,
+
+    /// the name of the strategy that should rank the laptops. if not provided, the
+    /// strategy is chosen by the server.
+    #[serde(default)]
+    pub strategy: Option<String>,
+
+    /// an identifier of the client, used to consistently assign it to the same
+    /// strategy when the server performs an a/b split.
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// the minimum confidence, between 0 and 1, that a laptop must have in order to be selected
+    #[serde(default)]
+    pub min_confidence: f32,
+
+    /// the code of the currency of the returned prices. if not provided, the base currency is used.
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// a point where raising the max price improves the best achievable score
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetBreakpoint {
+    /// the lowest max price at which this score can be achieved
+    pub max_price: f32,
+    /// the best achievable score with this max price
+    pub score: f32,
+    /// the laptop that achieves the score, which is the top laptop that a selection
+    /// with this max price would return
+    pub laptop: SelectedLaptop,
+}
+
+/// calculates how the best achievable score changes as the max price goes from the lowest to the
+/// highest laptop price, and returns the breakpoints where the best score improves, from the
+/// cheapest to the most expensive.
+///
+/// the laptops are visited from the cheapest to the most expensive, and each laptop is scored
+/// exactly once. a laptop is a breakpoint if its score is strictly higher than the score of every
+/// laptop that was visited before it, since for equal scores the cheaper laptop is ranked higher.
+pub fn select_budget_curve(
+    db_connection: &SelectorDBConnection,
+    strategy: &dyn SelectionStrategy,
+    parameters: &BudgetCurveParameters,
+) -> Result<Vec<BudgetBreakpoint>> {
+    if parameters.category_scores.is_empty() {
+        return Err(SelectorErrorKind::NoScoresProvided.into_empty_selector_error());
+    }
+    parameters.category_scores.validate()?;
+    if !(0.0..=1.0).contains(&parameters.min_confidence) {
+        return Err(SelectorErrorKind::InvalidMinConfidence.into_empty_selector_error());
+    }
+    let currency = Currency::resolve(db_connection, parameters.currency.as_deref())?;
+    let user_category_scores_by_id = parameters
+        .category_scores
+        .remap_key_to_category_id(db_connection)?;
+
+    let price_limits = db_connection.fetch_price_limits()?;
+    let score_matrix = LaptopScoreMatrix::load(db_connection, price_limits.max_price)?;
+    let weights = user_category_scores_by_id.to_weights(score_matrix.category_ids());
+    let no_preferences = SoftPreferences::default();
+    let criteria = RankingCriteria {
+        strategy,
+        weights: &weights,
+        preferences: &no_preferences,
+        currency: &currency,
+        min_confidence: parameters.min_confidence,
+    };
+
+    // laptops with the same price are visited from the best to the worst, so that only the laptop
+    // that a selection with that max price would return can become a breakpoint. just like in the
+    // ranking, the laptop with the lower id wins a tie in both price and score.
+    let mut scored_rows: Vec<(LaptopRow, f32)> = score_matrix
+        .rows()
+        .filter(|row| criteria.accepts(row))
+        .map(|row| {
+            let score = criteria.score(&row);
+            (row, score)
+        })
+        .collect();
+    scored_rows.sort_unstable_by(|(row1, score1), (row2, score2)| {
+        row1.price
+            .total_cmp(&row2.price)
+            .then_with(|| score2.total_cmp(score1))
+            .then_with(|| row1.laptop_id.cmp(&row2.laptop_id))
+    });
+
+    let mut breakpoints = Vec::new();
+    let mut best_score = f32::NEG_INFINITY;
+    for (row, score) in scored_rows {
+        if score > best_score {
+            best_score = score;
+            breakpoints.push(RankedLaptop::from_row(&row, score, &criteria, &score_matrix));
+        }
+    }
+
+    // load the information about the laptops of all breakpoints together
+    let category_id_to_name_map = db_connection.fetch_category_names()?;
+    let laptops = db_connection.fetch_selected_laptops(
+        breakpoints,
+        &category_id_to_name_map,
+        &currency,
+    )?;
+    Ok(laptops
+        .into_iter()
+        .map(|laptop| BudgetBreakpoint {
+            max_price: laptop.price,
+            score: laptop.score,
+            laptop,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::StrategyRegistry;
+    use crate::test_database::{connect_to_test_dataset, insert_laptop, FixtureLaptop};
+
+    fn budget_curve(db_connection: &SelectorDBConnection, parameters: &str) -> Vec<(f32, i32)> {
+        let parameters: BudgetCurveParameters = serde_json::from_str(parameters).unwrap();
+        let strategies = StrategyRegistry::with_builtin_strategies();
+        let strategy = strategies.resolve(Some("weightedSum"), None).unwrap();
+        select_budget_curve(db_connection, strategy, &parameters)
+            .unwrap()
+            .into_iter()
+            .map(|breakpoint| (breakpoint.max_price, breakpoint.laptop.id))
+            .collect()
+    }
+
+    #[test]
+    fn only_the_best_laptop_of_a_price_is_a_breakpoint() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 1000.0, &[(2.0, 1.0)]));
+        // the same price as the next laptop, which is better despite its higher id
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 2000.0, &[(3.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(3, 2000.0, &[(4.0, 1.0)]));
+        // ties with the previous laptop in both price and score, but has a higher id
+        insert_laptop(&db_connection, &FixtureLaptop::new(4, 2000.0, &[(4.0, 1.0)]));
+        // not better than a cheaper laptop
+        insert_laptop(&db_connection, &FixtureLaptop::new(5, 3000.0, &[(4.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(6, 4000.0, &[(5.0, 1.0)]));
+
+        let parameters = r#"{"categoryScores": {"gaming": 1}}"#;
+        assert_eq!(
+            budget_curve(&db_connection, parameters),
+            [(1000.0, 1), (2000.0, 3), (4000.0, 6)]
+        );
+    }
+
+    #[test]
+    fn prices_are_in_the_requested_currency() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 1000.0, &[(2.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 2000.0, &[(3.0, 1.0)]));
+
+        let parameters = r#"{"categoryScores": {"gaming": 1}, "currency": "usd"}"#;
+        assert_eq!(budget_curve(&db_connection, parameters), [(250.0, 1), (500.0, 2)]);
+    }
+}
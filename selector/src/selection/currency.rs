@@ -0,0 +1,42 @@
+use crate::{errors::*, fetch_data::FetchData, SelectorDBConnection};
+
+/// the currency in which the prices of a request are given and returned. all prices in the database
+/// are in the base currency, which is the currency of the price limits.
+#[derive(Debug)]
+pub struct Currency {
+    pub code: String,
+    /// the value of a single unit of this currency in the base currency
+    rate: f32,
+}
+impl Currency {
+    /// returns the base currency
+    pub fn base(db_connection: &SelectorDBConnection) -> Result<Self> {
+        Ok(Self {
+            code: db_connection.fetch_price_limits()?.currency,
+            rate: 1.0,
+        })
+    }
+
+    /// finds the currency with the given code, or the base currency if no code was given.
+    /// currency codes are case insensitive.
+    pub fn resolve(db_connection: &SelectorDBConnection, code: Option<&str>) -> Result<Self> {
+        let code = match code {
+            Some(code) => code.to_uppercase(),
+            None => return Self::base(db_connection),
+        };
+        match db_connection.fetch_exchange_rate(&code)? {
+            Some(rate) => Ok(Self { code, rate }),
+            None => Err(SelectorErrorKind::UnknownCurrency(code).into_empty_selector_error()),
+        }
+    }
+
+    /// converts a price in this currency to the base currency
+    pub fn price_to_base(&self, price: f32) -> f32 {
+        (price as f64 * self.rate as f64) as f32
+    }
+
+    /// converts a price in the base currency to this currency
+    pub fn price_from_base(&self, price: f32) -> f32 {
+        (price as f64 / self.rate as f64) as f32
+    }
+}
@@ -0,0 +1,138 @@
+use diesel::{
+    sql_types::{Array, BigInt, Double, Float, Integer},
+    RunQueryDsl,
+};
+
+use super::{
+    score_matrix::ScoresInCategoriesOfLaptop, strategies::SelectionStrategy,
+    top_laptops::RankedLaptop, user_category_scores::UserCategoryScoresById,
+};
+use crate::{errors::*, fetch_data::FetchData, SelectorDBConnection};
+use std::collections::HashMap;
+
+/// a laptop ranked by the database
+#[derive(Debug, QueryableByName)]
+struct DatabaseRankedLaptop {
+    #[sql_type = "Integer"]
+    laptop_id: i32,
+    #[sql_type = "Float"]
+    score: f32,
+    #[sql_type = "Float"]
+    confidence: f32,
+}
+
+/// ranks the laptops inside the database, such that only the top laptops are loaded into the selector.
+///
+/// the weighted sum is calculated in double precision, which is also what the in process ranking
+/// does, and only then converted to a real. the database may sum the scores in a different order, but
+/// the rounding error of a double precision sum is small enough that both backends almost always end
+/// up with the same scores. the ties are broken the same way as in
+/// the in process ranking, first by a lower price and then by a lower laptop id. the confidence of
+/// each laptop is calculated the same way too, so that the same laptops are filtered out, and the
+/// excluded laptops are skipped before the laptops are limited, just like in the in process ranking.
+pub fn rank_in_database(
+    db_connection: &SelectorDBConnection,
+    strategy: &dyn SelectionStrategy,
+    user_category_scores: &UserCategoryScoresById,
+    max_price: f32,
+    min_confidence: f32,
+    excluded_laptop_ids: &[i32],
+    amount: usize,
+) -> Result<Vec<RankedLaptop>> {
+    let total_score_sql = match strategy.total_score_sql() {
+        Some(total_score_sql) => total_score_sql,
+        None => {
+            return Err(SelectorErrorKind::StrategyDoesNotSupportDatabaseRanking(
+                strategy.name().to_string(),
+            )
+            .into_empty_selector_error())
+        }
+    };
+
+    let (category_ids, weights): (Vec<i32>, Vec<f64>) = user_category_scores
+        .iter()
+        .map(|(&category_id, &weight)| (category_id, weight as f64))
+        .unzip();
+
+    // the laptops are first filtered by price using the index on the laptop's price, then
+    // joined with their scores in the selected categories using the index on the category id,
+    // and only then summed up, ordered and limited.
+    let query = format!(
+        "SELECT id AS laptop_id, CAST({} AS REAL) AS score, confidence
+        FROM (
+            SELECT laptop.id, laptop.price,
+                SUM(CAST(laptop_score_in_category.score AS DOUBLE PRECISION) * weights.weight) AS weighted_sum,
+                CAST(COALESCE(
+                    SUM(CAST(laptop_score_in_category.confidence AS DOUBLE PRECISION) * weights.weight)
+                        / NULLIF(GREATEST(SUM(weights.weight), 0), 0),
+                    1
+                ) AS REAL) AS confidence
+            FROM laptop
+            INNER JOIN laptop_score_in_category ON laptop_score_in_category.laptop_id = laptop.id
+            INNER JOIN UNNEST($1, $2) AS weights (category_id, weight)
+                ON weights.category_id = laptop_score_in_category.category_id
+            WHERE laptop.price <= $3 AND laptop.id <> ALL($6)
+            GROUP BY laptop.id
+        ) AS weighted_laptops
+        WHERE $5 <= 0 OR confidence >= $5
+        ORDER BY score DESC, price ASC, id ASC
+        LIMIT $4",
+        total_score_sql
+    );
+    let database_ranked_laptops: Vec<DatabaseRankedLaptop> = diesel::sql_query(query)
+        .bind::<Array<Integer>, _>(category_ids)
+        .bind::<Array<Double>, _>(weights)
+        .bind::<Float, _>(max_price)
+        .bind::<BigInt, _>(amount as i64)
+        .bind::<Float, _>(min_confidence)
+        .bind::<Array<Integer>, _>(excluded_laptop_ids)
+        .load(&db_connection.0)
+        .into_selector_result(SelectorErrorKind::DatabaseError)?;
+
+    // load the scores in all categories, and their confidences, of only the top laptops
+    let laptop_ids: Vec<i32> = database_ranked_laptops
+        .iter()
+        .map(|ranked_laptop| ranked_laptop.laptop_id)
+        .collect();
+    let mut scores_in_categories_by_laptop_id: HashMap<i32, HashMap<i32, f32>> = HashMap::new();
+    let mut confidences_in_categories_by_laptop_id: HashMap<i32, HashMap<i32, f32>> =
+        HashMap::new();
+    for laptop_score_in_category in
+        db_connection.fetch_laptop_scores_in_categories_of_laptops(&laptop_ids)?
+    {
+        confidences_in_categories_by_laptop_id
+            .entry(laptop_score_in_category.laptop_id)
+            .or_default()
+            .insert(
+                laptop_score_in_category.category_id,
+                laptop_score_in_category.confidence,
+            );
+        scores_in_categories_by_laptop_id
+            .entry(laptop_score_in_category.laptop_id)
+            .or_default()
+            .insert(
+                laptop_score_in_category.category_id,
+                laptop_score_in_category.score,
+            );
+    }
+
+    Ok(database_ranked_laptops
+        .into_iter()
+        .map(|ranked_laptop| RankedLaptop {
+            laptop_id: ranked_laptop.laptop_id,
+            score: ranked_laptop.score,
+            scores_in_categories: ScoresInCategoriesOfLaptop::from(
+                scores_in_categories_by_laptop_id
+                    .remove(&ranked_laptop.laptop_id)
+                    .unwrap_or_default(),
+            ),
+            confidence: ranked_laptop.confidence,
+            confidence_in_categories: confidences_in_categories_by_laptop_id
+                .remove(&ranked_laptop.laptop_id)
+                .unwrap_or_default(),
+            preference_adjustments: Vec::new(),
+            variants: Vec::new(),
+            stability: None,
+        })
+        .collect())
+}
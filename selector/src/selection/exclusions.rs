@@ -0,0 +1,147 @@
+use serde::Deserialize;
+
+use crate::{errors::*, fetch_data::FetchData, SelectorDBConnection};
+
+/// the laptops that a selection should never return. the excluded laptops are skipped while
+/// ranking, so they are replaced by the next best laptops.
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Exclusions {
+    #[serde(default)]
+    pub laptop_ids: Vec<i32>,
+
+    /// patterns of the names of the excluded laptops. just like the patterns in the categories
+    /// file, a pattern may require several sequences, separated by `&&`, that must all appear in
+    /// the name. unlike them, the patterns are matched case insensitively.
+    #[serde(default)]
+    pub name_patterns: Vec<String>,
+
+    /// the names of the excluded stores, for example `ivory`, matched case insensitively
+    #[serde(default)]
+    pub stores: Vec<String>,
+
+    /// the excluded brands, matched case insensitively
+    #[serde(default)]
+    pub brands: Vec<String>,
+}
+
+/// the information about a candidate laptop that is required for checking whether it is excluded
+#[derive(Debug, Queryable)]
+pub struct ExclusionCandidate {
+    pub laptop_id: i32,
+    pub name: String,
+    pub store: String,
+    pub brand: Option<String>,
+}
+
+impl Exclusions {
+    /// an empty pattern, store or brand would either match every laptop or no laptop at all,
+    /// which is never what the user meant.
+    pub fn validate(&self) -> Result<()> {
+        let all_names = self
+            .name_patterns
+            .iter()
+            .chain(&self.stores)
+            .chain(&self.brands);
+        for name in all_names {
+            let has_empty_part = name.split("&&").any(|part| part.trim().is_empty());
+            if has_empty_part {
+                return Err(SelectorErrorKind::InvalidExclusion(name.clone()).into_empty_selector_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// finds the ids of the excluded laptops, out of all laptops where the price is lower or equal
+    /// to the given max price.
+    pub fn find_excluded_laptop_ids(
+        &self,
+        db_connection: &SelectorDBConnection,
+        max_price: f32,
+    ) -> Result<Vec<i32>> {
+        let mut excluded_laptop_ids = self.laptop_ids.clone();
+
+        // the excluded laptop ids don't require any information about the candidates
+        if self.name_patterns.is_empty() && self.stores.is_empty() && self.brands.is_empty() {
+            return Ok(excluded_laptop_ids);
+        }
+
+        let name_patterns: Vec<Vec<String>> = self
+            .name_patterns
+            .iter()
+            .map(|pattern| pattern.split("&&").map(|part| part.trim().to_lowercase()).collect())
+            .collect();
+        let stores: Vec<String> = self.stores.iter().map(|store| store.trim().to_lowercase()).collect();
+        let brands: Vec<String> = self.brands.iter().map(|brand| brand.trim().to_lowercase()).collect();
+
+        for candidate in db_connection.fetch_exclusion_candidates(max_price)? {
+            let name = candidate.name.to_lowercase();
+            let name_is_excluded = name_patterns.iter().any(|required_sequences| {
+                required_sequences
+                    .iter()
+                    .all(|required_sequence| name.contains(required_sequence.as_str()))
+            });
+            let store_is_excluded = stores.contains(&candidate.store.to_lowercase());
+            let brand_is_excluded = candidate
+                .brand
+                .is_some_and(|brand| brands.contains(&brand.trim().to_lowercase()));
+            if name_is_excluded || store_is_excluded || brand_is_excluded {
+                excluded_laptop_ids.push(candidate.laptop_id);
+            }
+        }
+        Ok(excluded_laptop_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_database::{connect_to_test_dataset, insert_laptop, FixtureLaptop};
+
+    #[test]
+    fn name_patterns_ignore_whitespace_around_their_parts() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        let laptops = [
+            FixtureLaptop {
+                name: "Lenovo IdeaPad 5",
+                ..FixtureLaptop::new(1, 1000.0, &[(1.0, 1.0)])
+            },
+            FixtureLaptop {
+                name: "Lenovo ThinkPad X1",
+                ..FixtureLaptop::new(2, 1000.0, &[(1.0, 1.0)])
+            },
+            FixtureLaptop {
+                name: "Dell XPS 13",
+                brand: Some("Dell"),
+                ..FixtureLaptop::new(3, 1000.0, &[(1.0, 1.0)])
+            },
+            FixtureLaptop {
+                name: "Asus ZenBook",
+                store: "ksp",
+                ..FixtureLaptop::new(4, 1000.0, &[(1.0, 1.0)])
+            },
+        ];
+        for laptop in &laptops {
+            insert_laptop(&db_connection, laptop);
+        }
+
+        let exclusions: Exclusions = serde_json::from_str(
+            r#"{"namePatterns": ["lenovo && ideapad"], "brands": [" dell"], "stores": ["KSP "]}"#,
+        )
+        .unwrap();
+        exclusions.validate().unwrap();
+        let mut excluded_laptop_ids = exclusions
+            .find_excluded_laptop_ids(&db_connection, 1000.0)
+            .unwrap();
+        excluded_laptop_ids.sort_unstable();
+        assert_eq!(excluded_laptop_ids, [1, 3, 4]);
+    }
+
+    #[test]
+    fn empty_parts_are_rejected() {
+        for json in [r#"{"namePatterns": ["lenovo && "]}"#, r#"{"brands": [" "]}"#] {
+            let exclusions: Exclusions = serde_json::from_str(json).unwrap();
+            assert!(exclusions.validate().is_err(), "{} should be invalid", json);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use db_access::{models, schema};
+use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::select::SelectionParameters;
+use crate::{errors::*, SelectorDBConnection};
+
+/// the parameters of a request that records what a user did with one of the selected laptops
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackParameters {
+    /// the id of the laptop, as returned by the selection
+    pub laptop_id: i32,
+    pub action: FeedbackAction,
+
+    /// the parameters of the selection request that returned the laptop, exactly as they were sent
+    pub selection: Value,
+}
+
+/// what the user did with a selected laptop
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FeedbackAction {
+    Clicked,
+    Liked,
+    Dismissed,
+}
+
+impl FeedbackAction {
+    /// the name of the action, as it is stored in the database
+    fn as_str(self) -> &'static str {
+        match self {
+            FeedbackAction::Clicked => "clicked",
+            FeedbackAction::Liked => "liked",
+            FeedbackAction::Dismissed => "dismissed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedFeedback {
+    pub feedback_id: i32,
+}
+
+pub trait RecordFeedback {
+    /// stores the feedback so that the data processor can later use it to tune the categories.
+    fn record_feedback(&self, parameters: &FeedbackParameters) -> Result<RecordedFeedback>;
+}
+impl RecordFeedback for SelectorDBConnection {
+    fn record_feedback(&self, parameters: &FeedbackParameters) -> Result<RecordedFeedback> {
+        // the data processor reads the category scores out of the selection parameters, so make sure
+        // that they really are the parameters of a valid selection
+        let selection = SelectionParameters::deserialize(&parameters.selection).into_selector_result(
+            SelectorErrorKind::InvalidFeedbackSelection,
+        )?;
+        if selection.category_scores.is_empty() {
+            return Err(SelectorErrorKind::InvalidFeedbackSelection.into_empty_selector_error());
+        }
+        selection.category_scores.validate()?;
+
+        // the laptop ids change whenever the laptops are reloaded, so the feedback refers to the
+        // laptop by its name
+        let laptop_name: String = {
+            use schema::laptop::dsl::*;
+            laptop
+                .find(parameters.laptop_id)
+                .select(name)
+                .first(&self.0)
+                .optional()
+                .into_selector_result(SelectorErrorKind::DatabaseError)?
+                .ok_or_else(|| {
+                    SelectorErrorKind::SelectedLaptopNotFound {
+                        laptop_id: parameters.laptop_id,
+                    }
+                    .into_empty_selector_error()
+                })?
+        };
+
+        let selection_parameters = parameters.selection.to_string();
+        let feedback_id = diesel::insert_into(schema::selection_feedback::table)
+            .values(&models::NewSelectionFeedback {
+                laptop_name: &laptop_name,
+                action: parameters.action.as_str(),
+                selection_parameters: &selection_parameters,
+            })
+            .returning(schema::selection_feedback::id)
+            .get_result(&self.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)?;
+        Ok(RecordedFeedback { feedback_id })
+    }
+}
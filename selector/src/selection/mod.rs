@@ -1,10 +1,32 @@
-mod scores_in_categories_of_laptops;
+mod ab_split;
+mod budget_curve;
+mod currency;
+mod database_ranking;
+mod exclusions;
+mod feedback;
+mod preferences;
+mod price_brackets;
+mod ranking_criteria;
+mod score_matrix;
 mod select;
+mod stability;
+mod strategies;
 mod top_laptops;
+mod upgrades;
 mod user_category_scores;
 
-pub use select::Select;
-pub use user_category_scores::UserCategoryScoresByName;
-pub use scores_in_categories_of_laptops::ScoresInCategoriesOfLaptop;
+pub use ab_split::AbSplit;
+pub use budget_curve::BudgetCurveParameters;
+pub use currency::Currency;
+pub use exclusions::ExclusionCandidate;
+pub use feedback::{FeedbackParameters, RecordFeedback};
+pub use preferences::{PreferenceAdjustment, PreferenceSpec};
+pub use price_brackets::PriceBracketsParameters;
+pub use select::{Select, SelectionParameters};
+pub use score_matrix::CandidateSpecs;
+pub use stability::LaptopStability;
+pub use top_laptops::{RankedLaptop, RankedVariant};
+pub use upgrades::UpgradeParameters;
+pub use strategies::StrategyRegistry;
 
-pub const TOP_LAPTOPS_AMOUNT: usize = 5;
\ No newline at end of file
+pub const TOP_LAPTOPS_AMOUNT: usize = 5;
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+use super::score_matrix::CandidateSpecs;
+use crate::errors::*;
+
+/// a spec of a laptop that a user can have a soft preference on
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PreferenceSpec {
+    /// the weight of the laptop, in the same units as the laptop's `weightGrams`
+    Weight,
+    /// the amount of ram in gigabytes
+    Ram,
+    Price,
+    /// the storage size in gigabytes
+    Storage,
+}
+
+/// whether the user prefers values of a spec that are lower or higher than the target
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PreferenceDirection {
+    AtMost,
+    AtLeast,
+}
+
+/// a soft preference on a spec, for example "I'd prefer under 1.5 kg". unlike a hard cutoff,
+/// laptops that don't satisfy the preference are not removed, but penalized.
+#[derive(Debug, Deserialize)]
+pub struct SoftPreference {
+    spec: PreferenceSpec,
+    target: f32,
+    direction: PreferenceDirection,
+    /// how strongly the user cares about this preference. a strength of 0.1 means that a laptop
+    /// that misses the target by 100% or more loses 10% of its score, and a laptop that beats the
+    /// target by 100% or more gains 10%.
+    strength: f32,
+}
+impl SoftPreference {
+    /// calculates the adjustment to the given base score of a laptop with the given spec value
+    fn adjustment(&self, base_score: f32, value: f32) -> f32 {
+        // the relative deviation from the target, positive when the value is on the preferred side
+        // of the target. it is clamped so that a single extreme spec can't dominate the score.
+        let deviation = match self.direction {
+            PreferenceDirection::AtMost => (self.target - value) / self.target,
+            PreferenceDirection::AtLeast => (value - self.target) / self.target,
+        };
+        base_score.abs() * self.strength * deviation.clamp(-1.0, 1.0)
+    }
+}
+
+/// a bonus or a penalty that was applied to a laptop's score due to a soft preference
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferenceAdjustment {
+    pub spec: PreferenceSpec,
+    pub value: f32,
+    pub target: f32,
+    /// the amount added to the laptop's score, negative for penalties
+    pub adjustment: f32,
+}
+
+/// all soft preferences of a selection request
+#[derive(Debug, Deserialize, Default)]
+#[serde(transparent)]
+pub struct SoftPreferences(Vec<SoftPreference>);
+impl SoftPreferences {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// makes sure that the preferences can be used for adjusting scores
+    pub fn validate(&self) -> Result<()> {
+        for preference in &self.0 {
+            let is_valid = preference.target.is_finite()
+                && preference.target > 0.0
+                && preference.strength.is_finite()
+                && preference.strength >= 0.0;
+            if !is_valid {
+                return Err(SelectorErrorKind::InvalidPreference(preference.spec)
+                    .into_empty_selector_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// calculates the total adjustment to a laptop's base score according to all preferences
+    pub fn total_adjustment(&self, base_score: f32, price: f32, specs: &CandidateSpecs) -> f32 {
+        self.0
+            .iter()
+            .filter_map(|preference| {
+                let value = spec_value(preference.spec, price, specs)?;
+                Some(preference.adjustment(base_score, value))
+            })
+            .sum()
+    }
+
+    /// lists the adjustments to a laptop's base score according to each of the preferences.
+    /// preferences on specs that are unknown for this laptop are not listed.
+    pub fn adjustments(
+        &self,
+        base_score: f32,
+        price: f32,
+        specs: &CandidateSpecs,
+    ) -> Vec<PreferenceAdjustment> {
+        self.0
+            .iter()
+            .filter_map(|preference| {
+                let value = spec_value(preference.spec, price, specs)?;
+                Some(PreferenceAdjustment {
+                    spec: preference.spec,
+                    value,
+                    target: preference.target,
+                    adjustment: preference.adjustment(base_score, value),
+                })
+            })
+            .collect()
+    }
+}
+
+/// returns the value of the given spec of a laptop, if it is known
+fn spec_value(spec: PreferenceSpec, price: f32, specs: &CandidateSpecs) -> Option<f32> {
+    match spec {
+        PreferenceSpec::Weight => Some(specs.weight_grams),
+        PreferenceSpec::Ram => Some(specs.ram_gigabytes as f32),
+        PreferenceSpec::Price => Some(price),
+        PreferenceSpec::Storage => specs.storage_gigabytes.map(|storage| storage as f32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specs(storage_gigabytes: Option<i32>) -> CandidateSpecs {
+        CandidateSpecs {
+            laptop_id: 1,
+            ram_gigabytes: 16,
+            weight_grams: 2000.0,
+            storage_gigabytes,
+        }
+    }
+
+    fn preferences(json: &str) -> SoftPreferences {
+        let preferences: SoftPreferences = serde_json::from_str(json).unwrap();
+        preferences.validate().unwrap();
+        preferences
+    }
+
+    #[test]
+    fn price_preference_rewards_cheaper_laptops() {
+        let preferences = preferences(r#"[{"spec": "price", "target": 4000, "direction": "atMost", "strength": 0.2}]"#);
+
+        // 25% under the target
+        let adjustments = preferences.adjustments(10.0, 3000.0, &specs(None));
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].spec, PreferenceSpec::Price);
+        assert_eq!(adjustments[0].value, 3000.0);
+        assert_eq!(adjustments[0].target, 4000.0);
+        assert!((adjustments[0].adjustment - 0.5).abs() < 1e-6);
+
+        // 50% over the target
+        let adjustment = preferences.total_adjustment(10.0, 6000.0, &specs(None));
+        assert!((adjustment + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn adjustments_are_clamped_and_combined() {
+        let preferences = preferences(
+            r#"[
+                {"spec": "ram", "target": 4, "direction": "atLeast", "strength": 0.1},
+                {"spec": "weight", "target": 1000, "direction": "atMost", "strength": 0.1}
+            ]"#,
+        );
+
+        // the ram beats the target by 300%, which is clamped to 100%, and the weight misses it by 100%
+        let adjustment = preferences.total_adjustment(-10.0, 1000.0, &specs(None));
+        assert!(adjustment.abs() < 1e-6);
+        let adjustments = preferences.adjustments(-10.0, 1000.0, &specs(None));
+        assert!((adjustments[0].adjustment - 1.0).abs() < 1e-6);
+        assert!((adjustments[1].adjustment + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unknown_specs_are_not_adjusted() {
+        let preferences = preferences(r#"[{"spec": "storage", "target": 256, "direction": "atLeast", "strength": 0.5}]"#);
+        assert!(preferences.adjustments(10.0, 1000.0, &specs(None)).is_empty());
+        assert!((preferences.total_adjustment(10.0, 1000.0, &specs(Some(512))) - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn invalid_preferences_are_rejected() {
+        for json in [
+            r#"[{"spec": "price", "target": 0, "direction": "atMost", "strength": 0.1}]"#,
+            r#"[{"spec": "ram", "target": 8, "direction": "atLeast", "strength": -0.1}]"#,
+        ] {
+            let preferences: SoftPreferences = serde_json::from_str(json).unwrap();
+            assert!(preferences.validate().is_err(), "{} should be invalid", json);
+        }
+    }
+}
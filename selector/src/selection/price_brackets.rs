@@ -0,0 +1,454 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    currency::Currency,
+    preferences::SoftPreferences,
+    ranking_criteria::RankingCriteria,
+    score_matrix::LaptopScoreMatrix,
+    strategies::SelectionStrategy,
+    top_laptops::{RankedLaptop, TopLaptops},
+    user_category_scores::UserCategoryScoresByName,
+};
+use crate::{
+    errors::*,
+    fetch_data::{FetchData, SelectedLaptop},
+    SelectorDBConnection,
+};
+use db_access::models;
+
+/// the parameters of a request for the best laptop in each price bracket
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceBracketsParameters {
+    pub category_scores: UserCategoryScoresByName,
+
+    /// how the prices should be split into brackets
+    pub brackets: PriceBracketsSpec,
+
+    /// the name of the strategy that should rank the laptops. if not provided, the
+    /// strategy is chosen by the server.
+    #[serde(default)]
+    pub strategy: Option<String>,
+
+    /// an identifier of the client, used to consistently assign it to the same
+    /// strategy when the server performs an a/b split.
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// the minimum confidence, between 0 and 1, that a laptop must have in order to be selected
+    #[serde(default)]
+    pub min_confidence: f32,
+
+    /// the code of the currency of the explicit brackets and of the returned prices. if not provided,
+    /// the base currency is used.
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// describes how the prices should be split into brackets
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PriceBracketsSpec {
+    /// the brackets are given explicitly
+    Explicit(Vec<PriceBracket>),
+    /// the range between the lowest and the highest laptop prices is split into the given
+    /// amount of brackets of equal width
+    EqualWidth(usize),
+    /// the laptops are split into the given amount of brackets, such that each bracket contains
+    /// roughly the same amount of laptops
+    Quantiles(usize),
+}
+impl PriceBracketsSpec {
+    /// the highest price, in the base currency, of a laptop that could belong to one of the brackets
+    fn max_price(&self, price_limits: &models::PriceLimits, currency: &Currency) -> f32 {
+        match self {
+            PriceBracketsSpec::Explicit(brackets) => brackets
+                .iter()
+                .map(|bracket| currency.price_to_base(bracket.max_price))
+                .fold(f32::NEG_INFINITY, f32::max),
+            PriceBracketsSpec::EqualWidth(_) | PriceBracketsSpec::Quantiles(_) => {
+                price_limits.max_price
+            }
+        }
+    }
+
+    /// creates the actual brackets in the base currency, from the cheapest to the most expensive, unless
+    /// they were given explicitly in the given currency, in which case they are kept in the given order.
+    fn resolve(
+        &self,
+        price_limits: &models::PriceLimits,
+        score_matrix: &LaptopScoreMatrix,
+        currency: &Currency,
+    ) -> Result<Vec<PriceBracket>> {
+        let invalid_brackets = || SelectorErrorKind::InvalidPriceBrackets.into_empty_selector_error();
+        match self {
+            PriceBracketsSpec::Explicit(brackets) => {
+                let all_brackets_valid = brackets.iter().all(|bracket| {
+                    bracket.min_price.is_finite()
+                        && bracket.max_price.is_finite()
+                        && bracket.min_price <= bracket.max_price
+                });
+                if brackets.is_empty() || !all_brackets_valid {
+                    return Err(invalid_brackets());
+                }
+                Ok(brackets.iter().map(|bracket| bracket.to_base(currency)).collect())
+            }
+            PriceBracketsSpec::EqualWidth(0) | PriceBracketsSpec::Quantiles(0) => {
+                Err(invalid_brackets())
+            }
+            PriceBracketsSpec::EqualWidth(amount) => Ok(equal_width_brackets(
+                price_limits.min_price,
+                price_limits.max_price,
+                *amount,
+            )),
+            PriceBracketsSpec::Quantiles(amount) => {
+                let mut prices: Vec<f32> = score_matrix.rows().map(|row| row.price).collect();
+
+                // without any laptops there are no quantiles, and all brackets would be empty anyway
+                if prices.is_empty() {
+                    return Ok(equal_width_brackets(
+                        price_limits.min_price,
+                        price_limits.max_price,
+                        *amount,
+                    ));
+                }
+                prices.sort_unstable_by(f32::total_cmp);
+
+                // the boundaries are the prices at the positions that split the sorted prices
+                // into equal parts
+                let boundaries: Vec<f32> = (0..=*amount)
+                    .map(|i| prices[i * (prices.len() - 1) / amount])
+                    .collect();
+                Ok(boundaries
+                    .windows(2)
+                    .map(|boundaries| PriceBracket {
+                        min_price: boundaries[0],
+                        max_price: boundaries[1],
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// splits the range between the given prices into the given amount of brackets of equal width
+fn equal_width_brackets(min_price: f32, max_price: f32, amount: usize) -> Vec<PriceBracket> {
+    let width = (max_price as f64 - min_price as f64) / amount as f64;
+    let boundary = |i: usize| {
+        // the last boundary is exactly the max price, so that rounding errors could never
+        // leave the most expensive laptop out of all brackets
+        if i == amount {
+            max_price
+        } else {
+            (min_price as f64 + width * i as f64) as f32
+        }
+    };
+    (0..amount)
+        .map(|i| PriceBracket {
+            min_price: boundary(i),
+            max_price: boundary(i + 1),
+        })
+        .collect()
+}
+
+/// a range of prices, including both the min and the max prices
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceBracket {
+    pub min_price: f32,
+    pub max_price: f32,
+}
+impl PriceBracket {
+    fn contains(&self, price: f32) -> bool {
+        self.min_price <= price && price <= self.max_price
+    }
+
+    /// converts a bracket in the given currency to the base currency
+    fn to_base(self, currency: &Currency) -> Self {
+        Self {
+            min_price: currency.price_to_base(self.min_price),
+            max_price: currency.price_to_base(self.max_price),
+        }
+    }
+
+    /// converts a bracket in the base currency to the given currency
+    fn to_currency(self, currency: &Currency) -> Self {
+        Self {
+            min_price: currency.price_from_base(self.min_price),
+            max_price: currency.price_from_base(self.max_price),
+        }
+    }
+}
+
+/// the best laptop in a single price bracket
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceBracketSelection {
+    #[serde(flatten)]
+    pub bracket: PriceBracket,
+    /// the amount of laptops in this bracket that were considered
+    pub laptops_amount: usize,
+    /// the best laptop in this bracket, if there are any laptops in it
+    pub best_laptop: Option<SelectedLaptop>,
+}
+
+/// finds the best laptop in each price bracket in a single pass over the laptops. a laptop whose
+/// price is contained in several brackets, for example a laptop whose price is exactly on the
+/// boundary between two brackets, belongs only to the first of them.
+pub fn select_best_per_price_bracket(
+    db_connection: &SelectorDBConnection,
+    strategy: &dyn SelectionStrategy,
+    parameters: &PriceBracketsParameters,
+) -> Result<Vec<PriceBracketSelection>> {
+    if parameters.category_scores.is_empty() {
+        return Err(SelectorErrorKind::NoScoresProvided.into_empty_selector_error());
+    }
+    parameters.category_scores.validate()?;
+    if !(0.0..=1.0).contains(&parameters.min_confidence) {
+        return Err(SelectorErrorKind::InvalidMinConfidence.into_empty_selector_error());
+    }
+    let currency = Currency::resolve(db_connection, parameters.currency.as_deref())?;
+    let user_category_scores_by_id = parameters
+        .category_scores
+        .remap_key_to_category_id(db_connection)?;
+
+    // only load the laptops that could belong to some bracket
+    let price_limits = db_connection.fetch_price_limits()?;
+    let score_matrix = LaptopScoreMatrix::load(
+        db_connection,
+        parameters.brackets.max_price(&price_limits, &currency),
+    )?;
+    let brackets = parameters.brackets.resolve(&price_limits, &score_matrix, &currency)?;
+
+    let weights = user_category_scores_by_id.to_weights(score_matrix.category_ids());
+    let no_preferences = SoftPreferences::default();
+    let criteria = RankingCriteria {
+        strategy,
+        weights: &weights,
+        preferences: &no_preferences,
+        currency: &currency,
+        min_confidence: parameters.min_confidence,
+    };
+
+    let mut best_in_brackets: Vec<TopLaptops> = brackets.iter().map(|_| TopLaptops::new(1)).collect();
+    let mut laptops_amounts = vec![0; brackets.len()];
+    for row in score_matrix.rows().filter(|row| criteria.accepts(row)) {
+        if let Some(bracket_index) = brackets.iter().position(|bracket| bracket.contains(row.price)) {
+            laptops_amounts[bracket_index] += 1;
+            best_in_brackets[bracket_index].update(row, criteria.score(&row));
+        }
+    }
+
+    // load the information about the best laptops of all brackets together. brackets without any
+    // laptops have no best laptop, so the selected laptops are in the order of the non-empty brackets.
+    let ranked_laptops: Vec<RankedLaptop> = best_in_brackets
+        .into_iter()
+        .flat_map(|best_in_bracket| best_in_bracket.into_ranked_laptops(&criteria, &score_matrix))
+        .collect();
+    let category_id_to_name_map = db_connection.fetch_category_names()?;
+    let mut selected_laptops = db_connection
+        .fetch_selected_laptops(
+            ranked_laptops,
+            &category_id_to_name_map,
+            &currency,
+        )?
+        .into_iter();
+
+    // explicit brackets are returned as they were given, rather than converted back from the base
+    // currency, which could change them slightly due to rounding
+    let returned_brackets: Vec<PriceBracket> = match &parameters.brackets {
+        PriceBracketsSpec::Explicit(brackets) => brackets.clone(),
+        PriceBracketsSpec::EqualWidth(_) | PriceBracketsSpec::Quantiles(_) => brackets
+            .iter()
+            .map(|bracket| bracket.to_currency(&currency))
+            .collect(),
+    };
+    Ok(returned_brackets
+        .into_iter()
+        .zip(laptops_amounts)
+        .map(|(bracket, laptops_amount)| PriceBracketSelection {
+            bracket,
+            laptops_amount,
+            best_laptop: if laptops_amount > 0 {
+                selected_laptops.next()
+            } else {
+                None
+            },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::StrategyRegistry;
+    use crate::test_database::{connect_to_test_dataset, insert_laptop, FixtureLaptop};
+
+    /// the min and max prices of a bracket and the amount of laptops in it, along with the id and price of
+    /// its best laptop
+    type BracketSummary = (f32, f32, usize, Option<(i32, f32)>);
+
+    fn price_brackets(db_connection: &SelectorDBConnection, parameters: &str) -> Vec<BracketSummary> {
+        let parameters: PriceBracketsParameters = serde_json::from_str(parameters).unwrap();
+        let strategies = StrategyRegistry::with_builtin_strategies();
+        let strategy = strategies.resolve(Some("weightedSum"), None).unwrap();
+        select_best_per_price_bracket(db_connection, strategy, &parameters)
+            .unwrap()
+            .into_iter()
+            .map(|selection| {
+                (
+                    selection.bracket.min_price,
+                    selection.bracket.max_price,
+                    selection.laptops_amount,
+                    selection.best_laptop.map(|laptop| (laptop.id, laptop.price)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prices_are_in_the_requested_currency() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 1000.0, &[(2.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 2000.0, &[(3.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(3, 3000.0, &[(4.0, 1.0)]));
+
+        // the explicit brackets are converted to the base currency, and returned as they were given
+        let parameters = r#"{
+            "categoryScores": {"gaming": 1},
+            "brackets": {"explicit": [{"minPrice": 0, "maxPrice": 300}, {"minPrice": 300, "maxPrice": 600}]},
+            "currency": "usd"
+        }"#;
+        assert_eq!(
+            price_brackets(&db_connection, parameters),
+            [(0.0, 300.0, 1, Some((1, 250.0))), (300.0, 600.0, 1, Some((2, 500.0)))]
+        );
+
+        // the price limits of the test dataset are 0 and 100000 in the base currency
+        let parameters = r#"{"categoryScores": {"gaming": 1}, "brackets": {"equalWidth": 2}, "currency": "usd"}"#;
+        assert_eq!(
+            price_brackets(&db_connection, parameters),
+            [(0.0, 12500.0, 3, Some((3, 750.0))), (12500.0, 25000.0, 0, None)]
+        );
+    }
+
+    /// the min and max prices of each bracket
+    fn bounds(brackets: &[PriceBracket]) -> Vec<(f32, f32)> {
+        brackets
+            .iter()
+            .map(|bracket| (bracket.min_price, bracket.max_price))
+            .collect()
+    }
+
+    #[test]
+    fn equal_width_brackets_end_exactly_at_the_max_price() {
+        assert_eq!(
+            bounds(&equal_width_brackets(1000.0, 4000.0, 3)),
+            [(1000.0, 2000.0), (2000.0, 3000.0), (3000.0, 4000.0)]
+        );
+
+        // a width that can't be represented exactly still ends at the max price, and the brackets
+        // still share their boundaries
+        let brackets = equal_width_brackets(0.1, 1000.7, 7);
+        assert_eq!(brackets.len(), 7);
+        assert_eq!(brackets[0].min_price, 0.1);
+        assert_eq!(brackets[6].max_price, 1000.7);
+        for adjacent_brackets in brackets.windows(2) {
+            assert_eq!(adjacent_brackets[0].max_price, adjacent_brackets[1].min_price);
+        }
+    }
+
+    #[test]
+    fn quantile_boundaries_may_repeat_duplicate_prices() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        for (id, price) in [(1, 1000.0), (2, 1000.0), (3, 1000.0), (4, 2000.0), (5, 3000.0)].iter() {
+            insert_laptop(&db_connection, &FixtureLaptop::new(*id, *price, &[(*id as f32, 1.0)]));
+        }
+        let price_limits = db_connection.fetch_price_limits().unwrap();
+        let score_matrix = LaptopScoreMatrix::load(&db_connection, price_limits.max_price).unwrap();
+        let currency = Currency::base(&db_connection).unwrap();
+        let brackets = PriceBracketsSpec::Quantiles(2)
+            .resolve(&price_limits, &score_matrix, &currency)
+            .unwrap();
+        assert_eq!(bounds(&brackets), [(1000.0, 1000.0), (1000.0, 3000.0)]);
+
+        // the laptops on the shared boundary only belong to the first bracket
+        let parameters = r#"{"categoryScores": {"gaming": 1}, "brackets": {"quantiles": 2}}"#;
+        assert_eq!(
+            price_brackets(&db_connection, parameters),
+            [(1000.0, 1000.0, 3, Some((3, 1000.0))), (1000.0, 3000.0, 2, Some((5, 3000.0)))]
+        );
+    }
+
+    #[test]
+    fn a_laptop_on_the_boundary_belongs_only_to_the_first_bracket() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 1000.0, &[(2.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 2000.0, &[(3.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(3, 2500.0, &[(1.0, 1.0)]));
+
+        let parameters = r#"{
+            "categoryScores": {"gaming": 1},
+            "brackets": {"explicit": [{"minPrice": 0, "maxPrice": 2000}, {"minPrice": 2000, "maxPrice": 3000}]}
+        }"#;
+        assert_eq!(
+            price_brackets(&db_connection, parameters),
+            [(0.0, 2000.0, 2, Some((2, 2000.0))), (2000.0, 3000.0, 1, Some((3, 2500.0)))]
+        );
+    }
+
+    #[test]
+    fn an_empty_bracket_has_no_best_laptop() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 1000.0, &[(2.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 3000.0, &[(3.0, 1.0)]));
+
+        let parameters = r#"{
+            "categoryScores": {"gaming": 1},
+            "brackets": {"explicit": [
+                {"minPrice": 0, "maxPrice": 1500},
+                {"minPrice": 1500, "maxPrice": 2500},
+                {"minPrice": 2500, "maxPrice": 3500}
+            ]}
+        }"#;
+        assert_eq!(
+            price_brackets(&db_connection, parameters),
+            [
+                (0.0, 1500.0, 1, Some((1, 1000.0))),
+                (1500.0, 2500.0, 0, None),
+                (2500.0, 3500.0, 1, Some((2, 3000.0)))
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_brackets_are_rejected() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        let price_limits = db_connection.fetch_price_limits().unwrap();
+        let score_matrix = LaptopScoreMatrix::load(&db_connection, price_limits.max_price).unwrap();
+        let currency = Currency::base(&db_connection).unwrap();
+        let invalid_specs = vec![
+            PriceBracketsSpec::EqualWidth(0),
+            PriceBracketsSpec::Quantiles(0),
+            PriceBracketsSpec::Explicit(Vec::new()),
+            PriceBracketsSpec::Explicit(vec![PriceBracket {
+                min_price: 3000.0,
+                max_price: 2000.0,
+            }]),
+            PriceBracketsSpec::Explicit(vec![
+                PriceBracket {
+                    min_price: 0.0,
+                    max_price: 1000.0,
+                },
+                PriceBracket {
+                    min_price: 1000.0,
+                    max_price: f32::INFINITY,
+                },
+            ]),
+        ];
+        for spec in invalid_specs {
+            let error = spec.resolve(&price_limits, &score_matrix, &currency).unwrap_err();
+            assert_eq!(error.kind, SelectorErrorKind::InvalidPriceBrackets, "{:?}", spec);
+        }
+    }
+}
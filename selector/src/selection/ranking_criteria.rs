@@ -0,0 +1,57 @@
+use super::{
+    currency::Currency,
+    preferences::{PreferenceAdjustment, SoftPreferences},
+    score_matrix::LaptopRow,
+    strategies::SelectionStrategy,
+    user_category_scores::CategoryWeights,
+};
+
+/// everything that determines the score of a laptop in a selection
+pub struct RankingCriteria<'a> {
+    pub strategy: &'a dyn SelectionStrategy,
+    pub weights: &'a CategoryWeights,
+    pub preferences: &'a SoftPreferences,
+    /// the currency of the prices in the soft preferences, which the laptops' prices are converted to
+    /// before they are compared with the preferences
+    pub currency: &'a Currency,
+    /// laptops whose confidence is lower than this are not ranked at all
+    pub min_confidence: f32,
+}
+impl<'a> RankingCriteria<'a> {
+    /// checks whether the laptop should be ranked at all
+    pub fn accepts(&self, row: &LaptopRow) -> bool {
+        !row.excluded && (self.min_confidence <= 0.0 || self.confidence(row) >= self.min_confidence)
+    }
+
+    /// calculates the confidence of a laptop's score, which is the average of the confidences of
+    /// its scores in categories, weighted by the user's category scores.
+    pub fn confidence(&self, row: &LaptopRow) -> f32 {
+        self.weights.weighted_average(row.confidences)
+    }
+
+    /// calculates the score of a laptop, which is the total score calculated by the strategy,
+    /// adjusted according to the user's soft preferences.
+    pub fn score(&self, row: &LaptopRow) -> f32 {
+        let base_score = self.strategy.total_score(row, self.weights);
+        match row.specs {
+            Some(specs) if !self.preferences.is_empty() => {
+                let price = self.currency.price_from_base(row.price);
+                base_score + self.preferences.total_adjustment(base_score, price, specs)
+            }
+            _ => base_score,
+        }
+    }
+
+    /// lists the adjustments that were applied to the score of a laptop due to the user's
+    /// soft preferences.
+    pub fn preference_adjustments(&self, row: &LaptopRow) -> Vec<PreferenceAdjustment> {
+        match row.specs {
+            Some(specs) if !self.preferences.is_empty() => {
+                let base_score = self.strategy.total_score(row, self.weights);
+                let price = self.currency.price_from_base(row.price);
+                self.preferences.adjustments(base_score, price, specs)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{errors::*, fetch_data::FetchData, SelectorDBConnection};
+
+/// the scores in categories of all candidate laptops, stored as a contiguous laptop × category
+/// matrix so that scoring a laptop is a walk over a single slice instead of hash lookups.
+#[derive(Debug)]
+pub struct LaptopScoreMatrix {
+    /// the id of the laptop in each row
+    laptop_ids: Vec<i32>,
+    /// the price of the laptop in each row
+    prices: Vec<f32>,
+    /// the id of the category in each column
+    category_ids: Vec<i32>,
+    /// the scores, stored row after row, such that the score of the laptop in row `r`
+    /// in the category in column `c` is at index `r * category_ids.len() + c`
+    scores: Vec<f32>,
+    /// the confidence of each of the scores, stored in the same layout as the scores
+    confidences: Vec<f32>,
+    /// the specs of the laptop in each row. the specs are only loaded when they are
+    /// required for ranking the laptops.
+    specs: Option<Vec<CandidateSpecs>>,
+    /// the index of the model family of the laptop in each row. the families are only loaded
+    /// when the laptops should be grouped by their model.
+    families: Option<Vec<usize>>,
+    /// whether the laptop in each row was excluded by the user. the exclusions are only set when
+    /// the user excluded some laptops.
+    excluded: Option<Vec<bool>>,
+}
+impl LaptopScoreMatrix {
+    /// loads the scores in categories of all laptops where the price is lower or equal to the
+    /// given max price, and arranges them in a matrix.
+    pub fn load(db_connection: &SelectorDBConnection, max_price: f32) -> Result<Self> {
+        let categories = db_connection.fetch_all_categories()?;
+        let category_ids: Vec<i32> = categories.iter().map(|category| category.id).collect();
+        let column_by_category_id: HashMap<i32, usize> = category_ids
+            .iter()
+            .enumerate()
+            .map(|(column, &category_id)| (category_id, column))
+            .collect();
+
+        let laptop_scores_in_categories =
+            db_connection.fetch_laptop_scores_in_categories(max_price)?;
+
+        let columns_amount = category_ids.len();
+        let mut laptop_ids = Vec::new();
+        let mut prices = Vec::new();
+        let mut row_by_laptop_id = HashMap::new();
+
+        // the cells are initialized to NaN so that we could later find cells that were never filled
+        let mut scores = Vec::new();
+        let mut confidences = Vec::new();
+        for laptop_score_in_category in laptop_scores_in_categories {
+            // first find or create the row of this laptop
+            let row = *row_by_laptop_id
+                .entry(laptop_score_in_category.laptop_id)
+                .or_insert_with(|| {
+                    laptop_ids.push(laptop_score_in_category.laptop_id);
+                    prices.push(laptop_score_in_category.price);
+                    scores.resize(scores.len() + columns_amount, f32::NAN);
+                    confidences.resize(confidences.len() + columns_amount, f32::NAN);
+                    laptop_ids.len() - 1
+                });
+
+            // a score or a confidence that is not finite would make the ranking meaningless, so
+            // it is rejected here instead of being silently ranked
+            if !laptop_score_in_category.score.is_finite()
+                || !laptop_score_in_category.confidence.is_finite()
+            {
+                return Err(SelectorErrorKind::NonFiniteLaptopScore {
+                    laptop_id: laptop_score_in_category.laptop_id,
+                    category_id: laptop_score_in_category.category_id,
+                }
+                .into_empty_selector_error());
+            }
+
+            // then fill the cell of this category in the laptop's row
+            let column = column_by_category_id[&laptop_score_in_category.category_id];
+            scores[row * columns_amount + column] = laptop_score_in_category.score;
+            confidences[row * columns_amount + column] = laptop_score_in_category.confidence;
+        }
+
+        let matrix = Self {
+            laptop_ids,
+            prices,
+            category_ids,
+            scores,
+            confidences,
+            specs: None,
+            families: None,
+            excluded: None,
+        };
+        matrix.verify_all_cells_are_filled()?;
+        Ok(matrix)
+    }
+
+    /// loads the specs of all laptops in the matrix
+    pub fn load_specs(&mut self, db_connection: &SelectorDBConnection, max_price: f32) -> Result<()> {
+        let mut specs_by_laptop_id: HashMap<i32, CandidateSpecs> = db_connection
+            .fetch_candidate_specs(max_price)?
+            .into_iter()
+            .map(|specs| (specs.laptop_id, specs))
+            .collect();
+
+        let mut specs = Vec::with_capacity(self.laptops_amount());
+        for &laptop_id in &self.laptop_ids {
+            match specs_by_laptop_id.remove(&laptop_id) {
+                Some(laptop_specs) => specs.push(laptop_specs),
+                None => {
+                    return Err(SelectorErrorKind::LaptopHasNoSpecs { laptop_id }
+                        .into_empty_selector_error())
+                }
+            }
+        }
+        self.specs = Some(specs);
+        Ok(())
+    }
+
+    /// loads the model families of all laptops in the matrix. laptops with the same brand and model
+    /// get the same family index, and each laptop with an unknown brand or model gets a family of
+    /// its own.
+    pub fn load_families(&mut self, db_connection: &SelectorDBConnection, max_price: f32) -> Result<()> {
+        let mut family_key_by_laptop_id: HashMap<i32, Option<String>> = db_connection
+            .fetch_laptop_brands_and_models(max_price)?
+            .into_iter()
+            .map(|laptop| {
+                let key = model_family_key(laptop.brand.as_deref(), laptop.model.as_deref());
+                (laptop.laptop_id, key)
+            })
+            .collect();
+
+        let mut family_by_key = HashMap::new();
+        let mut families = Vec::with_capacity(self.laptops_amount());
+        for (row, laptop_id) in self.laptop_ids.iter().enumerate() {
+            let family = match family_key_by_laptop_id.remove(laptop_id).flatten() {
+                Some(key) => {
+                    let next_family = family_by_key.len() + self.laptops_amount();
+                    *family_by_key.entry(key).or_insert(next_family)
+                }
+                // the row index is unique, and is lower than the indexes given to the families
+                // with known keys, so the laptop is alone in its family
+                None => row,
+            };
+            families.push(family);
+        }
+        self.families = Some(families);
+        Ok(())
+    }
+
+    /// marks the laptops with the given ids as excluded, so that they are never ranked
+    pub fn exclude(&mut self, laptop_ids: &[i32]) {
+        let laptop_ids: HashSet<i32> = laptop_ids.iter().copied().collect();
+        self.excluded = Some(
+            self.laptop_ids
+                .iter()
+                .map(|laptop_id| laptop_ids.contains(laptop_id))
+                .collect(),
+        );
+    }
+
+    /// after the data processor runs, all laptops should have scores for all categories, and it does
+    /// not make sense for a laptop to not have a score for some category, so return an error if some
+    /// cell of the matrix was not filled.
+    fn verify_all_cells_are_filled(&self) -> Result<()> {
+        for row in self.rows() {
+            if let Some(column) = row.scores.iter().position(|score| score.is_nan()) {
+                return Err(SelectorErrorKind::LaptopHasNoScoreForCategory {
+                    laptop_id: row.laptop_id,
+                    category_id: self.category_ids[column],
+                }
+                .into_empty_selector_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// the ids of the categories, in the order of the matrix's columns
+    pub fn category_ids(&self) -> &[i32] {
+        &self.category_ids
+    }
+
+    /// the amount of laptops in the matrix
+    pub fn laptops_amount(&self) -> usize {
+        self.laptop_ids.len()
+    }
+
+    /// returns the row at the given index
+    pub fn row(&self, index: usize) -> LaptopRow<'_> {
+        let columns_amount = self.category_ids.len();
+        let cells = index * columns_amount..(index + 1) * columns_amount;
+        LaptopRow {
+            index,
+            laptop_id: self.laptop_ids[index],
+            price: self.prices[index],
+            scores: &self.scores[cells.clone()],
+            confidences: &self.confidences[cells],
+            family: self.families.as_ref().map(|families| families[index]),
+            excluded: self.excluded.as_ref().is_some_and(|excluded| excluded[index]),
+            specs: self.specs.as_ref().map(|specs| &specs[index]),
+        }
+    }
+
+    /// returns an iterator over all rows of the matrix
+    pub fn rows(&self) -> impl Iterator<Item = LaptopRow<'_>> {
+        (0..self.laptops_amount()).map(move |index| self.row(index))
+    }
+
+    /// materializes the scores in categories of the laptop in the given row, mapped by category id.
+    /// this should only be used for the laptops that are actually returned to the user.
+    pub fn scores_in_categories_of(&self, row_index: usize) -> ScoresInCategoriesOfLaptop {
+        ScoresInCategoriesOfLaptop(
+            self.category_ids
+                .iter()
+                .copied()
+                .zip(self.row(row_index).scores.iter().copied())
+                .collect(),
+        )
+    }
+
+    /// materializes the confidences of the scores in categories of the laptop in the given row,
+    /// mapped by category id.
+    pub fn confidences_in_categories_of(&self, row_index: usize) -> HashMap<i32, f32> {
+        self.category_ids
+            .iter()
+            .copied()
+            .zip(self.row(row_index).confidences.iter().copied())
+            .collect()
+    }
+}
+
+/// a single row of the laptop score matrix
+#[derive(Debug, Clone, Copy)]
+pub struct LaptopRow<'a> {
+    /// the index of this row in the matrix
+    pub index: usize,
+    pub laptop_id: i32,
+    pub price: f32,
+    /// the scores of the laptop in each category, in the order of the matrix's columns
+    pub scores: &'a [f32],
+    /// the confidence of each of the scores, in the order of the matrix's columns
+    pub confidences: &'a [f32],
+    /// the specs of the laptop, if they were loaded
+    pub specs: Option<&'a CandidateSpecs>,
+    /// the index of the model family of the laptop, if the families were loaded
+    pub family: Option<usize>,
+    /// whether the laptop was excluded by the user
+    pub excluded: bool,
+}
+
+/// the key that identifies the model family of a laptop, which is its brand and model, compared
+/// case insensitively and ignoring extra whitespace, since stores don't write them consistently.
+/// returns `None` if the brand or the model is unknown.
+fn model_family_key(brand: Option<&str>, model: Option<&str>) -> Option<String> {
+    let (brand, model) = (brand?, model?);
+    if brand.trim().is_empty() || model.trim().is_empty() {
+        return None;
+    }
+    let words: Vec<String> = brand
+        .split_whitespace()
+        .chain(model.split_whitespace())
+        .map(str::to_lowercase)
+        .collect();
+    Some(words.join(" "))
+}
+
+/// the specs of a candidate laptop that are used for ranking it
+#[derive(Debug, Queryable)]
+pub struct CandidateSpecs {
+    pub laptop_id: i32,
+    pub ram_gigabytes: i32,
+    pub weight_grams: f32,
+    pub storage_gigabytes: Option<i32>,
+}
+
+/// the scores in categories of a single laptop, by category id
+#[derive(Debug, Clone)]
+pub struct ScoresInCategoriesOfLaptop(HashMap<i32, f32>);
+impl From<HashMap<i32, f32>> for ScoresInCategoriesOfLaptop {
+    fn from(scores_in_categories: HashMap<i32, f32>) -> Self {
+        Self(scores_in_categories)
+    }
+}
+impl ScoresInCategoriesOfLaptop {
+    pub fn inner(self) -> HashMap<i32, f32> {
+        self.0
+    }
+}
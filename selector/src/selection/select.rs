@@ -1,60 +1,444 @@
-use super::scores_in_categories_of_laptops::MappedScoresInCategoriesOfLaptops;
-use super::top_laptops::TopLaptops;
+use super::budget_curve::{self, BudgetBreakpoint, BudgetCurveParameters};
+use super::currency::Currency;
+use super::database_ranking;
+use super::exclusions::Exclusions;
+use super::preferences::SoftPreferences;
+use super::price_brackets::{self, PriceBracketSelection, PriceBracketsParameters};
+use super::ranking_criteria::RankingCriteria;
+use super::score_matrix::LaptopScoreMatrix;
+use super::stability::StabilityParameters;
+use super::strategies::SelectionStrategy;
+use super::top_laptops::{RankedLaptop, TopLaptops};
+use super::upgrades::{self, UpgradeParameters, UpgradeRecommendations};
 use super::user_category_scores::UserCategoryScoresByName;
 use super::TOP_LAPTOPS_AMOUNT;
 use crate::fetch_data::{FetchData, SelectedLaptop};
 use crate::{errors::*, SelectorDBConnection};
+use serde::Deserialize;
+
+/// the parameters of a selection request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionParameters {
+    pub max_price: f32,
+    pub category_scores: UserCategoryScoresByName,
+
+    /// the name of the strategy that should rank the laptops. if not provided, the
+    /// strategy is chosen by the server.
+    #[serde(default)]
+    pub strategy: Option<String>,
+
+    /// an identifier of the client, used to consistently assign it to the same
+    /// strategy when the server performs an a/b split.
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// where the laptops should be ranked
+    #[serde(default)]
+    pub backend: SelectionBackend,
+
+    /// soft preferences on the laptops' specs, which adjust the laptops' scores
+    #[serde(default)]
+    pub preferences: SoftPreferences,
+
+    /// the minimum confidence, between 0 and 1, that a laptop must have in order to be selected.
+    /// the confidence of a laptop is the share of its score that is backed by measured benchmarks
+    /// rather than by average scores that were given for missing benchmarks.
+    #[serde(default)]
+    pub min_confidence: f32,
+
+    /// whether the laptops should be grouped by their model family, which is their brand and model.
+    /// when grouped, each selected laptop is the best variant of its family, and lists the other
+    /// variants of the family.
+    #[serde(default)]
+    pub group_by_model: bool,
+
+    /// the code of the currency of the max price and of the returned prices. if not provided, the
+    /// base currency is used.
+    #[serde(default)]
+    pub currency: Option<String>,
+
+    /// the laptops that should never be selected. they are skipped while ranking, so the
+    /// selection still returns the full amount of laptops.
+    #[serde(default)]
+    pub exclude: Exclusions,
+
+    /// when provided, the laptops are ranked again many times with randomly perturbed category
+    /// scores, to find out how stable the ranking is
+    #[serde(default)]
+    pub stability: Option<StabilityParameters>,
+}
+
+/// where the ranking of the laptops is performed
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SelectionBackend {
+    /// load the scores of all candidate laptops and rank them in the selector
+    #[default]
+    InProcess,
+    /// rank the laptops inside the database, and only load the top laptops
+    Database,
+}
 
 pub trait Select {
-    /// selects the top laptops given the user category scores and max price.
+    /// selects the top laptops given the selection parameters, ranking them using the given strategy.
     fn select(
         &self,
-        user_category_scores: &UserCategoryScoresByName,
-        max_price: f32,
+        strategy: &dyn SelectionStrategy,
+        parameters: &SelectionParameters,
     ) -> Result<Vec<SelectedLaptop>>;
+
+    /// finds the best laptop in each of the price brackets described by the parameters,
+    /// ranking them using the given strategy.
+    fn select_best_per_price_bracket(
+        &self,
+        strategy: &dyn SelectionStrategy,
+        parameters: &PriceBracketsParameters,
+    ) -> Result<Vec<PriceBracketSelection>>;
+
+    /// finds the breakpoints where raising the max price improves the best achievable score,
+    /// ranking the laptops using the given strategy.
+    fn select_budget_curve(
+        &self,
+        strategy: &dyn SelectionStrategy,
+        parameters: &BudgetCurveParameters,
+    ) -> Result<Vec<BudgetBreakpoint>>;
+
+    /// finds the cheapest laptops that are a big enough upgrade over the user's current laptop
+    fn recommend_upgrades(&self, parameters: &UpgradeParameters) -> Result<UpgradeRecommendations>;
 }
 impl Select for SelectorDBConnection {
     fn select(
         &self,
-        user_category_scores_by_name: &UserCategoryScoresByName,
-        max_price: f32,
+        strategy: &dyn SelectionStrategy,
+        parameters: &SelectionParameters,
     ) -> Result<Vec<SelectedLaptop>> {
-        if user_category_scores_by_name.is_empty() {
-            return Err(SelectorErrorKind::NoScoresProvided.into_empty_selector_error());
-        }
+        let currency = Currency::resolve(self, parameters.currency.as_deref())?;
+        let ranked_laptops =
+            rank_laptops(self, strategy, parameters, &currency, parameters.backend)?;
 
-        // remap the user category scores to be mapped by category id instead of category name
-        let user_category_scores_by_id =
-            user_category_scores_by_name.remap_key_to_category_id(self)?;
+        // we have the ids of the selected laptops, we now need to load the
+        // information about them from the database. the selected laptops keep the order of the ranking.
+        let category_id_to_name_map = self.fetch_category_names()?;
+        self.fetch_selected_laptops(ranked_laptops, &category_id_to_name_map, &currency)
+    }
 
-        // load and map the laptop scores in categories
-        let scores_in_categories_of_laptops =
-            MappedScoresInCategoriesOfLaptops::load(self, max_price)?;
+    fn select_best_per_price_bracket(
+        &self,
+        strategy: &dyn SelectionStrategy,
+        parameters: &PriceBracketsParameters,
+    ) -> Result<Vec<PriceBracketSelection>> {
+        price_brackets::select_best_per_price_bracket(self, strategy, parameters)
+    }
 
-        let laptop_prices = self.fetch_laptop_prices()?;
+    fn select_budget_curve(
+        &self,
+        strategy: &dyn SelectionStrategy,
+        parameters: &BudgetCurveParameters,
+    ) -> Result<Vec<BudgetBreakpoint>> {
+        budget_curve::select_budget_curve(self, strategy, parameters)
+    }
 
-        // find the top laptops
-        let mut top_laptops = TopLaptops::new(TOP_LAPTOPS_AMOUNT);
-        top_laptops.find_top_laptops(
+    fn recommend_upgrades(&self, parameters: &UpgradeParameters) -> Result<UpgradeRecommendations> {
+        upgrades::recommend_upgrades(self, parameters)
+    }
+}
+
+/// finds the top laptops, from best to worst, using the given backend. the max price of the
+/// parameters is in the given currency.
+fn rank_laptops(
+    db_connection: &SelectorDBConnection,
+    strategy: &dyn SelectionStrategy,
+    parameters: &SelectionParameters,
+    currency: &Currency,
+    backend: SelectionBackend,
+) -> Result<Vec<RankedLaptop>> {
+    if parameters.category_scores.is_empty() {
+        return Err(SelectorErrorKind::NoScoresProvided.into_empty_selector_error());
+    }
+    parameters.category_scores.validate()?;
+    if parameters.max_price.is_nan() {
+        return Err(SelectorErrorKind::InvalidMaxPrice.into_empty_selector_error());
+    }
+    parameters.preferences.validate()?;
+    if !(0.0..=1.0).contains(&parameters.min_confidence) {
+        return Err(SelectorErrorKind::InvalidMinConfidence.into_empty_selector_error());
+    }
+    parameters.exclude.validate()?;
+    if let Some(stability) = &parameters.stability {
+        stability.validate()?;
+    }
+
+    // the prices in the database are in the base currency
+    let max_price = currency.price_to_base(parameters.max_price);
+    let excluded_laptop_ids = parameters
+        .exclude
+        .find_excluded_laptop_ids(db_connection, max_price)?;
+
+    // remap the user category scores to be mapped by category id instead of category name
+    let user_category_scores_by_id = parameters
+        .category_scores
+        .remap_key_to_category_id(db_connection)?;
+
+    match backend {
+        SelectionBackend::InProcess => {
+            // load the laptop scores in categories into a matrix, and arrange the user category
+            // scores according to the matrix's columns
+            let mut score_matrix = LaptopScoreMatrix::load(db_connection, max_price)?;
+            let weights = user_category_scores_by_id.to_weights(score_matrix.category_ids());
+
+            // the specs are only required for applying the soft preferences
+            if !parameters.preferences.is_empty() {
+                score_matrix.load_specs(db_connection, max_price)?;
+            }
+            if parameters.group_by_model {
+                score_matrix.load_families(db_connection, max_price)?;
+            }
+            if !excluded_laptop_ids.is_empty() {
+                score_matrix.exclude(&excluded_laptop_ids);
+            }
+
+            let criteria = RankingCriteria {
+                strategy,
+                weights: &weights,
+                preferences: &parameters.preferences,
+                currency,
+                min_confidence: parameters.min_confidence,
+            };
+            let find_top_laptops = |criteria: &RankingCriteria| {
+                let mut top_laptops = TopLaptops::new(TOP_LAPTOPS_AMOUNT);
+                if parameters.group_by_model {
+                    top_laptops.find_top_model_families(criteria, &score_matrix);
+                } else {
+                    top_laptops.find_top_laptops(criteria, &score_matrix);
+                }
+                top_laptops
+            };
+            let mut ranked_laptops =
+                find_top_laptops(&criteria).into_ranked_laptops(&criteria, &score_matrix);
+
+            if let Some(stability) = &parameters.stability {
+                // the analysis ranks the laptops using the unperturbed criteria too, in the exact
+                // same way, so its results are in the order of the ranked laptops
+                let laptops_stability = stability.analyze(
+                    &criteria,
+                    &score_matrix,
+                    parameters.group_by_model,
+                    find_top_laptops,
+                );
+                for (ranked_laptop, laptop_stability) in ranked_laptops.iter_mut().zip(laptops_stability) {
+                    ranked_laptop.stability = Some(laptop_stability);
+                }
+            }
+            Ok(ranked_laptops)
+        }
+        SelectionBackend::Database if !parameters.preferences.is_empty() => Err(
+            SelectorErrorKind::PreferencesNotSupportedByDatabaseRanking
+                .into_empty_selector_error(),
+        ),
+        SelectionBackend::Database if parameters.group_by_model => Err(
+            SelectorErrorKind::GroupingNotSupportedByDatabaseRanking.into_empty_selector_error(),
+        ),
+        SelectionBackend::Database if parameters.stability.is_some() => Err(
+            SelectorErrorKind::StabilityNotSupportedByDatabaseRanking.into_empty_selector_error(),
+        ),
+        SelectionBackend::Database => database_ranking::rank_in_database(
+            db_connection,
+            strategy,
             &user_category_scores_by_id,
-            &scores_in_categories_of_laptops,
-            &laptop_prices,
-        )?;
+            max_price,
+            parameters.min_confidence,
+            &excluded_laptop_ids,
+            TOP_LAPTOPS_AMOUNT,
+        ),
+    }
+}
 
-        // we have the ids of the selected laptops, we now need to load the
-        // information about them from the database
-        let selected_laptop_ids = top_laptops.laptop_ids();
-        let id_to_score_map = top_laptops.laptop_id_to_score_map();
-        let category_id_to_name_map = self.fetch_category_names()?;
-        let mut selected_laptops = self.fetch_selected_laptops(&selected_laptop_ids, &id_to_score_map, &category_id_to_name_map)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::StrategyRegistry;
+    use crate::test_database::{connect_to_test_dataset, insert_laptop, FixtureLaptop};
+
+    /// ranks the laptops of the test dataset using the given backend, and returns the ids and
+    /// scores of the ranked laptops
+    fn rank_with_backend(
+        db_connection: &SelectorDBConnection,
+        strategy: &dyn SelectionStrategy,
+        parameters: &SelectionParameters,
+        backend: SelectionBackend,
+    ) -> Vec<(i32, f32)> {
+        let currency = Currency::resolve(db_connection, parameters.currency.as_deref()).unwrap();
+        rank_laptops(db_connection, strategy, parameters, &currency, backend)
+            .unwrap()
+            .into_iter()
+            .map(|ranked_laptop| (ranked_laptop.laptop_id, ranked_laptop.score))
+            .collect()
+    }
+
+    #[test]
+    fn both_backends_rank_identically() {
+        let db_connection = connect_to_test_dataset(&["gaming", "dev"]);
+        let laptops = [
+            FixtureLaptop::new(1, 3000.0, &[(5.0, 1.0), (3.0, 1.0)]),
+            // ties with the first laptop, but is cheaper
+            FixtureLaptop::new(2, 2500.0, &[(5.0, 1.0), (3.0, 1.0)]),
+            // ties with the second laptop in both score and price, but has a higher id
+            FixtureLaptop::new(3, 2500.0, &[(5.0, 1.0), (3.0, 1.0)]),
+            // the best laptop, but its scores are mostly made of average scores
+            FixtureLaptop::new(4, 4000.0, &[(9.0, 0.2), (9.0, 0.2)]),
+            FixtureLaptop {
+                name: "Excluded Laptop",
+                ..FixtureLaptop::new(5, 1000.0, &[(4.0, 1.0), (2.0, 1.0)])
+            },
+            FixtureLaptop::new(6, 6000.0, &[(8.0, 1.0), (1.0, 1.0)]),
+            // more expensive than the max price
+            FixtureLaptop::new(7, 9000.0, &[(10.0, 1.0), (10.0, 1.0)]),
+            FixtureLaptop::new(8, 2000.0, &[(6.0, 1.0), (1.0, 1.0)]),
+            FixtureLaptop::new(9, 1500.0, &[(1.0, 0.6), (1.0, 0.8)]),
+        ];
+        for laptop in &laptops {
+            insert_laptop(&db_connection, laptop);
+        }
+
+        let requests = [
+            r#"{"maxPrice": 8000, "categoryScores": {"gaming": 1, "dev": 2}}"#,
+            r#"{"maxPrice": 8000, "categoryScores": {"gaming": 1, "dev": 2}, "minConfidence": 0.5}"#,
+            r#"{"maxPrice": 8000, "categoryScores": {"gaming": 3, "dev": 0.5},
+                "exclude": {"laptopIds": [8], "namePatterns": ["excluded"]}}"#,
+            r#"{"maxPrice": 2000, "categoryScores": {"gaming": 1, "dev": 1}, "currency": "USD"}"#,
+            r#"{"maxPrice": 2500, "categoryScores": {"gaming": 1}}"#,
+        ];
+        let strategies = StrategyRegistry::with_builtin_strategies();
+        for strategy_name in ["weightedSum", "valueForMoney"] {
+            let strategy = strategies.resolve(Some(strategy_name), None).unwrap();
+            for request in requests {
+                let parameters: SelectionParameters = serde_json::from_str(request).unwrap();
+                let in_process = rank_with_backend(
+                    &db_connection,
+                    strategy,
+                    &parameters,
+                    SelectionBackend::InProcess,
+                );
+                let database = rank_with_backend(
+                    &db_connection,
+                    strategy,
+                    &parameters,
+                    SelectionBackend::Database,
+                );
+                assert!(!in_process.is_empty(), "{} ranked no laptops for {}", strategy_name, request);
+                assert_eq!(in_process, database, "{} ranked differently for {}", strategy_name, request);
+            }
+        }
+    }
+
+    #[test]
+    fn ties_are_broken_by_price_and_then_by_id() {
+        let db_connection = connect_to_test_dataset(&["gaming", "dev"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 3000.0, &[(5.0, 1.0), (3.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(3, 2500.0, &[(5.0, 1.0), (3.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 2500.0, &[(5.0, 1.0), (3.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(4, 4000.0, &[(9.0, 0.2), (9.0, 0.2)]));
+
+        let parameters: SelectionParameters = serde_json::from_str(
+            r#"{"maxPrice": 8000, "categoryScores": {"gaming": 1, "dev": 2}, "minConfidence": 0.5}"#,
+        )
+        .unwrap();
+        let strategies = StrategyRegistry::with_builtin_strategies();
+        let strategy = strategies.resolve(Some("weightedSum"), None).unwrap();
+        for backend in [SelectionBackend::InProcess, SelectionBackend::Database] {
+            let laptop_ids: Vec<i32> = rank_with_backend(&db_connection, strategy, &parameters, backend)
+                .into_iter()
+                .map(|(laptop_id, _)| laptop_id)
+                .collect();
+            assert_eq!(laptop_ids, [2, 3, 1], "unexpected ranking of the {:?} backend", backend);
+        }
+    }
+
+    #[test]
+    fn price_preferences_are_in_the_requested_currency() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 1000.0, &[(10.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 3000.0, &[(10.0, 1.0)]));
+
+        // a USD is worth 4 ILS, so the laptops cost 250 and 750 USD
+        let parameters: SelectionParameters = serde_json::from_str(
+            r#"{"maxPrice": 1000, "categoryScores": {"gaming": 1}, "currency": "USD",
+            "preferences": [{"spec": "price", "target": 500, "direction": "atMost", "strength": 0.2}]}"#,
+        )
+        .unwrap();
+        let currency = Currency::resolve(&db_connection, parameters.currency.as_deref()).unwrap();
+        let strategies = StrategyRegistry::with_builtin_strategies();
+        let strategy = strategies.resolve(Some("weightedSum"), None).unwrap();
+        let ranked_laptops = rank_laptops(
+            &db_connection,
+            strategy,
+            &parameters,
+            &currency,
+            SelectionBackend::InProcess,
+        )
+        .unwrap();
+
+        let adjustments: Vec<(i32, f32, f32, f32)> = ranked_laptops
+            .iter()
+            .map(|ranked_laptop| {
+                let adjustment = &ranked_laptop.preference_adjustments[0];
+                (ranked_laptop.laptop_id, adjustment.value, adjustment.target, adjustment.adjustment)
+            })
+            .collect();
+        assert_eq!(adjustments, [(1, 250.0, 500.0, 1.0), (2, 750.0, 500.0, -1.0)]);
+        assert_eq!(ranked_laptops[0].score, 11.0);
+        assert_eq!(ranked_laptops[1].score, 9.0);
+    }
+
+    #[test]
+    fn variants_of_a_model_are_grouped_under_the_best_one() {
+        let db_connection = connect_to_test_dataset(&["gaming"]);
+        let ideapad = |id, price, scores| FixtureLaptop {
+            brand: Some("Lenovo"),
+            model: Some("IdeaPad 5"),
+            ..FixtureLaptop::new(id, price, scores)
+        };
+        let laptops = [
+            ideapad(1, 3000.0, &[(5.0, 1.0)]),
+            ideapad(2, 4000.0, &[(7.0, 1.0)]),
+            // the same model, even though it is written differently
+            FixtureLaptop {
+                brand: Some("lenovo"),
+                model: Some(" ideapad  5"),
+                ..FixtureLaptop::new(3, 3500.0, &[(6.0, 1.0)])
+            },
+            FixtureLaptop {
+                brand: Some("Dell"),
+                model: Some("XPS 13"),
+                ..FixtureLaptop::new(4, 5000.0, &[(6.5, 1.0)])
+            },
+            // a laptop without a model is a family of its own
+            FixtureLaptop {
+                brand: Some("Lenovo"),
+                ..FixtureLaptop::new(5, 2000.0, &[(4.0, 1.0)])
+            },
+        ];
+        for laptop in &laptops {
+            insert_laptop(&db_connection, laptop);
+        }
 
-        // since these laptops were returned from the database, they are no longer sorted by their
-        // score, so we should re-sort them by score
-        selected_laptops.sort_unstable_by(|laptop1, laptop2|{
-            // reverse the comparison result to sort them in descending order instead of ascending
-            laptop1.score.partial_cmp(&laptop2.score).unwrap().reverse()
-        });
+        let strategies = StrategyRegistry::with_builtin_strategies();
+        let strategy = strategies.resolve(Some("weightedSum"), None).unwrap();
+        let parameters: SelectionParameters =
+            serde_json::from_str(r#"{"maxPrice": 8000, "categoryScores": {"gaming": 1}, "groupByModel": true}"#)
+                .unwrap();
+        let currency = Currency::base(&db_connection).unwrap();
+        let ranked_laptops =
+            rank_laptops(&db_connection, strategy, &parameters, &currency, SelectionBackend::InProcess).unwrap();
 
-        Ok(selected_laptops)
+        let families: Vec<(i32, Vec<i32>)> = ranked_laptops
+            .iter()
+            .map(|ranked_laptop| {
+                let variant_ids = ranked_laptop.variants.iter().map(|variant| variant.laptop_id).collect();
+                (ranked_laptop.laptop_id, variant_ids)
+            })
+            .collect();
+        assert_eq!(families, [(2, vec![3, 1]), (4, vec![]), (5, vec![])]);
     }
 }
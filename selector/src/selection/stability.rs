@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ranking_criteria::RankingCriteria, score_matrix::LaptopScoreMatrix, top_laptops::TopLaptops,
+};
+use crate::errors::*;
+
+/// the max amount of perturbed rankings of a single selection, each of which ranks all candidate laptops
+const MAX_SAMPLES: usize = 1000;
+
+/// the seed of the random perturbations. a fixed seed makes identical requests produce identical
+/// stability results, which the result cache relies on.
+const PERTURBATION_SEED: u64 = 0x5eed_f00d_cafe_b0ba;
+
+/// the parameters of a ranking stability analysis, which re-ranks the laptops many times with
+/// randomly perturbed category scores.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StabilityParameters {
+    /// the amount of perturbed rankings
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+
+    /// the max relative change of each category score, for example 0.1 for changes of up to ±10%
+    #[serde(default = "default_perturbation")]
+    pub perturbation: f32,
+}
+
+fn default_samples() -> usize {
+    200
+}
+
+fn default_perturbation() -> f32 {
+    0.1
+}
+
+/// how stable the position of a selected laptop is when the category scores are perturbed
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LaptopStability {
+    /// the share of the perturbed rankings in which the laptop stays one of the top laptops
+    pub top_frequency: f32,
+    /// the share of the perturbed rankings in which the laptop is the best laptop
+    pub first_place_frequency: f32,
+}
+
+impl StabilityParameters {
+    pub fn validate(&self) -> Result<()> {
+        let valid_samples = (1..=MAX_SAMPLES).contains(&self.samples);
+        let valid_perturbation = self.perturbation > 0.0 && self.perturbation <= 1.0;
+        if !valid_samples || !valid_perturbation {
+            return Err(SelectorErrorKind::InvalidStabilityParameters.into_empty_selector_error());
+        }
+        Ok(())
+    }
+
+    /// ranks the laptops once for each sample, each time multiplying each of the criteria's category
+    /// weights by a random factor between `1 - perturbation` and `1 + perturbation`, and counts how
+    /// often each of the laptops that were ranked using the unperturbed criteria stays at the top.
+    ///
+    /// the top laptops are found by the given function, and when the laptops are grouped by model
+    /// family, a laptop counts as staying at the top if any variant of its family does.
+    pub fn analyze(
+        &self,
+        criteria: &RankingCriteria,
+        score_matrix: &LaptopScoreMatrix,
+        group_by_model: bool,
+        find_top_laptops: impl Fn(&RankingCriteria) -> TopLaptops,
+    ) -> Vec<LaptopStability> {
+        let ranking_key = |row_index: usize| {
+            if group_by_model {
+                score_matrix.row(row_index).family.unwrap_or(row_index)
+            } else {
+                row_index
+            }
+        };
+        let top_keys: Vec<usize> = find_top_laptops(criteria)
+            .into_row_indices()
+            .into_iter()
+            .map(ranking_key)
+            .collect();
+
+        let mut random = SplitMix64(PERTURBATION_SEED);
+        let mut top_counts: HashMap<usize, usize> = HashMap::new();
+        let mut first_place_counts: HashMap<usize, usize> = HashMap::new();
+        for _ in 0..self.samples {
+            let weights = criteria
+                .weights
+                .perturbed(|| 1.0 + self.perturbation * (2.0 * random.next_f32() - 1.0));
+            let perturbed_criteria = RankingCriteria {
+                weights: &weights,
+                ..*criteria
+            };
+            let perturbed_top_keys = find_top_laptops(&perturbed_criteria)
+                .into_row_indices()
+                .into_iter()
+                .map(ranking_key);
+            for (rank, key) in perturbed_top_keys.enumerate() {
+                *top_counts.entry(key).or_default() += 1;
+                if rank == 0 {
+                    *first_place_counts.entry(key).or_default() += 1;
+                }
+            }
+        }
+
+        let frequency = |count: Option<&usize>| *count.unwrap_or(&0) as f32 / self.samples as f32;
+        top_keys
+            .iter()
+            .map(|key| LaptopStability {
+                top_frequency: frequency(top_counts.get(key)),
+                first_place_frequency: frequency(first_place_counts.get(key)),
+            })
+            .collect()
+    }
+}
+
+/// a small and fast pseudo random number generator, which is good enough for sampling perturbations
+struct SplitMix64(u64);
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// returns a uniformly distributed number between 0 (inclusive) and 1 (exclusive)
+    fn next_f32(&mut self) -> f32 {
+        // the 24 highest bits fit exactly in the mantissa of an f32
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::{
+        currency::Currency, preferences::SoftPreferences, user_category_scores::UserCategoryScoresByName,
+        StrategyRegistry,
+    };
+    use crate::test_database::{connect_to_test_dataset, insert_laptop, FixtureLaptop};
+    use crate::SelectorDBConnection;
+
+    /// analyzes the stability of the best laptop of the test dataset with equal scores for the gaming
+    /// and dev categories, returning its top frequency and first place frequency
+    fn analyze_best_laptop(
+        db_connection: &SelectorDBConnection,
+        stability: &StabilityParameters,
+    ) -> (f32, f32) {
+        let strategies = StrategyRegistry::with_builtin_strategies();
+        let strategy = strategies.resolve(Some("weightedSum"), None).unwrap();
+        let category_scores: UserCategoryScoresByName =
+            serde_json::from_str(r#"{"gaming": 1, "dev": 1}"#).unwrap();
+        let score_matrix = LaptopScoreMatrix::load(db_connection, 100000.0).unwrap();
+        let weights = category_scores
+            .remap_key_to_category_id(db_connection)
+            .unwrap()
+            .to_weights(score_matrix.category_ids());
+        let no_preferences = SoftPreferences::default();
+        let currency = Currency::base(db_connection).unwrap();
+        let criteria = RankingCriteria {
+            strategy,
+            weights: &weights,
+            preferences: &no_preferences,
+            currency: &currency,
+            min_confidence: 0.0,
+        };
+
+        let laptops_stability = stability.analyze(&criteria, &score_matrix, false, |criteria| {
+            let mut top_laptops = TopLaptops::new(1);
+            top_laptops.find_top_laptops(criteria, &score_matrix);
+            top_laptops
+        });
+        assert_eq!(laptops_stability.len(), 1);
+        (laptops_stability[0].top_frequency, laptops_stability[0].first_place_frequency)
+    }
+
+    fn stability(samples: usize, perturbation: f32) -> StabilityParameters {
+        StabilityParameters { samples, perturbation }
+    }
+
+    #[test]
+    fn a_clear_winner_is_completely_stable() {
+        let db_connection = connect_to_test_dataset(&["gaming", "dev"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 3000.0, &[(5.0, 1.0), (5.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 3000.0, &[(1.0, 1.0), (1.0, 1.0)]));
+
+        assert_eq!(analyze_best_laptop(&db_connection, &stability(100, 0.0)), (1.0, 1.0));
+        assert_eq!(analyze_best_laptop(&db_connection, &stability(100, 0.5)), (1.0, 1.0));
+    }
+
+    #[test]
+    fn nearly_equal_laptops_are_not_stable() {
+        let db_connection = connect_to_test_dataset(&["gaming", "dev"]);
+        // the first laptop wins by a tiny margin, which perturbing the category scores often reverses
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 3000.0, &[(1.0, 1.0), (0.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 3000.0, &[(0.0, 1.0), (0.99, 1.0)]));
+
+        let (top_frequency, first_place_frequency) = analyze_best_laptop(&db_connection, &stability(200, 0.1));
+        assert!(top_frequency < 1.0 && top_frequency > 0.0, "{}", top_frequency);
+        assert_eq!(first_place_frequency, top_frequency);
+    }
+
+    #[test]
+    fn the_fixed_seed_makes_the_analysis_deterministic() {
+        let db_connection = connect_to_test_dataset(&["gaming", "dev"]);
+        insert_laptop(&db_connection, &FixtureLaptop::new(1, 3000.0, &[(1.0, 1.0), (0.0, 1.0)]));
+        insert_laptop(&db_connection, &FixtureLaptop::new(2, 3000.0, &[(0.0, 1.0), (0.99, 1.0)]));
+
+        let parameters = stability(200, 0.1);
+        assert_eq!(
+            analyze_best_laptop(&db_connection, &parameters),
+            analyze_best_laptop(&db_connection, &parameters)
+        );
+    }
+
+    #[test]
+    fn out_of_range_parameters_are_rejected() {
+        let invalid_parameters = [
+            (0, 0.1),
+            (MAX_SAMPLES + 1, 0.1),
+            (200, 0.0),
+            (200, -0.1),
+            (200, 1.1),
+            (200, f32::NAN),
+        ];
+        for (samples, perturbation) in &invalid_parameters {
+            let error = stability(*samples, *perturbation).validate().unwrap_err();
+            assert_eq!(error.kind, SelectorErrorKind::InvalidStabilityParameters);
+        }
+        for (samples, perturbation) in &[(1, 1.0), (MAX_SAMPLES, 0.001)] {
+            stability(*samples, *perturbation).validate().unwrap();
+        }
+    }
+}
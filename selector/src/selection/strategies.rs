@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use super::{ab_split::AbSplit, score_matrix::LaptopRow, user_category_scores::CategoryWeights};
+use crate::errors::*;
+
+/// the name of the strategy that is used when a request doesn't name a strategy
+/// and the client is not part of an a/b split.
+pub const DEFAULT_STRATEGY_NAME: &str = WeightedSumStrategy::NAME;
+
+/// a ranking formula used by the selection. each strategy decides how a laptop's
+/// total score is calculated, while the rest of the selection (loading the scores,
+/// finding the top laptops and fetching their information) is shared between all
+/// strategies. strategies must be `Sync` since laptops may be scored in parallel.
+pub trait SelectionStrategy: Sync {
+    /// the name used to refer to this strategy in requests and responses
+    fn name(&self) -> &'static str;
+
+    /// calculates the total score of a laptop given the user's category weights
+    fn total_score(&self, laptop: &LaptopRow, weights: &CategoryWeights) -> f32;
+
+    /// the SQL expression that calculates the same total score as `total_score`, used for ranking the
+    /// laptops inside the database. the expression can refer to the `weighted_sum` of the laptop's
+    /// scores in categories, as a double precision value, and to the laptop's `price`.
+    /// strategies that return `None` can only rank the laptops in process.
+    fn total_score_sql(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// the original ranking formula, the sum of the laptop's scores in categories,
+/// each multiplied by the user's score for that category.
+pub struct WeightedSumStrategy;
+impl WeightedSumStrategy {
+    const NAME: &'static str = "weightedSum";
+}
+impl SelectionStrategy for WeightedSumStrategy {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+    fn total_score(&self, laptop: &LaptopRow, weights: &CategoryWeights) -> f32 {
+        weights.weighted_sum(laptop.scores) as f32
+    }
+    fn total_score_sql(&self) -> Option<&'static str> {
+        Some("weighted_sum")
+    }
+}
+
+/// favors cheaper laptops by dividing the weighted sum by the square root of the price.
+/// the square root is used to dampen the effect of the price, otherwise the cheapest
+/// laptops would always win regardless of their performance.
+pub struct ValueForMoneyStrategy;
+impl ValueForMoneyStrategy {
+    const NAME: &'static str = "valueForMoney";
+}
+impl SelectionStrategy for ValueForMoneyStrategy {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+    fn total_score(&self, laptop: &LaptopRow, weights: &CategoryWeights) -> f32 {
+        let weighted_sum = weights.weighted_sum(laptop.scores);
+
+        // make sure we don't divide by zero if a laptop has no price
+        if laptop.price <= 0.0 {
+            return weighted_sum as f32;
+        }
+        (weighted_sum / (laptop.price as f64).sqrt()) as f32
+    }
+    fn total_score_sql(&self) -> Option<&'static str> {
+        Some(
+            "CASE WHEN price <= 0 THEN weighted_sum \
+            ELSE weighted_sum / SQRT(CAST(price AS DOUBLE PRECISION)) END",
+        )
+    }
+}
+
+/// all strategies that can be used for selection, mapped by name, along with the
+/// a/b split used to assign clients to strategies.
+pub struct StrategyRegistry {
+    strategies: HashMap<&'static str, Box<dyn SelectionStrategy>>,
+    ab_split: Option<AbSplit>,
+}
+impl StrategyRegistry {
+    /// creates a registry containing all built in strategies, without an a/b split
+    pub fn with_builtin_strategies() -> Self {
+        let mut registry = Self {
+            strategies: HashMap::new(),
+            ab_split: None,
+        };
+        registry.register(Box::new(WeightedSumStrategy));
+        registry.register(Box::new(ValueForMoneyStrategy));
+        registry
+    }
+
+    /// adds a strategy to the registry, replacing any strategy with the same name
+    pub fn register(&mut self, strategy: Box<dyn SelectionStrategy>) {
+        self.strategies.insert(strategy.name(), strategy);
+    }
+
+    /// sets the a/b split used to assign clients that don't request a specific strategy.
+    /// fails if the split refers to a strategy that isn't registered.
+    pub fn set_ab_split(&mut self, ab_split: AbSplit) -> Result<()> {
+        if let Some(unknown_strategy_name) = ab_split
+            .strategy_names()
+            .find(|strategy_name| !self.strategies.contains_key(strategy_name))
+        {
+            return Err(
+                SelectorErrorKind::UnknownStrategy(unknown_strategy_name.to_string())
+                    .into_empty_selector_error(),
+            );
+        }
+        self.ab_split = Some(ab_split);
+        Ok(())
+    }
+
+    /// finds the strategy that should handle a request.
+    /// a strategy explicitly named in the request always wins. otherwise, if an a/b split
+    /// is configured and the request has a client id, the client's bucket decides the strategy,
+    /// and if neither is available the default strategy is used.
+    pub fn resolve(
+        &self,
+        requested_strategy: Option<&str>,
+        client_id: Option<&str>,
+    ) -> Result<&dyn SelectionStrategy> {
+        let strategy_name = match (requested_strategy, client_id, &self.ab_split) {
+            (Some(requested_strategy), _, _) => requested_strategy,
+            (None, Some(client_id), Some(ab_split)) => ab_split.strategy_for_client(client_id),
+            _ => DEFAULT_STRATEGY_NAME,
+        };
+        match self.strategies.get(strategy_name) {
+            Some(strategy) => Ok(strategy.as_ref()),
+            None => Err(
+                SelectorErrorKind::UnknownStrategy(strategy_name.to_string())
+                    .into_empty_selector_error(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved_name(
+        registry: &StrategyRegistry,
+        requested_strategy: Option<&str>,
+        client_id: Option<&str>,
+    ) -> &'static str {
+        registry.resolve(requested_strategy, client_id).unwrap().name()
+    }
+
+    #[test]
+    fn a_requested_strategy_takes_precedence_over_the_ab_split() {
+        let mut registry = StrategyRegistry::with_builtin_strategies();
+        registry
+            .set_ab_split(AbSplit::parse("valueForMoney=1").unwrap())
+            .unwrap();
+
+        assert_eq!(resolved_name(&registry, Some("weightedSum"), Some("client")), "weightedSum");
+        assert_eq!(resolved_name(&registry, None, Some("client")), "valueForMoney");
+        // a client without an id can't be assigned a bucket
+        assert_eq!(resolved_name(&registry, None, None), DEFAULT_STRATEGY_NAME);
+    }
+
+    #[test]
+    fn without_an_ab_split_the_default_strategy_is_used() {
+        let registry = StrategyRegistry::with_builtin_strategies();
+        assert_eq!(resolved_name(&registry, None, Some("client")), DEFAULT_STRATEGY_NAME);
+        assert_eq!(resolved_name(&registry, Some("valueForMoney"), None), "valueForMoney");
+    }
+
+    #[test]
+    fn unknown_strategies_are_rejected() {
+        let mut registry = StrategyRegistry::with_builtin_strategies();
+        let error = registry
+            .set_ab_split(AbSplit::parse("weightedSum=1,unknown=1").unwrap())
+            .unwrap_err();
+        assert_eq!(error.kind, SelectorErrorKind::UnknownStrategy("unknown".to_string()));
+
+        let error = registry.resolve(Some("unknown"), None).err().unwrap();
+        assert_eq!(error.kind, SelectorErrorKind::UnknownStrategy("unknown".to_string()));
+    }
+}
@@ -1,160 +1,295 @@
-use std::collections::HashMap;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+};
 
 use super::{
-    scores_in_categories_of_laptops::{
-        MappedScoresInCategoriesOfLaptops, ScoresInCategoriesOfLaptop,
-    },
-    user_category_scores::UserCategoryScoresById,
+    preferences::PreferenceAdjustment,
+    ranking_criteria::RankingCriteria,
+    score_matrix::{LaptopRow, LaptopScoreMatrix, ScoresInCategoriesOfLaptop},
+    stability::LaptopStability,
 };
-use crate::errors::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 struct TopLaptopsEntry {
+    /// the index of the laptop's row in the score matrix
+    row_index: usize,
     laptop_id: i32,
     price: f32,
     score: f32,
-    scores_in_categories: ScoresInCategoriesOfLaptop,
+}
+impl TopLaptopsEntry {
+    /// compares the entries such that the better laptop is the greater one. this is a total
+    /// order, so identical requests always produce identical rankings:
+    /// 1. a laptop with a higher score is better.
+    /// 2. if the scores are equal, the laptop with the lower price is better.
+    /// 3. if the prices are equal too, the laptop with the lower id is better. this is also the
+    ///    order used by the database backend, so both backends produce the same ranking.
+    fn compare(&self, other: &Self) -> Ordering {
+        ranking_key(self.score)
+            .total_cmp(&ranking_key(other.score))
+            .then_with(|| ranking_key(other.price).total_cmp(&ranking_key(self.price)))
+            .then_with(|| other.laptop_id.cmp(&self.laptop_id))
+    }
+}
+
+/// converts a score or a price to a value that can be totally ordered in the same way that the
+/// database orders it. negative zero is considered equal to zero, and NaN, which could only come
+/// from a bug in a strategy since the scores are validated when they are loaded, is considered
+/// lower than any other value so that it never ranks first.
+fn ranking_key(value: f32) -> f32 {
+    if value.is_nan() {
+        f32::NEG_INFINITY
+    } else {
+        // adding zero turns negative zero into positive zero
+        value + 0.0
+    }
+}
+impl PartialEq for TopLaptopsEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.compare(other) == Ordering::Equal
+    }
+}
+impl Eq for TopLaptopsEntry {}
+impl PartialOrd for TopLaptopsEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for TopLaptopsEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(other)
+    }
 }
 
 /// a struct used for finding the top N laptops, given some laptops amount N
 #[derive(Debug)]
 pub struct TopLaptops {
-    /// the entries in this vector are ordered from best to worst, such that
-    /// the best laptop is at index 0
-    top_laptops: Vec<TopLaptopsEntry>,
+    /// a bounded min-heap of the best laptops found so far, such that the worst of the
+    /// top laptops is at the top of the heap, and can be replaced in logarithmic time
+    /// when a better laptop is found.
+    top_laptops: BinaryHeap<Reverse<TopLaptopsEntry>>,
     amount: usize,
+    /// when the laptops are grouped by model family, the variants of each family, from best to
+    /// worst, mapped by the family's index. empty if the laptops are not grouped.
+    variants_by_family: HashMap<usize, Vec<TopLaptopsEntry>>,
 }
 
 impl TopLaptops {
     pub fn new(amount: usize) -> Self {
         Self {
-            top_laptops: Vec::with_capacity(amount),
+            // one extra slot is reserved for the laptop that is pushed right before the worst
+            // laptop is popped
+            top_laptops: BinaryHeap::with_capacity(amount + 1),
             amount,
+            variants_by_family: HashMap::new(),
         }
     }
 
+    /// finds the top laptops out of all laptops in the score matrix that are accepted by the given
+    /// criteria, ranked using the given criteria
     pub fn find_top_laptops(
         &mut self,
-        user_category_scores: &UserCategoryScoresById,
-        scores_in_categories_of_laptops: &MappedScoresInCategoriesOfLaptops,
-        laptop_prices: &HashMap<i32, f32>,
-    ) -> Result<()> {
-        for laptop_with_scores in scores_in_categories_of_laptops.iter() {
-            let total_score = laptop_with_scores.calculate_total_score(user_category_scores)?;
-            let price = laptop_prices.get(&laptop_with_scores.laptop_id()).unwrap();
-            self.update(
-                laptop_with_scores.laptop_id(),
-                *price,
-                total_score,
-                laptop_with_scores.scores_in_categories().clone(),
-            );
+        criteria: &RankingCriteria,
+        score_matrix: &LaptopScoreMatrix,
+    ) {
+        #[cfg(feature = "parallel")]
+        {
+            *self = Self::find_top_laptops_in_parallel(self.amount, criteria, score_matrix);
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        for row in score_matrix.rows().filter(|row| criteria.accepts(row)) {
+            self.update(row, criteria.score(&row));
         }
-        Ok(())
     }
 
-    /// update the top laptops with a new laptop.
-    /// if this laptop is better than any of the laptops currently in the
-    /// list, it will be inserted before it.
-    pub fn update(
+    /// scores the rows of the score matrix on all cores. each thread finds the top laptops of the
+    /// rows that it scored, and the partial results are then merged together.
+    #[cfg(feature = "parallel")]
+    fn find_top_laptops_in_parallel(
+        amount: usize,
+        criteria: &RankingCriteria,
+        score_matrix: &LaptopScoreMatrix,
+    ) -> Self {
+        use rayon::prelude::*;
+
+        (0..score_matrix.laptops_amount())
+            .into_par_iter()
+            .fold(
+                || Self::new(amount),
+                |mut top_laptops, row_index| {
+                    let row = score_matrix.row(row_index);
+                    if criteria.accepts(&row) {
+                        top_laptops.update(row, criteria.score(&row));
+                    }
+                    top_laptops
+                },
+            )
+            .reduce(
+                || Self::new(amount),
+                |mut top_laptops, other| {
+                    top_laptops.merge(other);
+                    top_laptops
+                },
+            )
+    }
+
+    /// finds the top model families out of all laptops in the score matrix that are accepted by the
+    /// given criteria. each family is ranked by its best variant, and all of its other accepted
+    /// variants are kept so that they could be listed along with it. the families of the laptops
+    /// must be loaded into the score matrix.
+    pub fn find_top_model_families(
         &mut self,
-        laptop_id: i32,
-        price: f32,
-        score: f32,
-        scores_in_categories: ScoresInCategoriesOfLaptop,
+        criteria: &RankingCriteria,
+        score_matrix: &LaptopScoreMatrix,
     ) {
-        let mut was_better_than_any_of_top_laptops = false;
-        // start from the best laptop, and go down the list, each time checking if the new
-        // laptop is better than any laptop that is already in the top laptops list
-        for i in 0..self.top_laptops.len() {
-            // if the new laptop is better than some laptop in our current top laptops, insert the
-            // new laptop right before him.
-            if score > self.top_laptops[i].score
-                || (score == self.top_laptops[i].score && price < self.top_laptops[i].price)
-            {
-                self.insert_laptop_at(i, laptop_id, price, score, scores_in_categories.clone());
-
-                // the laptop was better than one of the current top laptops
-                was_better_than_any_of_top_laptops = true;
-
-                // break from the loop to make sure we don't insert the new laptop multiple times,
-                // since if it is better than the current laptop, it must be better than the laptops
-                // after it, due to the order in which the laptops in the top_laptops vector are ordered
-                break;
+        for row in score_matrix.rows().filter(|row| criteria.accepts(row)) {
+            let entry = TopLaptopsEntry {
+                row_index: row.index,
+                laptop_id: row.laptop_id,
+                price: row.price,
+                score: criteria.score(&row),
+            };
+            // laptops without a family are treated as families of their own
+            let family = row.family.unwrap_or(row.index);
+            self.variants_by_family.entry(family).or_default().push(entry);
+        }
+
+        for variants in self.variants_by_family.values_mut() {
+            // sort the variants from best to worst, so that the best variant is first
+            variants.sort_unstable_by(|variant1, variant2| variant2.cmp(variant1));
+            self.top_laptops.push(Reverse(variants[0]));
+            if self.top_laptops.len() > self.amount {
+                self.top_laptops.pop();
             }
         }
+    }
 
-        // if the new laptop isn't better than any of our current top laptops,
-        // but we don't yet have the required amount of laptops, add the new laptop as the last
-        // laptop, namely the worst one
-        if !was_better_than_any_of_top_laptops && self.top_laptops.len() < self.amount {
-            self.top_laptops.push(TopLaptopsEntry {
-                laptop_id,
-                price,
-                score,
-                scores_in_categories,
-            })
+    /// update the top laptops with a new laptop.
+    /// if this laptop is better than the worst of the top laptops, it replaces it.
+    pub fn update(&mut self, row: LaptopRow, score: f32) {
+        self.insert(TopLaptopsEntry {
+            row_index: row.index,
+            laptop_id: row.laptop_id,
+            price: row.price,
+            score,
+        });
+    }
+
+    /// adds all laptops of another top laptops struct to this one
+    #[cfg(feature = "parallel")]
+    fn merge(&mut self, other: Self) {
+        for Reverse(entry) in other.top_laptops {
+            self.insert(entry);
         }
     }
 
-    /// inserts a laptop at a specific index, while maintaining a correct length
-    /// for the top_laptops vector
-    fn insert_laptop_at(
-        &mut self,
-        index: usize,
-        laptop_id: i32,
-        price: f32,
-        score: f32,
-        scores_in_categories: ScoresInCategoriesOfLaptop,
-    ) {
-        // if the index is the last index in the top_laptops vector
-        // we don't need to perform any more modification to the top_laptops vector other
-        // than just updating the last entry according to the new laptop's information.
-        //
-        // note that this check is required, and if there was no check for this case, the insertion performed
-        // in case this is not true would fail, since we would be deleting the last element,
-        // and then trying to insert an element in place of it, which would case an error,
-        // since it no longer exists.
-        if index + 1 == self.amount {
-            self.top_laptops[index] = TopLaptopsEntry {
-                laptop_id,
-                price,
-                score,
-                scores_in_categories,
-            };
+    fn insert(&mut self, entry: TopLaptopsEntry) {
+        // if we don't yet have the required amount of laptops, the laptop is always added
+        if self.top_laptops.len() < self.amount {
+            self.top_laptops.push(Reverse(entry));
             return;
         }
 
-        // if we already filled the top_laptops vector with enough laptops, and we now want to
-        // insert a new laptop, we must delete the last laptop to not overflow the required amount
-        // of top laptops
-        if self.top_laptops.len() == self.amount {
-            self.top_laptops.pop();
+        // otherwise, only add the laptop if it is better than the worst of the top laptops,
+        // and remove the worst laptop to not overflow the required amount of top laptops
+        if let Some(Reverse(worst_entry)) = self.top_laptops.peek() {
+            if entry > *worst_entry {
+                self.top_laptops.push(Reverse(entry));
+                self.top_laptops.pop();
+            }
         }
-
-        self.top_laptops.insert(
-            index,
-            TopLaptopsEntry {
-                laptop_id,
-                price,
-                score,
-                scores_in_categories,
-            },
-        )
     }
 
-    /// returns the laptop ids of the top laptops, in order
-    pub fn laptop_ids(&self) -> Vec<i32> {
+    /// returns the indices of the rows of the top laptops in the score matrix, from best to worst
+    pub fn into_row_indices(self) -> Vec<usize> {
         self.top_laptops
-            .iter()
-            .map(|entry| entry.laptop_id)
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(entry)| entry.row_index)
             .collect()
     }
 
-    /// returns a map the maps each laptop id to its score
-    pub fn laptop_id_to_score_map(self) -> HashMap<i32, (f32, ScoresInCategoriesOfLaptop)> {
-        self.top_laptops
+    /// returns the top laptops, from best to worst, materializing the scores in categories, their
+    /// confidences, the preference adjustments and the variants of only these laptops.
+    pub fn into_ranked_laptops(
+        self,
+        criteria: &RankingCriteria,
+        score_matrix: &LaptopScoreMatrix,
+    ) -> Vec<RankedLaptop> {
+        let Self {
+            top_laptops,
+            variants_by_family,
+            ..
+        } = self;
+
+        // sorting the reversed entries in ascending order puts the best laptop first
+        top_laptops
+            .into_sorted_vec()
             .into_iter()
-            .map(|entry| (entry.laptop_id, (entry.score, entry.scores_in_categories)))
+            .map(|Reverse(entry)| {
+                let row = score_matrix.row(entry.row_index);
+                let mut ranked_laptop =
+                    RankedLaptop::from_row(&row, entry.score, criteria, score_matrix);
+                if let Some(variants) = variants_by_family.get(&row.family.unwrap_or(row.index)) {
+                    // the first variant is the ranked laptop itself
+                    ranked_laptop.variants = variants[1..]
+                        .iter()
+                        .map(|variant| RankedVariant {
+                            laptop_id: variant.laptop_id,
+                            score: variant.score,
+                        })
+                        .collect();
+                }
+                ranked_laptop
+            })
             .collect()
     }
 }
+
+/// one of the top laptops found by the selection
+#[derive(Debug)]
+pub struct RankedLaptop {
+    pub laptop_id: i32,
+    pub score: f32,
+    pub scores_in_categories: ScoresInCategoriesOfLaptop,
+    pub confidence: f32,
+    pub confidence_in_categories: HashMap<i32, f32>,
+    pub preference_adjustments: Vec<PreferenceAdjustment>,
+    /// the other variants of the laptop's model family, from best to worst, when the laptops
+    /// are grouped by model family
+    pub variants: Vec<RankedVariant>,
+    /// how stable the laptop's position is when the category scores are perturbed, if a stability
+    /// analysis was requested
+    pub stability: Option<LaptopStability>,
+}
+impl RankedLaptop {
+    /// materializes the scores in categories, their confidences and the preference adjustments
+    /// of the laptop in the given row, which was given the given score. this should only be used
+    /// for the laptops that are actually returned to the user.
+    pub fn from_row(
+        row: &LaptopRow,
+        score: f32,
+        criteria: &RankingCriteria,
+        score_matrix: &LaptopScoreMatrix,
+    ) -> Self {
+        Self {
+            laptop_id: row.laptop_id,
+            score,
+            scores_in_categories: score_matrix.scores_in_categories_of(row.index),
+            confidence: criteria.confidence(row),
+            confidence_in_categories: score_matrix.confidences_in_categories_of(row.index),
+            preference_adjustments: criteria.preference_adjustments(row),
+            variants: Vec::new(),
+            stability: None,
+        }
+    }
+}
+
+/// a variant of the model family of one of the top laptops
+#[derive(Debug)]
+pub struct RankedVariant {
+    pub laptop_id: i32,
+    pub score: f32,
+}
@@ -0,0 +1,451 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bigdecimal::ToPrimitive;
+use db_access::{models, schema};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, TextExpressionMethods};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    currency::Currency,
+    preferences::SoftPreferences,
+    ranking_criteria::RankingCriteria,
+    score_matrix::LaptopScoreMatrix,
+    strategies::WeightedSumStrategy,
+    top_laptops::RankedLaptop,
+    user_category_scores::UserCategoryScoresByName,
+    TOP_LAPTOPS_AMOUNT,
+};
+use crate::{
+    errors::*,
+    fetch_data::{FetchData, SelectedLaptop},
+    SelectorDBConnection,
+};
+
+sql_function! {
+    /// converts the text to lowercase
+    fn lower(text: diesel::sql_types::Text) -> diesel::sql_types::Text;
+}
+
+/// the laptop that the user currently has
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CurrentLaptop {
+    /// a laptop from the catalog, identified by its name
+    Named { name: String },
+    /// a laptop identified only by its cpu and gpu, which might not be in the catalog
+    Components { cpu: String, gpu: String },
+}
+
+/// the parameters of a request for upgrade recommendations
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeParameters {
+    pub current_laptop: CurrentLaptop,
+
+    /// the names of the categories that the user cares about
+    pub categories: Vec<String>,
+
+    /// the minimum improvement over the current laptop, in percents, that a laptop must
+    /// have in each of the categories in order to be recommended
+    pub min_improvement_percent: f32,
+
+    /// the maximum price of the recommended laptops. if not provided, laptops of any price
+    /// are recommended.
+    #[serde(default)]
+    pub max_price: Option<f32>,
+}
+
+/// the laptops that are recommended as upgrades of the user's current laptop
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeRecommendations {
+    /// the scores of the current laptop in the categories that the user cares about
+    pub current_scores_in_categories: BTreeMap<String, f32>,
+    /// the recommended laptops, from the cheapest to the most expensive
+    pub laptops: Vec<UpgradeRecommendation>,
+}
+
+/// a laptop that is recommended as an upgrade
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeRecommendation {
+    /// the smallest improvement over the current laptop, in percents, out of all the categories
+    /// that the user cares about
+    pub improvement_percent: f32,
+    /// the laptop, whose score is the sum of its scores in the categories that the user cares about
+    pub laptop: SelectedLaptop,
+}
+
+/// finds the cheapest laptops that beat the user's current laptop by at least the given
+/// percentage in each of the categories that the user cares about.
+pub fn recommend_upgrades(
+    db_connection: &SelectorDBConnection,
+    parameters: &UpgradeParameters,
+) -> Result<UpgradeRecommendations> {
+    if parameters.categories.is_empty() {
+        return Err(SelectorErrorKind::NoScoresProvided.into_empty_selector_error());
+    }
+    if !parameters.min_improvement_percent.is_finite() || parameters.min_improvement_percent < 0.0 {
+        return Err(SelectorErrorKind::InvalidMinImprovement.into_empty_selector_error());
+    }
+    if parameters.max_price.is_some_and(f32::is_nan) {
+        return Err(SelectorErrorKind::InvalidMaxPrice.into_empty_selector_error());
+    }
+
+    // the categories that the user cares about are given equal weights, which are used for
+    // calculating the confidence of the recommended laptops
+    let user_category_scores = UserCategoryScoresByName::from(
+        parameters
+            .categories
+            .iter()
+            .map(|category_name| (category_name.clone(), 1.0))
+            .collect::<HashMap<String, f32>>(),
+    );
+    let user_category_scores_by_id =
+        user_category_scores.remap_key_to_category_id(db_connection)?;
+
+    let (current_laptop_id, current_scores_by_category_id) = match &parameters.current_laptop {
+        CurrentLaptop::Named { name } => {
+            let laptop_id = fetch_laptop_id_by_name(db_connection, name)?;
+            let scores_in_categories = db_connection
+                .fetch_laptop_scores_in_categories_of_laptops(&[laptop_id])?
+                .into_iter()
+                .map(|score_in_category| (score_in_category.category_id, score_in_category.score))
+                .collect();
+            (Some(laptop_id), scores_in_categories)
+        }
+        CurrentLaptop::Components { cpu, gpu } => (
+            None,
+            calculate_scores_in_categories_of_components(db_connection, cpu, gpu)?,
+        ),
+    };
+
+    let max_price = match parameters.max_price {
+        Some(max_price) => max_price,
+        None => db_connection.fetch_price_limits()?.max_price,
+    };
+    let score_matrix = LaptopScoreMatrix::load(db_connection, max_price)?;
+    let weights = user_category_scores_by_id.to_weights(score_matrix.category_ids());
+    let no_preferences = SoftPreferences::default();
+    let currency = Currency::base(db_connection)?;
+    let criteria = RankingCriteria {
+        strategy: &WeightedSumStrategy,
+        weights: &weights,
+        preferences: &no_preferences,
+        currency: &currency,
+        min_confidence: 0.0,
+    };
+
+    // the columns of the categories that the user cares about, along with the score that the
+    // current laptop has in each of them. categories where the current laptop has no score are
+    // skipped, since there is nothing to compare the other laptops with.
+    let mut current_scores_in_columns = Vec::new();
+    for (column, category_id) in score_matrix.category_ids().iter().enumerate() {
+        if user_category_scores_by_id.iter().any(|(id, _)| id == category_id) {
+            if let Some(&current_score) = current_scores_by_category_id.get(category_id) {
+                current_scores_in_columns.push((column, current_score));
+            }
+        }
+    }
+    if current_scores_in_columns.is_empty() {
+        return Err(SelectorErrorKind::CurrentLaptopHasNoScores.into_empty_selector_error());
+    }
+
+    let min_ratio = 1.0 + parameters.min_improvement_percent / 100.0;
+    let mut upgrades = Vec::new();
+    for row in score_matrix.rows() {
+        if Some(row.laptop_id) == current_laptop_id {
+            continue;
+        }
+        let beats_current_laptop = current_scores_in_columns
+            .iter()
+            .all(|&(column, current_score)| row.scores[column] >= current_score * min_ratio);
+        if beats_current_laptop {
+            // categories where the current laptop has no score can't be improved by a percentage
+            let improvement_percent = current_scores_in_columns
+                .iter()
+                .filter(|(_, current_score)| *current_score > 0.0)
+                .map(|&(column, current_score)| (row.scores[column] / current_score - 1.0) * 100.0)
+                .fold(None, |min: Option<f32>, improvement| {
+                    Some(min.map_or(improvement, |min| min.min(improvement)))
+                })
+                .unwrap_or(0.0);
+            upgrades.push((row, improvement_percent));
+        }
+    }
+
+    // keep only the cheapest upgrades. upgrades with the same price are ordered by laptop id
+    // so that the result is deterministic.
+    upgrades.sort_unstable_by(|(row1, _), (row2, _)| {
+        row1.price
+            .total_cmp(&row2.price)
+            .then_with(|| row1.laptop_id.cmp(&row2.laptop_id))
+    });
+    upgrades.truncate(TOP_LAPTOPS_AMOUNT);
+    let ranked_laptops: Vec<RankedLaptop> = upgrades
+        .iter()
+        .map(|(row, _)| RankedLaptop::from_row(row, criteria.score(row), &criteria, &score_matrix))
+        .collect();
+
+    let category_id_to_name_map = db_connection.fetch_category_names()?;
+    let laptops = db_connection.fetch_selected_laptops(
+        ranked_laptops,
+        &category_id_to_name_map,
+        &currency,
+    )?;
+    Ok(UpgradeRecommendations {
+        current_scores_in_categories: current_scores_in_columns
+            .iter()
+            .map(|&(column, current_score)| {
+                let category_id = score_matrix.category_ids()[column];
+                (category_id_to_name_map[&category_id].clone(), current_score)
+            })
+            .collect(),
+        // the selected laptops keep the order of the upgrades
+        laptops: laptops
+            .into_iter()
+            .zip(upgrades)
+            .map(|(laptop, (_, improvement_percent))| UpgradeRecommendation {
+                improvement_percent,
+                laptop,
+            })
+            .collect(),
+    })
+}
+
+/// finds the id of the laptop with the given name
+fn fetch_laptop_id_by_name(db_connection: &SelectorDBConnection, name: &str) -> Result<i32> {
+    use schema::laptop;
+
+    let laptop_ids: Vec<i32> = laptop::table
+        .filter(laptop::name.eq(name.trim()))
+        .select(laptop::id)
+        .load(&db_connection.0)
+        .into_selector_result(SelectorErrorKind::DatabaseError)?;
+    match laptop_ids.first() {
+        Some(&laptop_id) => Ok(laptop_id),
+        None => Err(SelectorErrorKind::UnknownLaptop(name.to_string()).into_empty_selector_error()),
+    }
+}
+
+/// calculates the scores in categories of a laptop with the given cpu and gpu, using the same formula
+/// that the data processor uses for the laptops in the catalog. the scores of the cpu and the gpu in
+/// each benchmark are the scores that were recorded for laptops with the same cpu or gpu, and
+/// benchmarks that were never recorded for them are given the average score of the benchmark.
+fn calculate_scores_in_categories_of_components(
+    db_connection: &SelectorDBConnection,
+    cpu: &str,
+    gpu: &str,
+) -> Result<HashMap<i32, f32>> {
+    let mut scores_by_global_benchmark_id =
+        fetch_pu_benchmark_scores(db_connection, models::PuType::Cpu, cpu)?.ok_or_else(|| {
+            SelectorErrorKind::UnknownCpu(cpu.to_string()).into_empty_selector_error()
+        })?;
+    let gpu_scores_by_global_benchmark_id =
+        fetch_pu_benchmark_scores(db_connection, models::PuType::Gpu, gpu)?.ok_or_else(|| {
+            SelectorErrorKind::UnknownGpu(gpu.to_string()).into_empty_selector_error()
+        })?;
+    scores_by_global_benchmark_id.extend(gpu_scores_by_global_benchmark_id);
+
+    let global_benchmarks: HashMap<i32, models::GlobalBenchmark> = {
+        use schema::global_benchmark::dsl::*;
+        global_benchmark
+            .load::<models::GlobalBenchmark>(&db_connection.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)?
+            .into_iter()
+            .map(|benchmark| (benchmark.id, benchmark))
+            .collect()
+    };
+    let benchmark_scores_in_categories: Vec<models::BenchmarkScoreInCategory> = {
+        use schema::benchmark_score_in_category::dsl::*;
+        benchmark_score_in_category
+            .load(&db_connection.0)
+            .into_selector_result(SelectorErrorKind::DatabaseError)?
+    };
+
+    let mut scores_in_categories = HashMap::new();
+    for benchmark_score_in_category in benchmark_scores_in_categories {
+        let global_benchmark = match global_benchmarks.get(&benchmark_score_in_category.global_benchmark_id) {
+            Some(global_benchmark) => global_benchmark,
+            None => continue,
+        };
+        let score_in_benchmark = match scores_by_global_benchmark_id.get(&global_benchmark.id) {
+            Some(&score) => score,
+            None => average_score_of(global_benchmark),
+        };
+
+        // when calculating the normalized score, make sure we don't divide by zero
+        let normalized_score_in_benchmark = if global_benchmark.max == 0.0 {
+            0.0
+        } else {
+            score_in_benchmark / global_benchmark.max
+        };
+        *scores_in_categories
+            .entry(benchmark_score_in_category.category_id)
+            .or_insert(0.0) += normalized_score_in_benchmark * benchmark_score_in_category.score;
+    }
+    Ok(scores_in_categories)
+}
+
+/// the average score of all laptops in the given global benchmark
+fn average_score_of(global_benchmark: &models::GlobalBenchmark) -> f32 {
+    if global_benchmark.amount == 0 {
+        return 0.0;
+    }
+    (&global_benchmark.sum / global_benchmark.amount)
+        .to_f32()
+        .unwrap_or(0.0)
+}
+
+/// loads the scores of the cpu or gpu with the given name in each of its benchmarks, mapped by
+/// global benchmark id. the scores are taken from all laptops with this cpu or gpu, and averaged
+/// in case different laptops recorded different scores. the name is compared case insensitively,
+/// since stores don't write it consistently. returns `None` if no laptop in the catalog has this
+/// cpu or gpu, in which case there is nothing to score it by.
+fn fetch_pu_benchmark_scores(
+    db_connection: &SelectorDBConnection,
+    pu_type: models::PuType,
+    pu_name: &str,
+) -> Result<Option<HashMap<i32, f32>>> {
+    use schema::{benchmark, global_benchmark, laptop};
+
+    let pu_name = pu_name.trim().to_lowercase();
+    let laptops = laptop::table.select(laptop::id);
+    let laptop_ids: Vec<i32> = match pu_type {
+        models::PuType::Cpu => laptops.filter(lower(laptop::cpu).eq(&pu_name)).load(&db_connection.0),
+        models::PuType::Gpu => laptops.filter(lower(laptop::gpu).eq(&pu_name)).load(&db_connection.0),
+    }
+    .into_selector_result(SelectorErrorKind::DatabaseError)?;
+    if laptop_ids.is_empty() {
+        return Ok(None);
+    }
+
+    // the pu type of a global benchmark is encoded in the first character of its name
+    let global_benchmark_name_pattern = match pu_type {
+        models::PuType::Cpu => "c%",
+        models::PuType::Gpu => "g%",
+    };
+    let scores: Vec<(i32, f32)> = benchmark::table
+        .inner_join(global_benchmark::table)
+        .filter(benchmark::laptop_id.eq_any(&laptop_ids))
+        .filter(global_benchmark::name.like(global_benchmark_name_pattern))
+        .select((benchmark::global_benchmark_id, benchmark::score))
+        .load(&db_connection.0)
+        .into_selector_result(SelectorErrorKind::DatabaseError)?;
+
+    let mut sums_and_amounts: HashMap<i32, (f64, u32)> = HashMap::new();
+    for (global_benchmark_id, score) in scores {
+        let (sum, amount) = sums_and_amounts.entry(global_benchmark_id).or_default();
+        *sum += score as f64;
+        *amount += 1;
+    }
+    Ok(Some(
+        sums_and_amounts
+            .into_iter()
+            .map(|(global_benchmark_id, (sum, amount))| (global_benchmark_id, (sum / amount as f64) as f32))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_database::{connect_to_test_dataset, execute, insert_laptop, FixtureLaptop};
+
+    /// creates a dataset where the gaming category is made of a cpu and a gpu benchmark, and the
+    /// study category has no benchmarks at all, so no cpu and gpu have a score in it
+    fn connect_to_dataset_with_benchmarks() -> SelectorDBConnection {
+        let db_connection = connect_to_test_dataset(&["gaming", "study"]);
+        let laptops = [
+            FixtureLaptop {
+                cpu: "Intel i5",
+                gpu: "Iris",
+                ..FixtureLaptop::new(1, 3000.0, &[(0.45, 1.0), (1.0, 1.0)])
+            },
+            FixtureLaptop::new(2, 2000.0, &[(0.9, 1.0), (0.1, 1.0)]),
+            FixtureLaptop::new(3, 1500.0, &[(0.5, 1.0), (0.1, 1.0)]),
+            FixtureLaptop::new(4, 1000.0, &[(0.46, 1.0), (5.0, 1.0)]),
+        ];
+        for laptop in &laptops {
+            insert_laptop(&db_connection, laptop);
+        }
+        execute(
+            &db_connection,
+            "INSERT INTO global_benchmark (id, name, max, sum, amount) \
+            VALUES (1, 'cCinebench', 100, 100, 2), (2, 'g3DMark', 200, 300, 2)",
+        );
+        execute(
+            &db_connection,
+            "INSERT INTO benchmark (score, laptop_id, global_benchmark_id) VALUES (40, 1, 1), (100, 1, 2)",
+        );
+        execute(
+            &db_connection,
+            "INSERT INTO benchmark_score_in_category (score, category_id, global_benchmark_id) \
+            VALUES (0.5, 1, 1), (0.5, 1, 2)",
+        );
+        db_connection
+    }
+
+    fn parameters(json: &str) -> UpgradeParameters {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn components_are_compared_only_in_categories_where_they_have_scores() {
+        let db_connection = connect_to_dataset_with_benchmarks();
+
+        // the components' score in gaming is 40 / 100 * 0.5 + 100 / 200 * 0.5 = 0.45
+        let recommendations = recommend_upgrades(
+            &db_connection,
+            &parameters(
+                r#"{"currentLaptop": {"cpu": " intel I5 ", "gpu": "IRIS"}, "categories": ["gaming", "study"],
+                "minImprovementPercent": 10}"#,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(recommendations.current_scores_in_categories.len(), 1);
+        assert!((recommendations.current_scores_in_categories["gaming"] - 0.45).abs() < 1e-6);
+        let laptop_ids: Vec<i32> = recommendations
+            .laptops
+            .iter()
+            .map(|recommendation| recommendation.laptop.id)
+            .collect();
+        assert_eq!(laptop_ids, [3, 2]);
+
+        // the improvement only considers gaming, while the score sums both categories
+        let cheapest_upgrade = &recommendations.laptops[0];
+        assert!((cheapest_upgrade.improvement_percent - 100.0 / 9.0).abs() < 1e-3);
+        assert!((cheapest_upgrade.laptop.score - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unknown_components_are_reported() {
+        let db_connection = connect_to_dataset_with_benchmarks();
+
+        let error = recommend_upgrades(
+            &db_connection,
+            &parameters(
+                r#"{"currentLaptop": {"cpu": "Intel i5", "gpu": "Radeon"}, "categories": ["gaming"],
+                "minImprovementPercent": 10}"#,
+            ),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind, SelectorErrorKind::UnknownGpu("Radeon".to_string()));
+    }
+
+    #[test]
+    fn a_current_laptop_without_scores_is_reported() {
+        let db_connection = connect_to_dataset_with_benchmarks();
+
+        let error = recommend_upgrades(
+            &db_connection,
+            &parameters(
+                r#"{"currentLaptop": {"cpu": "Intel i5", "gpu": "Iris"}, "categories": ["study"],
+                "minImprovementPercent": 10}"#,
+            ),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind, SelectorErrorKind::CurrentLaptopHasNoScores);
+    }
+}
@@ -6,6 +6,11 @@ use crate::{errors::*, fetch_data::FetchData, SelectorDBConnection};
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct UserCategoryScoresByName(HashMap<String, f32>);
+impl From<HashMap<String, f32>> for UserCategoryScoresByName {
+    fn from(category_scores: HashMap<String, f32>) -> Self {
+        Self(category_scores)
+    }
+}
 impl UserCategoryScoresByName {
     /// remaps the user category scores to use the category id as the key
     /// instead of the category names
@@ -55,12 +60,81 @@ impl UserCategoryScoresByName {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// makes sure that all category scores are finite and non-negative. if there are multiple
+    /// invalid scores, the category that comes first alphabetically is reported, so that the
+    /// same request always gets the same error.
+    pub fn validate(&self) -> Result<()> {
+        let invalid_category_name = self
+            .0
+            .iter()
+            .filter(|(_, score)| !score.is_finite() || **score < 0.0)
+            .map(|(category_name, _)| category_name)
+            .min();
+        match invalid_category_name {
+            Some(category_name) => Err(SelectorErrorKind::InvalidCategoryScore(
+                category_name.clone(),
+            )
+            .into_empty_selector_error()),
+            None => Ok(()),
+        }
+    }
 }
 
 /// the user's category scores, mapping each category id to its score
 pub struct UserCategoryScoresById(HashMap<i32, f32>);
 impl UserCategoryScoresById {
-    pub fn iter(&self)->std::collections::hash_map::Iter<i32,f32>{
+    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, i32, f32> {
         self.0.iter()
     }
+
+    /// arranges the user category scores in the order of the given category ids, which are
+    /// the columns of a laptop score matrix. categories that the user has not selected a
+    /// score for get a weight of 0.
+    pub fn to_weights(&self, category_ids: &[i32]) -> CategoryWeights {
+        CategoryWeights(
+            category_ids
+                .iter()
+                .map(|category_id| self.0.get(category_id).copied().unwrap_or(0.0))
+                .collect(),
+        )
+    }
+}
+
+/// the user's category scores, in the order of the columns of a laptop score matrix
+#[derive(Debug)]
+pub struct CategoryWeights(Vec<f32>);
+impl CategoryWeights {
+    /// calculates the sum of the given scores in categories, each multiplied by the weight
+    /// that the user has chosen for its category. the scores must be in the order of the
+    /// columns of the matrix that these weights were created for.
+    ///
+    /// the sum is calculated in double precision, like the database side ranking does. the rounding
+    /// error of a double precision sum is small enough that, once converted to single precision, the
+    /// result almost always matches the database's result, even though the database may sum the
+    /// scores in a different order.
+    pub fn weighted_sum(&self, scores_in_categories: &[f32]) -> f64 {
+        self.0
+            .iter()
+            .zip(scores_in_categories)
+            .map(|(&weight, &score)| weight as f64 * score as f64)
+            .sum()
+    }
+
+    /// creates weights in which each of these weights is multiplied by the next factor returned by
+    /// the given function
+    pub fn perturbed(&self, mut next_factor: impl FnMut() -> f32) -> Self {
+        Self(self.0.iter().map(|&weight| weight * next_factor()).collect())
+    }
+
+    /// calculates the average of the given values in categories, weighted by the user's category
+    /// scores. if the user gave no weight to any category, all values are considered irrelevant
+    /// and the average is 1.
+    pub fn weighted_average(&self, values_in_categories: &[f32]) -> f32 {
+        let total_weight: f64 = self.0.iter().map(|&weight| weight as f64).sum();
+        if total_weight <= 0.0 {
+            return 1.0;
+        }
+        (self.weighted_sum(values_in_categories) / total_weight) as f32
+    }
 }
@@ -1,3 +1,5 @@
+mod result_cache;
+
 use std::{
     io::{Read, Write},
     net::{TcpListener, TcpStream},
@@ -7,21 +9,32 @@ use std::{
 use crate::fetch_data::FetchData;
 use crate::{
     errors::*,
-    selection::{Select, UserCategoryScoresByName},
+    selection::{
+        BudgetCurveParameters, FeedbackParameters, PriceBracketsParameters, RecordFeedback, Select,
+        SelectionParameters, StrategyRegistry, UpgradeParameters,
+    },
     SelectorDBConnection,
 };
+use db_access::dataset_generations::use_dataset_generation;
 use log::{info, error};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use result_cache::ResultCache;
 
 const SERVER_ENDPOINT: &str = "127.0.0.1:4741";
 const BUFFER_SIZE: usize = 16384;
 
-struct ClientHandler<'buf, 'conn> {
+struct ClientHandler<'buf, 'conn, 'reg, 'cache, 'gen> {
     stream: TcpStream,
     buffer: &'buf mut [u8],
     db_connection: &'conn SelectorDBConnection,
+    strategies: &'reg StrategyRegistry,
+    cache: &'cache mut ResultCache,
+    /// the generation of the dataset that the database connection currently uses
+    followed_generation: &'gen mut Option<i64>,
 }
-impl<'buf, 'conn> ClientHandler<'buf, 'conn> {
+impl<'buf, 'conn, 'reg, 'cache, 'gen> ClientHandler<'buf, 'conn, 'reg, 'cache, 'gen> {
     fn handle_client(&mut self) -> Result<()> {
         loop {
             if let Err(e) = self.handle_client_request() {
@@ -39,6 +52,9 @@ impl<'buf, 'conn> ClientHandler<'buf, 'conn> {
                 let response: SelectorResponse<()> = SelectorResponse {
                     success: false,
                     content: None,
+                    strategy: None,
+                    stability_score: None,
+                    error: Some(format!("{:?}", e.kind)),
                 };
                 let serialized_response = serde_json::to_vec(&response)
                     .into_selector_result(SelectorErrorKind::FailedToSerializeResponse)?;
@@ -61,16 +77,20 @@ impl<'buf, 'conn> ClientHandler<'buf, 'conn> {
         if length == 0 {
             return Err(SelectorErrorKind::TcpStreamError.into_empty_selector_error());
         }
-        let request: SelectorRequest = serde_json::from_slice(&self.buffer[..length])
+        // the raw request is kept since the cache key is created from it
+        let raw_request: Value = serde_json::from_slice(&self.buffer[..length])
+            .into_selector_result(SelectorErrorKind::FailedToDeserializeClientRequest)?;
+        let request = SelectorRequest::deserialize(&raw_request)
             .into_selector_result(SelectorErrorKind::FailedToDeserializeClientRequest)?;
 
         info!("received request: {:?}", request);
 
         info!("handling request");
 
+        self.follow_active_generation()?;
+
         // serialize and send the response to the client
-        let serialized_response =
-            request.handle_request_and_serialize_response(self.db_connection)?;
+        let serialized_response = self.handle_request_using_cache(&request, &raw_request)?;
 
         info!("response: {:?}", std::str::from_utf8(&serialized_response));
 
@@ -79,22 +99,106 @@ impl<'buf, 'conn> ClientHandler<'buf, 'conn> {
             .into_selector_result(SelectorErrorKind::TcpStreamError)?;
         Ok(())
     }
+
+    /// makes the database connection use the dataset generation that the data processor activated,
+    /// and drops the cached results if the data changed since they were cached.
+    fn follow_active_generation(&mut self) -> Result<()> {
+        let dataset_version = self.db_connection.fetch_dataset_version()?;
+        if *self.followed_generation != Some(dataset_version.active_generation) {
+            info!("using dataset generation {}", dataset_version.active_generation);
+            use_dataset_generation(&self.db_connection.0, dataset_version.active_generation, false)
+                .into_selector_result(SelectorErrorKind::DatabaseError)?;
+            *self.followed_generation = Some(dataset_version.active_generation);
+        }
+        self.cache.set_generation(dataset_version.generation);
+        Ok(())
+    }
+
+    /// returns the cached response of the request if there is one, and otherwise handles the
+    /// request and caches its response.
+    fn handle_request_using_cache(
+        &mut self,
+        request: &SelectorRequest,
+        raw_request: &Value,
+    ) -> Result<Vec<u8>> {
+        let key = match request.cache_key(raw_request, self.strategies)? {
+            Some(key) => key,
+            None => {
+                return request.handle_request_and_serialize_response(
+                    self.db_connection,
+                    self.strategies,
+                    self.cache,
+                )
+            }
+        };
+
+        if let Some(cached_response) = self.cache.get(&key) {
+            info!("using cached response");
+            return Ok(cached_response.to_vec());
+        }
+
+        let serialized_response = request.handle_request_and_serialize_response(
+            self.db_connection,
+            self.strategies,
+            self.cache,
+        )?;
+        self.cache.insert(key, serialized_response.clone());
+        Ok(serialized_response)
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", content = "parameters")]
 enum SelectorRequest {
     #[serde(rename = "selection")]
-    Selection {
-        #[serde(rename = "maxPrice")]
-        max_price: f32,
-        #[serde(rename = "categoryScores")]
-        category_scores: UserCategoryScoresByName,
-    },
+    Selection(SelectionParameters),
     #[serde(rename = "fetchCategoryNamesAndPriceLimits")]
     FetchCategoryNamesAndPriceLimits,
+    /// finds the best laptop in each price bracket
+    #[serde(rename = "bestPerPriceBracket")]
+    BestPerPriceBracket(PriceBracketsParameters),
+    /// finds how the best achievable score changes as the max price increases
+    #[serde(rename = "budgetCurve")]
+    BudgetCurve(BudgetCurveParameters),
+    /// finds the cheapest laptops that are a big enough upgrade over the user's current laptop
+    #[serde(rename = "upgradeRecommendations")]
+    UpgradeRecommendations(UpgradeParameters),
+    /// reports the usage statistics of the result cache
+    #[serde(rename = "cacheStatistics")]
+    CacheStatistics,
+    /// records what a user did with one of the selected laptops
+    #[serde(rename = "feedback")]
+    Feedback(FeedbackParameters),
 }
 impl SelectorRequest {
+    /// returns the key of the request's response in the result cache, or None if the response
+    /// should not be cached. the key includes the name of the strategy that handles the request,
+    /// rather than the client id that was used to choose it.
+    fn cache_key(&self, raw_request: &Value, strategies: &StrategyRegistry) -> Result<Option<String>> {
+        let strategy = match self {
+            SelectorRequest::Selection(parameters) => Some(strategies.resolve(
+                parameters.strategy.as_deref(),
+                parameters.client_id.as_deref(),
+            )?),
+            SelectorRequest::BestPerPriceBracket(parameters) => Some(strategies.resolve(
+                parameters.strategy.as_deref(),
+                parameters.client_id.as_deref(),
+            )?),
+            SelectorRequest::BudgetCurve(parameters) => Some(strategies.resolve(
+                parameters.strategy.as_deref(),
+                parameters.client_id.as_deref(),
+            )?),
+            SelectorRequest::UpgradeRecommendations(_)
+            | SelectorRequest::FetchCategoryNamesAndPriceLimits => None,
+            // the cache statistics change with every request, and feedback must always be recorded
+            SelectorRequest::CacheStatistics | SelectorRequest::Feedback(_) => return Ok(None),
+        };
+        Ok(Some(result_cache::normalized_request_key(
+            raw_request,
+            strategy.map(|strategy| strategy.name()),
+        )))
+    }
+
     /// handles a request, produces a response, and serializes it.
     /// note that it would seem more reasonable to just return a response here and
     /// serialize it somewhere else, but the serde Serialize trait is not object safe,
@@ -102,22 +206,73 @@ impl SelectorRequest {
     fn handle_request_and_serialize_response(
         &self,
         db_connection: &SelectorDBConnection,
+        strategies: &StrategyRegistry,
+        cache: &ResultCache,
     ) -> Result<Vec<u8>> {
         match self {
-            SelectorRequest::Selection {
-                max_price,
-                category_scores,
-            } => {
+            SelectorRequest::Selection(parameters) => {
+                let strategy = strategies.resolve(
+                    parameters.strategy.as_deref(),
+                    parameters.client_id.as_deref(),
+                )?;
+                info!("selecting using the {} strategy", strategy.name());
+
                 // perform the selection and measure the elapsed time
                 let start = Instant::now();
-                let selection_results = db_connection.select(category_scores, *max_price)?;
+                let selection_results = db_connection.select(strategy, parameters)?;
                 let elapsed = Instant::now() - start;
 
                 println!("selection elapsed time: {:?}", elapsed);
 
+                let stability_score = selection_results
+                    .first()
+                    .and_then(|best_laptop| best_laptop.stability)
+                    .map(|stability| stability.first_place_frequency);
                 serde_json::to_vec(&SelectorResponse {
                     success: true,
                     content: Some(selection_results),
+                    strategy: Some(strategy.name()),
+                    stability_score,
+                    error: None,
+                })
+            }
+            SelectorRequest::BestPerPriceBracket(parameters) => {
+                let strategy = strategies.resolve(
+                    parameters.strategy.as_deref(),
+                    parameters.client_id.as_deref(),
+                )?;
+                let best_per_price_bracket =
+                    db_connection.select_best_per_price_bracket(strategy, parameters)?;
+                serde_json::to_vec(&SelectorResponse {
+                    success: true,
+                    content: Some(best_per_price_bracket),
+                    strategy: Some(strategy.name()),
+                    stability_score: None,
+                    error: None,
+                })
+            }
+            SelectorRequest::BudgetCurve(parameters) => {
+                let strategy = strategies.resolve(
+                    parameters.strategy.as_deref(),
+                    parameters.client_id.as_deref(),
+                )?;
+                let budget_curve = db_connection.select_budget_curve(strategy, parameters)?;
+                serde_json::to_vec(&SelectorResponse {
+                    success: true,
+                    content: Some(budget_curve),
+                    strategy: Some(strategy.name()),
+                    stability_score: None,
+                    error: None,
+                })
+            }
+            SelectorRequest::UpgradeRecommendations(parameters) => {
+                let upgrade_recommendations = db_connection.recommend_upgrades(parameters)?;
+                serde_json::to_vec(&SelectorResponse {
+                    success: true,
+                    content: Some(upgrade_recommendations),
+                    strategy: None,
+                    stability_score: None,
+                    error: None,
                 })
             }
             SelectorRequest::FetchCategoryNamesAndPriceLimits => {
@@ -126,6 +281,26 @@ impl SelectorRequest {
                 serde_json::to_vec(&SelectorResponse {
                     success: true,
                     content: Some(category_names_and_price_limits),
+                    strategy: None,
+                    stability_score: None,
+                    error: None,
+                })
+            }
+            SelectorRequest::CacheStatistics => serde_json::to_vec(&SelectorResponse {
+                success: true,
+                content: Some(cache.statistics()),
+                strategy: None,
+                stability_score: None,
+                error: None,
+            }),
+            SelectorRequest::Feedback(parameters) => {
+                let recorded_feedback = db_connection.record_feedback(parameters)?;
+                serde_json::to_vec(&SelectorResponse {
+                    success: true,
+                    content: Some(recorded_feedback),
+                    strategy: None,
+                    stability_score: None,
+                    error: None,
                 })
             }
         }
@@ -137,10 +312,29 @@ impl SelectorRequest {
 struct SelectorResponse<T: Serialize> {
     success: bool,
     content: Option<T>,
+
+    /// the name of the strategy that produced the content, only present in selection responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strategy: Option<&'static str>,
+
+    /// the share of the perturbed rankings in which the best laptop stays the best, only present in
+    /// selection responses that requested a stability analysis
+    #[serde(rename = "stabilityScore", skip_serializing_if = "Option::is_none")]
+    stability_score: Option<f32>,
+
+    /// the kind of the error that failed the request, for example `UnknownCpu("...")`, only present
+    /// in failure responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
-pub fn start_server(db_connection: &SelectorDBConnection) -> Result<()> {
+pub fn start_server(
+    db_connection: &SelectorDBConnection,
+    strategies: &StrategyRegistry,
+    mut cache: ResultCache,
+) -> Result<()> {
     let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut followed_generation = None;
     let listener = TcpListener::bind(SERVER_ENDPOINT)
         .into_selector_result(SelectorErrorKind::FailedToCreateListener)?;
     for possible_stream in listener.incoming() {
@@ -155,6 +349,9 @@ pub fn start_server(db_connection: &SelectorDBConnection) -> Result<()> {
             stream,
             buffer: &mut buffer,
             db_connection,
+            strategies,
+            cache: &mut cache,
+            followed_generation: &mut followed_generation,
         };
 
         if let Err(e) = client_handler.handle_client() {
@@ -0,0 +1,386 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// the amount of significant digits that numbers in requests are rounded to when creating cache
+/// keys, so that requests that only differ by tiny floating point noise share the same results
+const CACHE_KEY_SIGNIFICANT_DIGITS: usize = 6;
+
+/// the largest integer below which every integer can be represented exactly as an f64
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// the request parameters that never affect the results, and are thus left out of cache keys.
+/// the client id only chooses the strategy, which is part of the key by itself.
+const IGNORED_PARAMETERS: &[&str] = &["clientId"];
+
+/// a least recently used cache of serialized responses, mapped by their normalized requests.
+///
+/// the cache only ever holds the results of a single dataset generation. whenever the data
+/// processor reloads the data, the generation changes, and all cached results are dropped.
+#[derive(Debug)]
+pub struct ResultCache {
+    max_entries: usize,
+    max_bytes: usize,
+    generation: Option<i64>,
+    entries: HashMap<String, CacheEntry>,
+    /// the keys of the entries, mapped by the time they were last used, such that the least
+    /// recently used entry is first
+    usage_order: BTreeMap<u64, String>,
+    /// a counter that is incremented whenever an entry is used
+    clock: u64,
+    bytes: usize,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    invalidations: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: Vec<u8>,
+    last_used: u64,
+}
+
+/// statistics about the usage of the result cache
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatistics {
+    pub hits: u64,
+    pub misses: u64,
+    /// the amount of entries that were removed to make room for new ones
+    pub evictions: u64,
+    /// the amount of times the cache was cleared because the data was reloaded
+    pub invalidations: u64,
+    pub entries: usize,
+    pub bytes: usize,
+    pub max_entries: usize,
+    pub max_bytes: usize,
+    /// the dataset generation of the cached results
+    pub generation: Option<i64>,
+}
+
+impl ResultCache {
+    /// creates an empty cache holding at most the given amount of entries, whose responses take
+    /// at most the given amount of bytes. a max entries amount of 0 disables the cache.
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            max_entries,
+            max_bytes,
+            generation: None,
+            entries: HashMap::new(),
+            usage_order: BTreeMap::new(),
+            clock: 0,
+            bytes: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+            invalidations: 0,
+        }
+    }
+
+    /// makes sure that the cache holds the results of the given dataset generation, dropping all
+    /// cached results if they belong to a different generation.
+    pub fn set_generation(&mut self, generation: i64) {
+        if self.generation == Some(generation) {
+            return;
+        }
+        if !self.entries.is_empty() {
+            self.invalidations += 1;
+        }
+        self.entries.clear();
+        self.usage_order.clear();
+        self.bytes = 0;
+        self.generation = Some(generation);
+    }
+
+    /// returns the cached response of the request with the given key, if there is one, and marks
+    /// it as the most recently used entry.
+    pub fn get(&mut self, key: &str) -> Option<&[u8]> {
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                self.hits += 1;
+                self.clock += 1;
+                let key = self
+                    .usage_order
+                    .remove(&entry.last_used)
+                    .expect("every cache entry must appear in the usage order");
+                self.usage_order.insert(self.clock, key);
+                entry.last_used = self.clock;
+                Some(&entry.response)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// caches the response of the request with the given key, evicting the least recently used
+    /// entries if the cache is full.
+    pub fn insert(&mut self, key: String, response: Vec<u8>) {
+        // a response that can't fit in the cache by itself would just evict everything else
+        if self.max_entries == 0 || response.len() > self.max_bytes {
+            return;
+        }
+        if let Some(old_entry) = self.entries.remove(&key) {
+            self.usage_order.remove(&old_entry.last_used);
+            self.bytes -= old_entry.response.len();
+        }
+        while self.entries.len() >= self.max_entries || self.bytes + response.len() > self.max_bytes {
+            self.evict_least_recently_used();
+        }
+
+        self.clock += 1;
+        self.bytes += response.len();
+        self.usage_order.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                response,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let least_recently_used = self.usage_order.keys().next().copied();
+        if let Some(last_used) = least_recently_used {
+            let key = self.usage_order.remove(&last_used).unwrap();
+            let entry = self.entries.remove(&key).unwrap();
+            self.bytes -= entry.response.len();
+            self.evictions += 1;
+        }
+    }
+
+    pub fn statistics(&self) -> CacheStatistics {
+        CacheStatistics {
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            invalidations: self.invalidations,
+            entries: self.entries.len(),
+            bytes: self.bytes,
+            max_entries: self.max_entries,
+            max_bytes: self.max_bytes,
+            generation: self.generation,
+        }
+    }
+}
+
+/// creates the cache key of a request from its raw json and the name of the strategy that handles
+/// it, if any. the key is a canonical form of the request, in which object keys are sorted, so that
+/// for example the order of the category scores doesn't matter, numbers are rounded, and parameters
+/// that don't affect the results are left out.
+pub fn normalized_request_key(raw_request: &Value, strategy_name: Option<&str>) -> String {
+    let mut key = String::new();
+    if let Some(strategy_name) = strategy_name {
+        key.push_str(strategy_name);
+    }
+    key.push('|');
+
+    let mut request = raw_request.clone();
+    if let Some(Value::Object(parameters)) = request.get_mut("parameters") {
+        for ignored_parameter in IGNORED_PARAMETERS {
+            parameters.remove(*ignored_parameter);
+        }
+    }
+    write_canonical_value(&request, &mut key);
+    key
+}
+
+fn write_canonical_value(value: &Value, key: &mut String) {
+    match value {
+        Value::Null => key.push_str("null"),
+        Value::Bool(value) => key.push_str(if *value { "true" } else { "false" }),
+        Value::Number(number) => match number.as_f64() {
+            // whole numbers are written as integers, so that for example `1` and `1.0` share the same
+            // key, and are never rounded, since they could be amounts
+            Some(float) if float.fract() == 0.0 && float.abs() < MAX_EXACT_INTEGER => {
+                key.push_str(&(float as i64).to_string())
+            }
+            Some(float) if float.is_finite() => key.push_str(&format!(
+                "{:.*e}",
+                CACHE_KEY_SIGNIFICANT_DIGITS - 1,
+                float
+            )),
+            _ => key.push_str(&number.to_string()),
+        },
+        Value::String(string) => key.push_str(&Value::String(string.clone()).to_string()),
+        Value::Array(values) => {
+            key.push('[');
+            for value in values {
+                write_canonical_value(value, key);
+                key.push(',');
+            }
+            key.push(']');
+        }
+        Value::Object(object) => {
+            let mut fields: Vec<(&String, &Value)> = object.iter().collect();
+            fields.sort_unstable_by_key(|(name, _)| *name);
+
+            key.push('{');
+            for (name, value) in fields {
+                key.push_str(&Value::String(name.clone()).to_string());
+                key.push(':');
+                write_canonical_value(value, key);
+                key.push(',');
+            }
+            key.push('}');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(size: usize) -> Vec<u8> {
+        vec![0; size]
+    }
+
+    fn cached_keys(cache: &ResultCache) -> Vec<&str> {
+        cache.usage_order.values().map(String::as_str).collect()
+    }
+
+    fn key(raw_request: &str) -> String {
+        normalized_request_key(&serde_json::from_str(raw_request).unwrap(), Some("default"))
+    }
+
+    #[test]
+    fn the_least_recently_used_entry_is_evicted() {
+        let mut cache = ResultCache::new(2, 1000);
+        cache.insert("a".to_string(), response(1));
+        cache.insert("b".to_string(), response(1));
+        // getting an entry makes it the most recently used one
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_string(), response(1));
+        assert_eq!(cached_keys(&cache), vec!["a", "c"]);
+        assert!(cache.get("b").is_none());
+
+        // inserting an existing key replaces its entry and makes it the most recently used one
+        cache.insert("a".to_string(), response(2));
+        cache.insert("d".to_string(), response(1));
+        assert_eq!(cached_keys(&cache), vec!["a", "d"]);
+        assert_eq!(cache.get("a").map(<[u8]>::len), Some(2));
+
+        let statistics = cache.statistics();
+        assert_eq!(statistics.evictions, 2);
+        assert_eq!(statistics.entries, 2);
+        assert_eq!(statistics.bytes, 3);
+    }
+
+    #[test]
+    fn entries_are_evicted_until_the_response_fits_in_the_max_bytes() {
+        let mut cache = ResultCache::new(10, 10);
+        cache.insert("a".to_string(), response(4));
+        cache.insert("b".to_string(), response(4));
+        cache.insert("c".to_string(), response(2));
+        assert_eq!(cache.statistics().bytes, 10);
+
+        cache.insert("d".to_string(), response(7));
+        assert_eq!(cached_keys(&cache), vec!["c", "d"]);
+        assert_eq!(cache.statistics().bytes, 9);
+        assert_eq!(cache.statistics().evictions, 2);
+    }
+
+    #[test]
+    fn a_response_larger_than_the_max_bytes_is_not_cached() {
+        let mut cache = ResultCache::new(10, 10);
+        cache.insert("a".to_string(), response(5));
+        cache.insert("b".to_string(), response(11));
+        assert_eq!(cached_keys(&cache), vec!["a"]);
+        assert_eq!(cache.statistics().evictions, 0);
+
+        // a response of exactly the max bytes still fits
+        cache.insert("c".to_string(), response(10));
+        assert_eq!(cached_keys(&cache), vec!["c"]);
+    }
+
+    #[test]
+    fn a_cache_with_no_entries_caches_nothing() {
+        let mut cache = ResultCache::new(0, 1000);
+        cache.insert("a".to_string(), response(1));
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.statistics().entries, 0);
+    }
+
+    #[test]
+    fn changing_the_generation_clears_the_cache_but_keeps_the_counters() {
+        let mut cache = ResultCache::new(10, 1000);
+        cache.set_generation(1);
+        cache.insert("a".to_string(), response(3));
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+
+        // setting the same generation keeps the entries
+        cache.set_generation(1);
+        assert!(cache.get("a").is_some());
+
+        cache.set_generation(2);
+        let statistics = cache.statistics();
+        assert_eq!(statistics.entries, 0);
+        assert_eq!(statistics.bytes, 0);
+        assert_eq!(statistics.generation, Some(2));
+        assert_eq!(statistics.invalidations, 1);
+        assert_eq!(statistics.hits, 2);
+        assert_eq!(statistics.misses, 1);
+
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.statistics().misses, 2);
+
+        // an empty cache isn't invalidated
+        cache.set_generation(3);
+        assert_eq!(cache.statistics().invalidations, 1);
+
+        // the cache keeps working after it is cleared
+        cache.insert("a".to_string(), response(3));
+        assert!(cache.get("a").is_some());
+        assert_eq!(cache.statistics().hits, 3);
+    }
+
+    #[test]
+    fn the_key_doesnt_depend_on_the_order_of_object_keys() {
+        assert_eq!(
+            key(r#"{"parameters": {"categoryScores": {"dev": 1, "gaming": 2}, "maxPrice": 5000}}"#),
+            key(r#"{"parameters": {"maxPrice": 5000, "categoryScores": {"gaming": 2, "dev": 1}}}"#)
+        );
+        // the order of arrays does matter
+        assert_ne!(key(r#"{"values": [1, 2]}"#), key(r#"{"values": [2, 1]}"#));
+    }
+
+    #[test]
+    fn numbers_are_rounded_in_the_key() {
+        assert_eq!(key(r#"{"score": 0.1}"#), key(r#"{"score": 0.10000000000000002}"#));
+        assert_eq!(key(r#"{"score": 0.3333333}"#), key(r#"{"score": 0.33333334}"#));
+        assert_ne!(key(r#"{"score": 0.33333}"#), key(r#"{"score": 0.33334}"#));
+        assert_eq!(key(r#"{"amount": 1}"#), key(r#"{"amount": 1.0}"#));
+        // whole numbers are never rounded
+        assert_ne!(key(r#"{"amount": 1234567}"#), key(r#"{"amount": 1234568}"#));
+    }
+
+    #[test]
+    fn the_client_id_is_left_out_of_the_key() {
+        assert_eq!(
+            key(r#"{"parameters": {"clientId": "first", "maxPrice": 5000}}"#),
+            key(r#"{"parameters": {"clientId": "second", "maxPrice": 5000}}"#)
+        );
+        assert_eq!(
+            key(r#"{"parameters": {"clientId": "first", "maxPrice": 5000}}"#),
+            key(r#"{"parameters": {"maxPrice": 5000}}"#)
+        );
+    }
+
+    #[test]
+    fn the_strategy_is_part_of_the_key() {
+        let request = serde_json::json!({"parameters": {"maxPrice": 5000}});
+        assert_ne!(
+            normalized_request_key(&request, Some("default")),
+            normalized_request_key(&request, Some("experimental"))
+        );
+        assert_ne!(
+            normalized_request_key(&request, Some("default")),
+            normalized_request_key(&request, None)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! fixtures for the tests that query the development database. each test gets a connection whose
+//! dataset tables are replaced by empty temporary tables, which shadow the tables of the public schema
+//! until the connection's test transaction is rolled back, so the tests never see or modify the
+//! actual dataset.
+
+use diesel::{Connection, RunQueryDsl};
+
+use crate::SelectorDBConnection;
+
+/// the tables that store the dataset, which are replaced by temporary tables
+const DATASET_TABLES: &[&str] = &[
+    "laptop",
+    "laptop_image",
+    "laptop_specs",
+    "global_benchmark",
+    "benchmark",
+    "category",
+    "benchmark_score_in_category",
+    "laptop_score_in_category",
+    "price_limits",
+    "exchange_rate",
+];
+
+/// a laptop of a test dataset
+pub struct FixtureLaptop<'a> {
+    pub id: i32,
+    pub name: &'a str,
+    /// the price in the base currency
+    pub price: f32,
+    pub store: &'a str,
+    pub brand: Option<&'a str>,
+    pub model: Option<&'a str>,
+    pub cpu: &'a str,
+    pub gpu: &'a str,
+    /// the score of the laptop in each category, along with its confidence, in the order of the
+    /// categories' ids
+    pub scores: &'a [(f32, f32)],
+}
+impl<'a> FixtureLaptop<'a> {
+    /// a laptop with the given price and fully confident scores, and unremarkable values for
+    /// everything else
+    pub fn new(id: i32, price: f32, scores: &'a [(f32, f32)]) -> Self {
+        Self {
+            id,
+            name: "Laptop",
+            price,
+            store: "ivory",
+            brand: None,
+            model: None,
+            cpu: "cpu",
+            gpu: "gpu",
+            scores,
+        }
+    }
+}
+
+/// connects to the development database, and creates an empty dataset whose base currency is ILS,
+/// which is worth a quarter of a USD, and which has a category with each of the given names. the
+/// ids of the categories are their positions in the given names, starting from 1.
+pub fn connect_to_test_dataset(category_names: &[&str]) -> SelectorDBConnection {
+    let db_connection = db_access::get_db_connection();
+    db_connection
+        .begin_test_transaction()
+        .expect("failed to begin the test transaction");
+    let db_connection = SelectorDBConnection(db_connection);
+    for table in DATASET_TABLES {
+        execute(
+            &db_connection,
+            &format!("CREATE TEMPORARY TABLE {0} (LIKE public.{0} INCLUDING ALL)", table),
+        );
+    }
+    execute(
+        &db_connection,
+        "INSERT INTO price_limits (id, max_price, min_price, currency) VALUES (0, 100000, 0, 'ILS')",
+    );
+    execute(
+        &db_connection,
+        "INSERT INTO exchange_rate (currency, rate) VALUES ('ILS', 1), ('USD', 4)",
+    );
+    for (index, category_name) in category_names.iter().enumerate() {
+        diesel::sql_query("INSERT INTO category (id, name) VALUES ($1, $2)")
+            .bind::<diesel::sql_types::Integer, _>(index as i32 + 1)
+            .bind::<diesel::sql_types::Text, _>(category_name)
+            .execute(&db_connection.0)
+            .expect("failed to insert a test category");
+    }
+    db_connection
+}
+
+/// inserts a laptop, along with its specs and its scores in categories, into the test dataset
+pub fn insert_laptop(db_connection: &SelectorDBConnection, laptop: &FixtureLaptop) {
+    use diesel::sql_types::{Float, Integer, Nullable, Text};
+
+    diesel::sql_query(
+        "INSERT INTO laptop (id, name, url, price, cpu, gpu, currency, listed_price, store, brand, model) \
+        VALUES ($1, $2, 'https://example.com', $3, $4, $5, 'ILS', $3, $6, $7, $8)",
+    )
+    .bind::<Integer, _>(laptop.id)
+    .bind::<Text, _>(laptop.name)
+    .bind::<Float, _>(laptop.price)
+    .bind::<Text, _>(laptop.cpu)
+    .bind::<Text, _>(laptop.gpu)
+    .bind::<Text, _>(laptop.store)
+    .bind::<Nullable<Text>, _>(laptop.brand)
+    .bind::<Nullable<Text>, _>(laptop.model)
+    .execute(&db_connection.0)
+    .expect("failed to insert a test laptop");
+    diesel::sql_query(
+        "INSERT INTO laptop_specs (laptop_id, ram_gigabytes, weight_grams, storage_gigabytes) \
+        VALUES ($1, 16, 1500, 512)",
+    )
+    .bind::<Integer, _>(laptop.id)
+    .execute(&db_connection.0)
+    .expect("failed to insert the specs of a test laptop");
+    for (index, &(score, confidence)) in laptop.scores.iter().enumerate() {
+        diesel::sql_query(
+            "INSERT INTO laptop_score_in_category (laptop_id, category_id, score, confidence) \
+            VALUES ($1, $2, $3, $4)",
+        )
+        .bind::<Integer, _>(laptop.id)
+        .bind::<Integer, _>(index as i32 + 1)
+        .bind::<Float, _>(score)
+        .bind::<Float, _>(confidence)
+        .execute(&db_connection.0)
+        .expect("failed to insert a score of a test laptop");
+    }
+}
+
+/// executes a statement that modifies the test dataset
+pub fn execute(db_connection: &SelectorDBConnection, statement: &str) {
+    diesel::sql_query(statement)
+        .execute(&db_connection.0)
+        .unwrap_or_else(|error| panic!("failed to execute {}: {}", statement, error));
+}
@@ -13,7 +13,62 @@ export type SelectionRequestParameters = {
 	maxPrice: number,
 	categoryScores: {
 		[category_name: string]: number,
-	}
+	},
+	strategy?: string,
+	clientId?: string,
+	backend?: "inProcess" | "database",
+	preferences?: SoftPreference[],
+	minConfidence?: number,
+	groupByModel?: boolean,
+	currency?: string,
+	exclude?: Exclusions,
+	stability?: StabilityParameters,
+}
+
+export type StabilityParameters = {
+	samples?: number,
+	perturbation?: number,
+}
+
+export type LaptopStability = {
+	topFrequency: number,
+	firstPlaceFrequency: number,
+}
+
+export type Exclusions = {
+	laptopIds?: number[],
+	namePatterns?: string[],
+	stores?: string[],
+	brands?: string[],
+}
+
+export type LaptopVariant = {
+	name: string,
+	url: string,
+	price: number,
+	score: number,
+	priceDifference: number,
+	ramGigabytesDifference: number,
+	weightGramsDifference: number,
+	storageGigabytesDifference: number | null,
+	cpu?: string,
+	gpu?: string,
+}
+
+export type PreferenceSpec = "weight" | "ram" | "price" | "storage"
+
+export type SoftPreference = {
+	spec: PreferenceSpec,
+	target: number,
+	direction: "atMost" | "atLeast",
+	strength: number,
+}
+
+export type PreferenceAdjustment = {
+	spec: PreferenceSpec,
+	value: number,
+	target: number,
+	adjustment: number,
 }
 
 export type SelectorRequest =
@@ -24,32 +79,64 @@ export type SelectorRequest =
 	| {
 		type: "fetchCategoryNamesAndPriceLimits",
 	}
+	| {
+		type: "feedback",
+		parameters: FeedbackRequestParameters
+	}
+
+export type FeedbackAction = "clicked" | "liked" | "dismissed"
+
+export type FeedbackRequestParameters = {
+	laptopId: number,
+	action: FeedbackAction,
+	selection: SelectionRequestParameters,
+}
+
+type RecordedFeedback = {
+	feedbackId: number,
+}
 
 
 type CategoryScoreMap = {[category: string]: number}
 
 export type SelectedLaptop = {
+	id: number,
 	name: string,
 	url: string,
 	cpu: string,
 	gpu: string,
 	price: number,
+	currency: string,
+	listedPrice: number,
+	listedCurrency: string,
+	store: string,
 	score: number,
 	scoresInCategories: CategoryScoreMap,
 	imageUrls?: string[],
 	ramGigabytes: number,
 	weightGrams: number,
+	storageGigabytes: number | null,
+	confidence: number,
+	confidenceInCategories: CategoryScoreMap,
+	brand: string | null,
+	model: string | null,
+	variants?: LaptopVariant[],
+	preferenceAdjustments?: PreferenceAdjustment[],
+	stability?: LaptopStability,
 }
 
 type SelectorResponse<T> = {
 	success: boolean,
 	content: T | null,
+	strategy?: string,
+	stabilityScore?: number,
 }
 
 type CategoryNamesAndPriceLimits = {
 	categoryNames: string[],
 	maxPrice: number,
 	minPrice: number,
+	currency: string,
 }
 
 export type PriceLimits = {
@@ -255,6 +342,16 @@ export async function select(requestParameters: SelectionRequestParameters): Pro
 	});
 }
 
+// records what the user did with a laptop that was returned by the given selection, and returns
+// the id of the stored feedback
+export async function sendFeedback(requestParameters: FeedbackRequestParameters): Promise<number> {
+	let recordedFeedback: RecordedFeedback = await sendRequestaAndGetResponseContent({
+		type: "feedback",
+		parameters: requestParameters
+	});
+	return recordedFeedback.feedbackId;
+}
+
 // fetches the category names and price limits from the selector and caches them in the
 // global variable `categoryNamesAndPriceLimits`. the cached data can be accessed
 // using the `getCategoryNames` and `getPriceLimits` functions.